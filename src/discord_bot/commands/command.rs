@@ -1,23 +1,122 @@
 use serenity::{
-    all::{AutocompleteOption, CommandInteraction, ComponentInteraction, ModalInteraction},
+    all::{
+        AutocompleteOption, CommandInteraction, ComponentInteraction, InteractionContext,
+        ModalInteraction,
+    },
     async_trait,
-    builder::{CreateAutocompleteResponse, CreateCommand},
-    model::{application::CommandType, Permissions},
+    builder::{CreateAutocompleteResponse, CreateCommand, CreateCommandOption},
+    model::{
+        application::{CommandOptionType, CommandType},
+        id::{GuildId, UserId},
+        Permissions,
+    },
     prelude::Context,
 };
 
 use crate::{
     discord_bot::commands::{
-        hide::HideCommand, ping::PingCommand, say::SayCommand, time::TimeCommand
+        config::ConfigCommand, convert::ConvertCommand, convert_message::ConvertMessageCommand,
+        countdown::CountdownCommand, dst::DstCommand, hide::HideCommand, hours::HoursCommand,
+        local_time::LocalTimeCommand, meeting::MeetingCommand, ping::PingCommand,
+        remind::RemindCommand, say::SayCommand, time::TimeCommand, timestamp::TimestampCommand,
+        timezone::TimezoneCommand, worldclock::WorldClockCommand,
     },
     state::AppState,
+    store::Feature,
+};
+
+use super::{
+    options::{Options, Subcommand},
+    pagination::Pages,
+    util::{CommandResponse, FailureMessageKind},
 };
 
-use super::util::CommandResponse;
+/// reads a command of type `T` from the options provided to one of its subcommands
+pub type SubcommandParser<'a, T> = fn(Options<'a>) -> Result<T, String>;
+
+/// A subcommand of a [Command], e.g. `set` in `/timezone set`, along with how to read the command
+/// when it is used
+pub struct SubcommandSpec<'a, T> {
+    /// the name of the subcommand
+    name: &'static str,
+    /// the description of the subcommand
+    description: &'static str,
+    /// the options of the subcommand, in order
+    options: Vec<CreateCommandOption>,
+    /// reads the command from the options provided to the subcommand
+    parse: SubcommandParser<'a, T>,
+}
+
+impl<'a, T> SubcommandSpec<'a, T> {
+    /// create a subcommand with no options, read into its command by `parse` when it is used
+    pub fn new(
+        name: &'static str,
+        description: &'static str,
+        parse: SubcommandParser<'a, T>,
+    ) -> Self {
+        Self {
+            name,
+            description,
+            options: Vec::new(),
+            parse,
+        }
+    }
+
+    /// add the options of the subcommand, declared by a [SlashCommand](super::options::SlashCommand)
+    pub fn options(mut self, options: Vec<CreateCommandOption>) -> Self {
+        self.options.extend(options);
+        self
+    }
+
+    /// the subcommand in the form sent to discord
+    fn build(self) -> CreateCommandOption {
+        self.options.into_iter().fold(
+            CreateCommandOption::new(CommandOptionType::SubCommand, self.name, self.description),
+            CreateCommandOption::add_sub_option,
+        )
+    }
+}
+
+/// A group of subcommands of a [Command], e.g. `roles` in `/config roles add`
+pub struct SubcommandGroupSpec<'a, T> {
+    /// the name of the group
+    name: &'static str,
+    /// the description of the group
+    description: &'static str,
+    /// the subcommands in the group, in order
+    subcommands: Vec<SubcommandSpec<'a, T>>,
+}
+
+impl<'a, T> SubcommandGroupSpec<'a, T> {
+    /// create a group with no subcommands
+    pub fn new(name: &'static str, description: &'static str) -> Self {
+        Self {
+            name,
+            description,
+            subcommands: Vec::new(),
+        }
+    }
+
+    /// add a subcommand to the group
+    pub fn subcommand(mut self, subcommand: SubcommandSpec<'a, T>) -> Self {
+        self.subcommands.push(subcommand);
+        self
+    }
 
-const DEFAULT_PERMISSIONS: Permissions = Permissions::ADMINISTRATOR;
+    /// the group in the form sent to discord
+    fn build(self) -> CreateCommandOption {
+        self.subcommands.into_iter().fold(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommandGroup,
+                self.name,
+                self.description,
+            ),
+            |group, subcommand| group.add_sub_option(subcommand.build()),
+        )
+    }
+}
 
-/// A command that can be used in a guild, restricted to administrators
+/// A command that can be used in a guild, and optionally in direct messages
 #[async_trait]
 pub trait Command<'a>: TryFrom<&'a CommandInteraction> {
     /// Get the name of the command
@@ -27,7 +126,44 @@ pub trait Command<'a>: TryFrom<&'a CommandInteraction> {
     fn description() -> &'static str;
 
     /// Get the discord defined usage of this command, to be sent to discord
-    fn get_application_command_options(command: CreateCommand) -> CreateCommand;
+    fn get_application_command_options(command: CreateCommand) -> CreateCommand {
+        command
+    }
+
+    /// the subcommands of this command, registered with discord after any options from
+    /// [Command::get_application_command_options]. a command with subcommands is read by the
+    /// parser of the subcommand used, rather than through `TryFrom`
+    fn subcommands() -> Vec<SubcommandSpec<'a, Self>> {
+        Vec::new()
+    }
+
+    /// the groups of subcommands of this command, registered with discord after its subcommands
+    fn subcommand_groups() -> Vec<SubcommandGroupSpec<'a, Self>> {
+        Vec::new()
+    }
+
+    /// the feature this command belongs to, if it can be turned off with `/config feature`
+    fn feature() -> Option<Feature> {
+        None
+    }
+
+    /// the permissions a member needs to use this command unless a guild grants it otherwise
+    /// in its integration settings, everyone can use the command if there are none
+    fn default_permissions() -> Option<Permissions> {
+        None
+    }
+
+    /// how the command is used, either typed as a slash command or picked from the context menu
+    /// of a message or user. context menu commands have no description or options
+    fn kind() -> CommandType {
+        CommandType::ChatInput
+    }
+
+    /// whether this command is personal, so can be used in direct messages with the bot as well as
+    /// in guilds. such commands are registered globally rather than in each guild
+    fn dm_permission() -> bool {
+        false
+    }
 
     /// handle the execution of this application command
     async fn handle_application_command<'b>(
@@ -85,36 +221,129 @@ pub trait ModalSubmit<'a>: Command<'a> + InteractionCommand<'a> {
     ) -> Result<CommandResponse, CommandResponse>;
 }
 
-// #[async_trait]
-// pub trait PaginatedResponse<'a>: Command<'a> {
-//     /// Get the number of pages this response has
-//     fn get_page_count(&self) -> usize;
-
-//     /// Get a specific page of this response
-//     fn get_page(&self, page: usize) -> CommandResponse<'a>;
-// }
+/// A command which responds with a list shown a page at a time. The list is rebuilt whenever a
+/// page is turned, so its [InteractionCommand] can hand the clicks to [turn_page](super::pagination::turn_page)
+#[async_trait]
+pub trait PaginatedResponse<'a>: Command<'a> + InteractionCommand<'a> {
+    /// build the list shown to a user, in the guild it was requested in
+    async fn get_pages<'b>(
+        user: UserId,
+        guild_id: Option<GuildId>,
+        app_state: &'b AppState,
+        context: &'b Context,
+    ) -> Result<Pages, CommandResponse>;
+}
 
 /// match against a list of provided command types, and generate an application command that can be registered with discord
+/// for each of those which can, or can't, be used in direct messages
 macro_rules! application_command {
-    ( $base:expr, $( $x:ty ),* $(,)? ) => {
+    ( $base:expr, $dm:expr, $( $x:ty ),* $(,)? ) => {
         {
             /// ensures that the provided type has relevant traits
             fn assert_command<'a, T: Command<'a, Error=String>>() {}
             $(
                 assert_command::<$x>();
-                let mut v_base = <$x>::get_application_command_options(CreateCommand::new("unnamed command"));
-                v_base = v_base
-                    .name(<$x>::name())
-                    .description(<$x>::description())
-                    .default_member_permissions(DEFAULT_PERMISSIONS)
-                    .dm_permission(false)
-                    .kind(CommandType::ChatInput);
-                $base.push(v_base);
+                if <$x>::dm_permission() == $dm {
+                    let mut v_base = <$x>::get_application_command_options(CreateCommand::new("unnamed command"));
+                    for subcommand in <$x>::subcommands() {
+                        v_base = v_base.add_option(subcommand.build());
+                    }
+                    for group in <$x>::subcommand_groups() {
+                        v_base = v_base.add_option(group.build());
+                    }
+                    v_base = v_base
+                        .name(<$x>::name())
+                        .contexts(contexts(<$x>::dm_permission()))
+                        .kind(<$x>::kind());
+                    // discord rejects context menu commands which have a description
+                    if <$x>::kind() == CommandType::ChatInput {
+                        v_base = v_base.description(<$x>::description());
+                    }
+                    if let Some(permissions) = <$x>::default_permissions() {
+                        v_base = v_base.default_member_permissions(permissions);
+                    }
+                    $base.push(v_base);
+                }
             )*
         }
     };
 }
 
+/// check that a command can be used where it was, i.e. that it can be used in direct messages if it
+/// was used in one, that its feature is turned on in the guild and that the member using it has a
+/// role it is restricted to
+async fn check_access(
+    command: &CommandInteraction,
+    app_state: &AppState,
+    name: &str,
+    feature: Option<Feature>,
+    dm_permission: bool,
+) -> Result<(), CommandResponse> {
+    let Some(guild_id) = command.guild_id else {
+        return match dm_permission {
+            true => Ok(()),
+            false => Err(CommandResponse::BasicFailure(format!(
+                "`/{}` can only be used in a server",
+                name
+            ))),
+        };
+    };
+    let config = app_state.guild_config(guild_id.into()).await;
+
+    if let Some(feature) = feature.filter(|f| !config.enabled(*f)) {
+        return Err(CommandResponse::BasicFailure(format!(
+            "{} is turned off in this server, an administrator can turn it on with `/config feature`",
+            feature
+        )));
+    }
+
+    let member = command.member.as_deref();
+    let roles = member
+        .map(|m| m.roles.iter().map(|r| r.get()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    let administrator = member
+        .and_then(|m| m.permissions)
+        .is_some_and(|p| p.administrator());
+    if !config.allows(name, &roles, administrator) {
+        return Err(CommandResponse::BasicFailure(format!(
+            "`/{}` is restricted to certain roles in this server, and you don't have any of them",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// whether a command is split into subcommands, so is read by [parse_subcommand]
+fn has_subcommands<'a, T: Command<'a>>() -> bool {
+    !T::subcommands().is_empty() || !T::subcommand_groups().is_empty()
+}
+
+/// read a command which is split into subcommands, using the parser declared for the subcommand
+/// which was used
+pub fn parse_subcommand<'a, T: Command<'a>>(options: Options<'a>) -> Result<T, String> {
+    let used = Subcommand::find(options)?;
+    let declared = match used.group {
+        Some(group) => T::subcommand_groups()
+            .into_iter()
+            .find(|g| g.name == group)
+            .and_then(|g| g.subcommands.into_iter().find(|s| s.name == used.name)),
+        None => T::subcommands().into_iter().find(|s| s.name == used.name),
+    };
+    match declared {
+        Some(subcommand) => (subcommand.parse)(used.options),
+        None => Err(used.unknown()),
+    }
+}
+
+/// the response when the options provided to a command couldn't be understood
+fn parse_failure(name: &str, error: String) -> CommandResponse {
+    CommandResponse::ComplexFailure {
+        log_message: format!("failed to parse /{}: {}", name, error),
+        response: error,
+        kind: FailureMessageKind::Warn,
+    }
+}
+
 /// match against a list of provided command types, and produce a response which can be sent to the user
 macro_rules! command {
     ( $cmd:expr, $state:expr, $context:expr, $( $x:ty ),* $(,)? ) => {
@@ -124,8 +353,14 @@ macro_rules! command {
             $(
                 assert_command::<$x>();
                 if ($cmd).data.name == <$x>::name() {
-                    if let Ok(value) = <$x>::try_from($cmd) {
-                        return value.handle_application_command($cmd, $state, $context).await
+                    check_access($cmd, $state, <$x>::name(), <$x>::feature(), <$x>::dm_permission()).await?;
+                    let parsed = match has_subcommands::<$x>() {
+                        true => parse_subcommand::<$x>(Options::of($cmd)),
+                        false => <$x>::try_from($cmd),
+                    };
+                    return match parsed {
+                        Ok(value) => value.handle_application_command($cmd, $state, $context).await,
+                        Err(e) => Err(parse_failure(<$x>::name(), e)),
                     }
                 }
             )*
@@ -134,25 +369,36 @@ macro_rules! command {
     };
 }
 
+/// list the names of the provided command types
+macro_rules! command_names {
+    ( $( $x:ty ),* $(,)? ) => {
+        {
+            /// ensures that the provided type has relevant traits
+            fn assert_command<'a, T: Command<'a, Error=String>>() {}
+            vec![$( { assert_command::<$x>(); <$x>::name() } ),*]
+        }
+    };
+}
+
 /// match against a list of provided autocomplete command types, and produce a response which can be sent to the user
-// macro_rules! autocomplete {
-//     ( $cmd:expr, $state:expr, $context:expr, $( $x:ty ),* $(,)? ) => {
-//         {
-//             /// ensures that the provided type has relevant traits
-//             fn assert_autocomplete<'a, T: AutocompleteCommand<'a, Error=String>>() {}
-//             $(
-//                 assert_autocomplete::<$x>();
-//                 if ($cmd).data.name == <$x>::name() {
-//                     return match $cmd.data.autocomplete() {
-//                         Some(data) => <$x>::autocomplete($cmd, &data, $state, $context).await,
-//                         None => Err(CommandResponse::InternalFailure(String::from("No Autocomplete Data Provided")))
-//                     }
-//                 }
-//             )*
-//             Err(CommandResponse::InternalFailure(String::from("Unsupported Autocomplete Command")))
-//         }
-//     };
-// }
+macro_rules! autocomplete {
+    ( $cmd:expr, $state:expr, $context:expr, $( $x:ty ),* $(,)? ) => {
+        {
+            /// ensures that the provided type has relevant traits
+            fn assert_autocomplete<'a, T: AutocompleteCommand<'a, Error=String>>() {}
+            $(
+                assert_autocomplete::<$x>();
+                if ($cmd).data.name == <$x>::name() {
+                    return match $cmd.data.autocomplete() {
+                        Some(data) => <$x>::autocomplete($cmd, &data, $state, $context).await,
+                        None => Err(CommandResponse::InternalFailure(String::from("No Autocomplete Data Provided")))
+                    }
+                }
+            )*
+            Err(CommandResponse::InternalFailure(String::from("Unsupported Autocomplete Command")))
+        }
+    };
+}
 
 /// match against a list of provided interaction command types, and produce a response which can be sent to the user
 macro_rules! interaction {
@@ -188,18 +434,77 @@ macro_rules! modal {
     };
 }
 
+/// the commands which can only be used in guilds, registered in each guild the bot is in
 pub fn application_command() -> Vec<CreateCommand> {
+    commands(false)
+}
+
+/// the personal commands, registered globally so that they can be used in direct messages too
+pub fn global_application_command() -> Vec<CreateCommand> {
+    commands(true)
+}
+
+/// where a command can be used. those which can be used in direct messages can also be used in other
+/// private channels, which only a user's own install of the bot can reach
+fn contexts(dm: bool) -> Vec<InteractionContext> {
+    match dm {
+        true => vec![
+            InteractionContext::Guild,
+            InteractionContext::BotDm,
+            InteractionContext::PrivateChannel,
+        ],
+        false => vec![InteractionContext::Guild],
+    }
+}
+
+/// the commands which can, or can't, be used in direct messages
+fn commands(dm: bool) -> Vec<CreateCommand> {
     let mut base = vec![];
     application_command!(
         &mut base,
+        dm,
+        ConfigCommand,
+        ConvertCommand,
+        ConvertMessageCommand,
+        CountdownCommand,
+        DstCommand,
         HideCommand,
+        HoursCommand,
+        LocalTimeCommand,
+        MeetingCommand,
         PingCommand,
+        RemindCommand,
         SayCommand,
-        TimeCommand
+        TimeCommand,
+        TimestampCommand,
+        TimezoneCommand,
+        WorldClockCommand,
     );
     base
 }
 
+/// the names of every command, so they can be referred to in the bot's configuration
+pub fn command_names() -> Vec<&'static str> {
+    command_names!(
+        ConfigCommand,
+        ConvertCommand,
+        ConvertMessageCommand,
+        CountdownCommand,
+        DstCommand,
+        HideCommand,
+        HoursCommand,
+        LocalTimeCommand,
+        MeetingCommand,
+        PingCommand,
+        RemindCommand,
+        SayCommand,
+        TimeCommand,
+        TimestampCommand,
+        TimezoneCommand,
+        WorldClockCommand,
+    )
+}
+
 pub async fn command<'a>(
     command: &'a CommandInteraction,
     app_state: &'a AppState,
@@ -209,37 +514,65 @@ pub async fn command<'a>(
         command,
         app_state,
         context,
+        ConfigCommand,
+        ConvertCommand,
+        ConvertMessageCommand,
+        CountdownCommand,
+        DstCommand,
         HideCommand,
+        HoursCommand,
+        LocalTimeCommand,
+        MeetingCommand,
         PingCommand,
+        RemindCommand,
         SayCommand,
-        TimeCommand
+        TimeCommand,
+        TimestampCommand,
+        TimezoneCommand,
+        WorldClockCommand,
     )
 }
 
-#[allow(dead_code, unused_variables)]
 pub async fn autocomplete<'a>(
     command: &'a CommandInteraction,
     app_state: &'a AppState,
     context: &'a Context,
 ) -> Result<CreateAutocompleteResponse, CommandResponse> {
-    // autocomplete!(command, app_state, context)
-    Err(CommandResponse::InternalFailure(String::from(
-        "Unsupported Autocomplete Command",
-    )))
+    autocomplete!(
+        command,
+        app_state,
+        context,
+        ConfigCommand,
+        ConvertCommand,
+        CountdownCommand,
+        RemindCommand,
+        TimeCommand,
+        TimestampCommand,
+        TimezoneCommand,
+        WorldClockCommand
+    )
 }
 
 pub async fn interaction<'a>(
-    _command: &'a ComponentInteraction,
-    _app_state: &'a AppState,
-    _context: &'a Context,
+    command: &'a ComponentInteraction,
+    app_state: &'a AppState,
+    context: &'a Context,
 ) -> Result<CommandResponse, CommandResponse> {
-    interaction!(command, app_state, context, )
+    interaction!(
+        command,
+        app_state,
+        context,
+        CountdownCommand,
+        HoursCommand,
+        RemindCommand,
+        TimezoneCommand
+    )
 }
 
 pub async fn handle_modal<'a>(
-    _modal: &'a ModalInteraction,
-    _app_state: &'a AppState,
-    _context: &'a Context,
+    modal: &'a ModalInteraction,
+    app_state: &'a AppState,
+    context: &'a Context,
 ) -> Result<CommandResponse, CommandResponse> {
-    modal!(modal, app_state, context, )
+    modal!(modal, app_state, context, HoursCommand)
 }
@@ -0,0 +1,591 @@
+use chrono::Utc;
+use serenity::{
+    all::{AutocompleteOption, CommandInteraction},
+    async_trait,
+    builder::CreateAutocompleteResponse,
+    model::{
+        id::{ChannelId, RoleId},
+        Permissions,
+    },
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::dst::notice,
+    interaction::{Embed, Failure, Invocation, Reply, Respond, Transport},
+    state::AppState,
+    store::{ClockFormat, Feature, GuildConfig, Locale},
+};
+
+use super::{
+    command::{
+        command_names, parse_subcommand, AutocompleteCommand, Command, SubcommandGroupSpec,
+        SubcommandSpec,
+    },
+    options::{Options, SlashCommand},
+    transport::{respond, Discord},
+    util::{
+        resolve_timezone_option, timezone_autocomplete, CommandResponse, MAX_AUTOCOMPLETE_CHOICES,
+    },
+};
+
+/// the most auto-convert channels listed in the summary, before the rest are counted instead
+const MAX_LISTED_CHANNELS: usize = 10;
+
+/// view and change the configuration of a guild
+pub enum ConfigCommand<'a> {
+    /// show a summary of the guild's configuration
+    Show,
+    /// set the timezone used for members who have not registered one, or clear it if none is provided
+    Timezone(Option<&'a str>),
+    /// set how times are written
+    Clock(ClockFormat),
+    /// set how dates are read and written
+    Locale(Locale),
+    /// turn a feature on or off
+    Feature(Feature, bool),
+    /// set the channel announcements are posted in, or stop announcing if none is provided
+    Announcements(Option<ChannelId>),
+    /// turn automatic time conversion on or off in a channel, defaulting to the current channel
+    AutoConvert(bool, Option<ChannelId>),
+    /// change which roles may use a command
+    Roles(&'a str, RoleChange),
+}
+
+/// the options of `/config timezone`
+#[derive(SlashCommand)]
+pub struct TimezoneOptions<'a> {
+    /// the default timezone, or none to remove it
+    #[option(
+        description = "The timezone, city or country, leave empty to remove the default",
+        autocomplete
+    )]
+    timezone: Option<&'a str>,
+}
+
+/// the options of `/config clock`
+#[derive(SlashCommand)]
+pub struct ClockOptions<'a> {
+    /// "12" or "24"
+    #[option(
+        description = "The clock to show times with",
+        choice(name = ClockFormat::TwelveHour.to_string(), value = "12"),
+        choice(name = ClockFormat::TwentyFourHour.to_string(), value = "24")
+    )]
+    format: &'a str,
+}
+
+/// the options of `/config locale`
+#[derive(SlashCommand)]
+pub struct LocaleOptions<'a> {
+    /// the code of the locale, e.g. "en-GB"
+    #[option(description = "The locale to read dates in", choices = locale_choices())]
+    locale: &'a str,
+}
+
+/// the options of `/config feature`
+#[derive(SlashCommand)]
+pub struct FeatureOptions<'a> {
+    /// the id of the feature
+    #[option(
+        description = "The feature to turn on or off",
+        choices = Feature::ALL.iter().map(|f| (f.to_string(), f.id()))
+    )]
+    feature: &'a str,
+    /// whether to turn the feature on
+    #[option(description = "Whether the feature should be on")]
+    enabled: bool,
+}
+
+/// the options of `/config announcements`
+#[derive(SlashCommand)]
+pub struct AnnouncementsOptions {
+    /// the channel to announce in, or none to stop announcing
+    #[option(
+        description = "The channel to post in, leave empty to stop announcing",
+        channel_types(Text)
+    )]
+    channel: Option<ChannelId>,
+}
+
+/// the options of `/config autoconvert`
+#[derive(SlashCommand)]
+pub struct AutoConvertOptions {
+    /// whether to convert times
+    #[option(description = "Whether times should be converted")]
+    enabled: bool,
+    /// the channel to configure, or none for the current channel
+    #[option(
+        description = "The channel to configure, defaults to this channel",
+        channel_types(Text)
+    )]
+    channel: Option<ChannelId>,
+}
+
+/// the options of `/config roles add` and `/config roles remove`
+#[derive(SlashCommand)]
+pub struct RoleOptions<'a> {
+    /// the name of the command to change the roles of
+    #[option(description = "The command", autocomplete)]
+    command: &'a str,
+    /// the role to allow or disallow
+    #[option(description = "The role")]
+    role: RoleId,
+}
+
+/// the options of `/config roles clear`
+#[derive(SlashCommand)]
+pub struct RolesClearOptions<'a> {
+    /// the name of the command to lift the restrictions on
+    #[option(description = "The command", autocomplete)]
+    command: &'a str,
+}
+
+/// the locales dates can be read in, named with an example of how they are read
+fn locale_choices() -> impl Iterator<Item = (String, &'static str)> {
+    Locale::ALL.iter().map(|locale| {
+        let example = match locale.month_first() {
+            true => "month/day",
+            false => "day/month",
+        };
+        (format!("{} ({})", locale.code(), example), locale.code())
+    })
+}
+
+/// A change to the roles which may use a command
+pub enum RoleChange {
+    /// allow members with the role to use the command, restricting it if it wasn't already
+    Add(RoleId),
+    /// stop allowing members with the role to use the command
+    Remove(RoleId),
+    /// lift every restriction on the command
+    Clear,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for ConfigCommand<'a> {
+    type Error = String;
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        Self::parse(Discord::invocation(interaction).options)
+    }
+}
+
+/// summarise a guild's configuration in an embed
+fn render_config(config: &GuildConfig) -> Embed {
+    let timezone = match config.default_timezone {
+        Some(tz) => format!(
+            "{} (currently {})",
+            tz.name(),
+            Utc::now()
+                .with_timezone(&tz)
+                .format(config.clock.time_format())
+        ),
+        None => String::from("None, members need to register their own"),
+    };
+
+    let features = Feature::ALL
+        .iter()
+        .map(|f| format!("{} {}", if config.enabled(*f) { "✅" } else { "❌" }, f))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let announcements = match config.announcement_channel {
+        Some(channel) => format!("<#{}>", channel),
+        None => String::from("Off"),
+    };
+
+    let mut channels = config
+        .auto_convert_channels
+        .iter()
+        .take(MAX_LISTED_CHANNELS)
+        .map(|c| format!("<#{}>", c))
+        .collect::<Vec<_>>();
+    if config.auto_convert_channels.len() > MAX_LISTED_CHANNELS {
+        channels.push(format!(
+            "and {} more",
+            config.auto_convert_channels.len() - MAX_LISTED_CHANNELS
+        ));
+    }
+    let auto_convert = match channels.is_empty() {
+        true => String::from("No channels"),
+        false => channels.join(", "),
+    };
+
+    let restrictions = match config.command_roles.is_empty() {
+        true => String::from("Every command can be used by anyone allowed to by discord"),
+        false => config
+            .command_roles
+            .iter()
+            .map(|(command, roles)| {
+                format!(
+                    "`/{}`: {}",
+                    command,
+                    roles
+                        .iter()
+                        .map(|r| format!("<@&{}>", r))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+
+    Embed::new("⚙️ Server configuration")
+        .field("Default timezone", timezone, false)
+        .field("Clock", config.clock.to_string(), true)
+        .field("Locale", config.locale.code(), true)
+        .field("Announcements", announcements, true)
+        .field("Features", features, false)
+        .field("Automatic time conversion", auto_convert, false)
+        .field("Restricted commands", restrictions, false)
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ConfigCommand<'a> {
+    fn name() -> &'static str {
+        "config"
+    }
+
+    fn description() -> &'static str {
+        "View and change how the bot behaves in this server"
+    }
+
+    fn default_permissions() -> Option<Permissions> {
+        Some(Permissions::MANAGE_GUILD)
+    }
+
+    fn subcommands() -> Vec<SubcommandSpec<'a, Self>> {
+        vec![
+            SubcommandSpec::new("show", "Show this server's configuration", |_| {
+                Ok(Self::Show)
+            }),
+            SubcommandSpec::new(
+                "timezone",
+                "Set the timezone used for members who haven't registered one",
+                |options| Ok(Self::Timezone(TimezoneOptions::parse(options)?.timezone)),
+            )
+            .options(TimezoneOptions::options()),
+            SubcommandSpec::new(
+                "clock",
+                "Set whether times are shown with a 12 or 24 hour clock",
+                |options| match ClockOptions::parse(options)?.format {
+                    "12" => Ok(Self::Clock(ClockFormat::TwelveHour)),
+                    "24" => Ok(Self::Clock(ClockFormat::TwentyFourHour)),
+                    format => Err(format!("Unknown clock format {}", format)),
+                },
+            )
+            .options(ClockOptions::options()),
+            SubcommandSpec::new("locale", "Set how dates such as 3/11 are read", |options| {
+                let code = LocaleOptions::parse(options)?.locale;
+                Ok(Self::Locale(
+                    Locale::from_code(code).ok_or_else(|| format!("Unknown locale {}", code))?,
+                ))
+            })
+            .options(LocaleOptions::options()),
+            SubcommandSpec::new(
+                "feature",
+                "Turn a feature on or off in this server",
+                |options| {
+                    let FeatureOptions { feature, enabled } = FeatureOptions::parse(options)?;
+                    Ok(Self::Feature(
+                        Feature::from_id(feature)
+                            .ok_or_else(|| format!("Unknown feature {}", feature))?,
+                        enabled,
+                    ))
+                },
+            )
+            .options(FeatureOptions::options()),
+            SubcommandSpec::new(
+                "announcements",
+                "Set the channel announcements such as daylight savings changes are posted in",
+                |options| {
+                    Ok(Self::Announcements(
+                        AnnouncementsOptions::parse(options)?.channel,
+                    ))
+                },
+            )
+            .options(AnnouncementsOptions::options()),
+            SubcommandSpec::new(
+                "autoconvert",
+                "Turn automatic conversion of times mentioned in messages on or off in a channel",
+                |options| {
+                    let AutoConvertOptions { enabled, channel } =
+                        AutoConvertOptions::parse(options)?;
+                    Ok(Self::AutoConvert(enabled, channel))
+                },
+            )
+            .options(AutoConvertOptions::options()),
+        ]
+    }
+
+    fn subcommand_groups() -> Vec<SubcommandGroupSpec<'a, Self>> {
+        vec![SubcommandGroupSpec::new(
+            "roles",
+            "Restrict commands to certain roles, administrators can always use every command",
+        )
+        .subcommand(
+            SubcommandSpec::new(
+                "add",
+                "Allow a role to use a command, restricting it to its roles",
+                |options| {
+                    let RoleOptions { command, role } = RoleOptions::parse(options)?;
+                    Ok(Self::Roles(command, RoleChange::Add(role)))
+                },
+            )
+            .options(RoleOptions::options()),
+        )
+        .subcommand(
+            SubcommandSpec::new(
+                "remove",
+                "Stop allowing a role to use a command",
+                |options| {
+                    let RoleOptions { command, role } = RoleOptions::parse(options)?;
+                    Ok(Self::Roles(command, RoleChange::Remove(role)))
+                },
+            )
+            .options(RoleOptions::options()),
+        )
+        .subcommand(
+            SubcommandSpec::new("clear", "Let anyone use a command again", |options| {
+                Ok(Self::Roles(
+                    RolesClearOptions::parse(options)?.command,
+                    RoleChange::Clear,
+                ))
+            })
+            .options(RolesClearOptions::options()),
+        )]
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        respond(self, interaction, app_state).await
+    }
+}
+
+#[async_trait]
+impl<'a> Respond<'a> for ConfigCommand<'a> {
+    fn parse(options: Options<'a>) -> Result<Self, String> {
+        parse_subcommand(options)
+    }
+
+    async fn respond(
+        self,
+        invocation: &Invocation<'_>,
+        app_state: &AppState,
+    ) -> Result<Reply, Failure> {
+        let guild_id = invocation.guild.ok_or_else(|| {
+            Failure::Refused(String::from("This command can only be used in a server"))
+        })?;
+
+        let (result, response) = match self {
+            Self::Show => {
+                return Ok(Reply::embed(render_config(
+                    &app_state.guild_config(guild_id).await,
+                )))
+            }
+            Self::Timezone(Some(zone)) => {
+                let tz = resolve_timezone_option(zone).map_err(Failure::Refused)?;
+                (
+                    app_state
+                        .update_guild_config(guild_id, |c| c.default_timezone = Some(tz))
+                        .await,
+                    format!(
+                        "Members who haven't registered a timezone are now assumed to be in {}",
+                        tz.name()
+                    ),
+                )
+            }
+            Self::Timezone(None) => (
+                app_state
+                    .update_guild_config(guild_id, |c| c.default_timezone = None)
+                    .await,
+                String::from(
+                    "Removed the default timezone, members will need to register their own",
+                ),
+            ),
+            Self::Clock(clock) => (
+                app_state
+                    .update_guild_config(guild_id, |c| c.clock = clock)
+                    .await,
+                format!("Times will now be shown with a {} clock", clock),
+            ),
+            Self::Locale(locale) => (
+                app_state
+                    .update_guild_config(guild_id, |c| c.locale = locale)
+                    .await,
+                format!(
+                    "Dates will now be read as {} ({})",
+                    if locale.month_first() {
+                        "month/day"
+                    } else {
+                        "day/month"
+                    },
+                    locale.code()
+                ),
+            ),
+            Self::Feature(feature, enabled) => (
+                app_state
+                    .update_guild_config(guild_id, |c| {
+                        if enabled {
+                            c.disabled_features.remove(&feature);
+                        } else {
+                            c.disabled_features.insert(feature);
+                        }
+                    })
+                    .await,
+                format!(
+                    "{} is now turned {}",
+                    feature,
+                    if enabled { "on" } else { "off" }
+                ),
+            ),
+            Self::Announcements(Some(channel)) => {
+                // changes already within the notice period are announced in the new channel
+                if let Err(e) = app_state
+                    .update_guild_settings(guild_id, |s| s.dst_announced_until = None)
+                    .await
+                {
+                    return Err(save_failure(guild_id, e));
+                }
+                (
+                    app_state
+                        .update_guild_config(guild_id, |c| {
+                            c.announcement_channel = Some(channel.into())
+                        })
+                        .await,
+                    format!(
+                        "Announcements will now be posted in <#{}>, including daylight savings changes {} days in advance",
+                        channel,
+                        notice().num_days()
+                    ),
+                )
+            }
+            Self::Announcements(None) => (
+                app_state
+                    .update_guild_config(guild_id, |c| c.announcement_channel = None)
+                    .await,
+                String::from("Announcements will no longer be posted"),
+            ),
+            Self::AutoConvert(enabled, channel) => {
+                let channel = channel
+                    .map(u64::from)
+                    .or(invocation.channel)
+                    .ok_or_else(|| {
+                        Failure::Refused(String::from("Please pick the channel to configure"))
+                    })?;
+                let config = app_state
+                    .update_guild_config(guild_id, |c| {
+                        if enabled {
+                            c.auto_convert_channels.insert(channel);
+                        } else {
+                            c.auto_convert_channels.remove(&channel);
+                        }
+                    })
+                    .await
+                    .map_err(|e| save_failure(guild_id, e))?;
+                let response = format!(
+                    "Automatic time conversion is now {} in <#{}>",
+                    if enabled { "enabled" } else { "disabled" },
+                    channel
+                );
+                return Ok(Reply::text(
+                    match config.enabled(Feature::AutoConvert) {
+                        true => response,
+                        false => format!(
+                            "{}. Note that it is currently turned off for the whole server, turn it on with `/config feature`",
+                            response
+                        ),
+                    },
+                ));
+            }
+            Self::Roles(command, change) => {
+                let command = command.trim().trim_start_matches('/');
+                if !command_names().contains(&command) {
+                    return Err(Failure::Refused(format!(
+                        "There is no `/{}` command, try picking one of the suggestions",
+                        command
+                    )));
+                }
+                let config = app_state
+                    .update_guild_config(guild_id, |c| {
+                        let roles = c.command_roles.entry(command.to_string()).or_default();
+                        match change {
+                            RoleChange::Add(role) => {
+                                roles.insert(role.into());
+                            }
+                            RoleChange::Remove(role) => {
+                                roles.remove(&role.into());
+                            }
+                            RoleChange::Clear => roles.clear(),
+                        }
+                        // with no roles left the command is open to everyone again
+                        if roles.is_empty() {
+                            c.command_roles.remove(command);
+                        }
+                    })
+                    .await
+                    .map_err(|e| save_failure(guild_id, e))?;
+                let response = match config.command_roles.get(command) {
+                    Some(roles) => format!(
+                        "`/{}` can now only be used by administrators and members with {}",
+                        command,
+                        roles
+                            .iter()
+                            .map(|r| format!("<@&{}>", r))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                    None => format!(
+                        "`/{}` can now be used by anyone allowed to by discord",
+                        command
+                    ),
+                };
+                return Ok(Reply::text(response));
+            }
+        };
+
+        match result {
+            Ok(_) => Ok(Reply::text(response)),
+            Err(e) => Err(save_failure(guild_id, e)),
+        }
+    }
+}
+
+/// the failure when a change to a guild's configuration couldn't be saved
+fn save_failure(guild_id: u64, e: impl std::fmt::Display) -> Failure {
+    Failure::Error {
+        response: String::from("Failed to save the server configuration, please try again"),
+        log_message: format!(
+            "failed to update configuration for guild {}: {}",
+            guild_id, e
+        ),
+    }
+}
+
+#[async_trait]
+impl<'a> AutocompleteCommand<'a> for ConfigCommand<'a> {
+    async fn autocomplete<'c>(
+        _: &'c CommandInteraction,
+        option: &'c AutocompleteOption,
+        _: &'c AppState,
+        _: &'c Context,
+    ) -> Result<CreateAutocompleteResponse, CommandResponse> {
+        match option.name {
+            "command" => {
+                let query = option.value.trim().trim_start_matches('/').to_lowercase();
+                Ok(command_names()
+                    .into_iter()
+                    .filter(|name| name.contains(query.as_str()))
+                    .take(MAX_AUTOCOMPLETE_CHOICES)
+                    .fold(CreateAutocompleteResponse::new(), |resp, name| {
+                        resp.add_string_choice(format!("/{}", name), name)
+                    }))
+            }
+            _ => Ok(timezone_autocomplete(option.value)),
+        }
+    }
+}
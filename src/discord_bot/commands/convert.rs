@@ -0,0 +1,159 @@
+use serenity::{
+    all::{AutocompleteOption, CommandInteraction},
+    async_trait,
+    builder::{CreateAutocompleteResponse, CreateCommand},
+    prelude::Context,
+};
+
+use crate::{
+    interaction::{Embed, Failure, Invocation, Options, Reply, Respond},
+    state::AppState,
+    timezones,
+};
+
+use super::{
+    command::{AutocompleteCommand, Command},
+    options::SlashCommand,
+    transport::respond,
+    util::{
+        invocation_parse_context, parse_time_option, resolve_timezone_option,
+        timezone_autocomplete, CommandResponse,
+    },
+};
+
+/// the number of target timezones which can be converted to at once
+const MAX_TARGETS: usize = 5;
+
+#[derive(SlashCommand)]
+pub struct ConvertCommand<'a> {
+    /// the time to convert, in any format understood by [time_parse]
+    #[option(
+        description = "The time to convert, e.g. \"3pm\", \"next friday 16:30\" or \"tomorrow 9am EST\""
+    )]
+    time: &'a str,
+    /// the timezone the time is in, if not given in the time itself
+    #[option(
+        description = "The timezone the time is in, defaults to your registered timezone",
+        autocomplete
+    )]
+    from: Option<&'a str>,
+    /// the timezones to convert the time into, from the options "to", "to2", "to3"...
+    #[option(
+        description = "The timezone, city or country to convert the time into",
+        autocomplete,
+        repeat(
+            count = MAX_TARGETS,
+            description = "Another timezone to convert the time into"
+        )
+    )]
+    to: Vec<&'a str>,
+}
+
+/// describe the difference in calendar days between two dates, e.g. "+1 day"
+fn day_offset(days: i64) -> Option<String> {
+    match days {
+        0 => None,
+        1 | -1 => Some(format!("{:+} day", days)),
+        days => Some(format!("{:+} days", days)),
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ConvertCommand<'a> {
+    fn name() -> &'static str {
+        "convert"
+    }
+
+    fn description() -> &'static str {
+        "Convert a time from one timezone into others"
+    }
+
+    fn dm_permission() -> bool {
+        true
+    }
+
+    fn get_application_command_options(i: CreateCommand) -> CreateCommand {
+        Self::register_options(i)
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        respond(self, interaction, app_state).await
+    }
+}
+
+#[async_trait]
+impl<'a> Respond<'a> for ConvertCommand<'a> {
+    fn parse(options: Options<'a>) -> Result<Self, String> {
+        <Self as SlashCommand>::parse(options)
+    }
+
+    async fn respond(
+        self,
+        invocation: &Invocation<'_>,
+        app_state: &AppState,
+    ) -> Result<Reply, Failure> {
+        let targets = self
+            .to
+            .iter()
+            .map(|name| resolve_timezone_option(name))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let context = invocation_parse_context(invocation, app_state, self.from)
+            .await?
+            .prefer_future();
+        let parsed = parse_time_option(self.time, &context)?;
+
+        let source = parsed.time;
+        let unix = source.timestamp();
+        let mut embed = Embed::new(format!(
+            "{} in {}",
+            source.format("%-I:%M%P on %a %-d %b %Y"),
+            source.timezone().name()
+        ))
+        .description(format!(
+            "<t:{}:F> (<t:{}:R>) in your own timezone\n`<t:{}:F>` `<t:{}:R>`",
+            unix, unix, unix, unix
+        ));
+
+        for tz in targets {
+            let local = source.with_timezone(&tz);
+            let days = (local.date_naive() - source.date_naive()).num_days();
+            let mut value = format!("**{}**", local.format("%-I:%M%P %a %-d %b"));
+            if let Some(offset) = day_offset(days) {
+                value.push_str(&format!(" ({})", offset));
+            }
+            value.push_str(&format!("\n{}", timezones::format_utc_offset(&local)));
+            embed = embed.field(tz.name(), value, true);
+        }
+
+        if parsed.is_ambiguous() {
+            let notes = parsed
+                .ambiguities
+                .iter()
+                .map(|a| a.to_string())
+                .collect::<Vec<_>>()
+                .join("\n");
+            embed = embed.footer(notes);
+        }
+
+        Ok(Reply::embed(embed))
+    }
+}
+
+#[async_trait]
+impl<'a> AutocompleteCommand<'a> for ConvertCommand<'a> {
+    async fn autocomplete<'c>(
+        _: &'c CommandInteraction,
+        option: &'c AutocompleteOption,
+        _: &'c AppState,
+        _: &'c Context,
+    ) -> Result<CreateAutocompleteResponse, CommandResponse> {
+        // every autocompleted option of this command is a timezone
+        Ok(timezone_autocomplete(option.value))
+    }
+}
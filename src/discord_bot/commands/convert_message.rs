@@ -0,0 +1,124 @@
+use chrono::Utc;
+use chrono_tz::Tz;
+use serenity::{
+    all::{CommandInteraction, CommandType, ResolvedTarget},
+    async_trait,
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::scanner::format_reply,
+    interaction::{Embed, Failure, Invocation, Options, Reply, Respond, UserRef},
+    state::AppState,
+    time_parse::{find_all, ParseContext},
+};
+
+use super::{
+    command::Command,
+    transport::respond,
+    util::{guild_config, CommandResponse},
+};
+
+/// convert the times mentioned in a message, picked from the message's context menu
+pub struct ConvertMessageCommand<'a> {
+    /// the text of the message
+    content: &'a str,
+    /// who wrote the message
+    author: UserRef,
+    /// a link to the message, if the transport has one
+    link: Option<String>,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for ConvertMessageCommand<'a> {
+    type Error = String;
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        match interaction.data.target() {
+            Some(ResolvedTarget::Message(message)) => Ok(Self {
+                content: &message.content,
+                author: UserRef(message.author.id.into()),
+                link: Some(message.link()),
+            }),
+            _ => Err(String::from("No message provided")),
+        }
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for ConvertMessageCommand<'a> {
+    fn name() -> &'static str {
+        "Convert times in this message"
+    }
+
+    fn description() -> &'static str {
+        "Convert every time mentioned in a message into your own timezone"
+    }
+
+    fn kind() -> CommandType {
+        CommandType::Message
+    }
+
+    fn dm_permission() -> bool {
+        true
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        respond(self, interaction, app_state).await
+    }
+}
+
+#[async_trait]
+impl<'a> Respond<'a> for ConvertMessageCommand<'a> {
+    /// read the message from the options `content`, `author` and optionally `link`, for
+    /// transports which can't pick a message from a menu
+    fn parse(options: Options<'a>) -> Result<Self, String> {
+        Ok(Self {
+            content: options.required("content")?,
+            author: options.required("author")?,
+            link: options.optional::<&str>("link")?.map(String::from),
+        })
+    }
+
+    async fn respond(
+        self,
+        invocation: &Invocation<'_>,
+        app_state: &AppState,
+    ) -> Result<Reply, Failure> {
+        let config = guild_config(invocation.guild, app_state).await;
+
+        // the times were written by the author, so they are read in the author's timezone
+        let author_tz = app_state
+            .effective_timezone(self.author.into(), invocation.guild)
+            .await;
+        // without one, times are read in UTC rather than skipped, as the footer explains
+        let parse_context = ParseContext::new(Utc::now(), Some(author_tz.unwrap_or(Tz::UTC)))
+            .month_first(config.locale.month_first());
+        let times = find_all(self.content, &parse_context);
+        if times.is_empty() {
+            return Err(Failure::Refused(String::from(
+                "I couldn't find any times in that message",
+            )));
+        }
+
+        let footer = match author_tz {
+            Some(tz) => format!(
+                "Times without a timezone were read in {}, the author's timezone",
+                tz.name()
+            ),
+            None => String::from(
+                "The author hasn't registered a timezone, so times without one were read in UTC",
+            ),
+        };
+        let mut embed = Embed::new("Times in this message")
+            .description(format_reply(&times))
+            .footer(footer);
+        if let Some(link) = self.link {
+            embed = embed.url(link);
+        }
+        Ok(Reply::embed(embed))
+    }
+}
@@ -0,0 +1,363 @@
+use chrono::Utc;
+use log::warn;
+use serenity::{
+    all::{AutocompleteOption, CommandInteraction, ComponentInteraction},
+    async_trait,
+    builder::{CreateAutocompleteResponse, CreateInteractionResponse, CreateMessage},
+    model::{
+        id::{ChannelId, GuildId, MessageId, UserId},
+        Permissions,
+    },
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::countdown::{remaining, render_countdown},
+    state::AppState,
+    store::{Countdown, Feature},
+};
+
+use super::{
+    command::{
+        parse_subcommand, AutocompleteCommand, Command, InteractionCommand, PaginatedResponse,
+        SubcommandSpec,
+    },
+    options::{Options, SlashCommand},
+    pagination::{self, Pages},
+    util::{
+        parse_time_option, timezone_autocomplete, user_parse_context, CommandResponse,
+        FailureMessageKind, MAX_AUTOCOMPLETE_CHOICES,
+    },
+};
+
+/// the most countdowns that can be running in a guild at once
+const MAX_COUNTDOWNS_PER_GUILD: usize = 25;
+
+/// the longest label a countdown can have
+const MAX_LABEL_LENGTH: usize = 200;
+
+/// start, view and cancel countdowns
+pub enum CountdownCommand<'a> {
+    /// start a countdown to a date and time
+    To(ToOptions<'a>),
+    /// list the countdowns running in the guild
+    List,
+    /// cancel a countdown, by the id of its message
+    Cancel(&'a str),
+}
+
+/// the options of `/countdown to`
+#[derive(SlashCommand)]
+pub struct ToOptions<'a> {
+    /// when the countdown ends, e.g. "friday 5pm"
+    #[option(description = "When the countdown ends, e.g. \"friday 5pm\" or \"2026-12-25 00:00\"")]
+    time: &'a str,
+    /// what is being counted down to
+    #[option(description = "What is being counted down to, e.g. \"Launch day\"")]
+    label: &'a str,
+    /// the timezone to interpret the time in, defaults to the user's timezone
+    #[option(
+        description = "The timezone of the time, defaults to your registered timezone",
+        autocomplete
+    )]
+    timezone: Option<&'a str>,
+    /// the channel to post the countdown in, defaults to the current channel
+    #[option(
+        description = "The channel to post the countdown in, defaults to this channel",
+        channel_types(Text)
+    )]
+    channel: Option<ChannelId>,
+    /// whether to edit the countdown as time passes
+    #[option(
+        description = "Edit the countdown to show the time remaining as it passes",
+        default
+    )]
+    live: bool,
+}
+
+/// the options of `/countdown cancel`
+#[derive(SlashCommand)]
+pub struct CancelOptions<'a> {
+    /// the id of the countdown's message
+    #[option(description = "The countdown to cancel", autocomplete)]
+    countdown: &'a str,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for CountdownCommand<'a> {
+    type Error = String;
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        parse_subcommand(Options::of(interaction))
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for CountdownCommand<'a> {
+    fn name() -> &'static str {
+        "countdown"
+    }
+
+    fn description() -> &'static str {
+        "Count down to a date and time, announcing it when it arrives"
+    }
+
+    fn default_permissions() -> Option<Permissions> {
+        Some(Permissions::MANAGE_MESSAGES)
+    }
+
+    fn feature() -> Option<Feature> {
+        Some(Feature::Countdowns)
+    }
+
+    fn subcommands() -> Vec<SubcommandSpec<'a, Self>> {
+        vec![
+            SubcommandSpec::new("to", "Start a countdown to a date and time", |options| {
+                Ok(Self::To(ToOptions::parse(options)?))
+            })
+            .options(ToOptions::options()),
+            SubcommandSpec::new("list", "List the countdowns running in this server", |_| {
+                Ok(Self::List)
+            }),
+            SubcommandSpec::new("cancel", "Cancel a countdown you started", |options| {
+                Ok(Self::Cancel(CancelOptions::parse(options)?.countdown))
+            })
+            .options(CancelOptions::options()),
+        ]
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        context: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: u64 = match interaction.guild_id {
+            Some(g_id) => g_id.into(),
+            None => {
+                return Err(CommandResponse::BasicFailure(String::from(
+                    "Countdowns can only be started in a server",
+                )))
+            }
+        };
+        let user_id: u64 = interaction.user.id.into();
+
+        match self {
+            Self::To(ToOptions {
+                time,
+                label,
+                timezone,
+                channel,
+                live,
+            }) => {
+                let label = label.trim();
+                if label.is_empty() || label.chars().count() > MAX_LABEL_LENGTH {
+                    return Err(CommandResponse::BasicFailure(format!(
+                        "Countdown labels must be between 1 and {} characters long",
+                        MAX_LABEL_LENGTH
+                    )));
+                }
+                if app_state.guild_settings(guild_id).await.countdowns.len()
+                    >= MAX_COUNTDOWNS_PER_GUILD
+                {
+                    return Err(CommandResponse::BasicFailure(format!(
+                        "This server already has {} countdowns running, cancel one with `/countdown cancel` first",
+                        MAX_COUNTDOWNS_PER_GUILD
+                    )));
+                }
+
+                let parse_context = user_parse_context(interaction, app_state, timezone)
+                    .await
+                    .map_err(CommandResponse::BasicFailure)?
+                    .prefer_future();
+                let now = parse_context.now;
+                let parsed = parse_time_option(time, &parse_context)
+                    .map_err(CommandResponse::BasicFailure)?;
+                let target = parsed.time.with_timezone(&Utc);
+                if target <= now {
+                    return Err(CommandResponse::BasicFailure(format!(
+                        "<t:{}:F> has already passed, countdowns must be to a time in the future",
+                        target.timestamp()
+                    )));
+                }
+
+                let channel = channel.unwrap_or(interaction.channel_id);
+                let mut countdown = Countdown {
+                    channel_id: channel.into(),
+                    message_id: 0,
+                    user_id,
+                    label: label.to_string(),
+                    target,
+                    live,
+                };
+                let message = channel
+                    .send_message(
+                        context,
+                        CreateMessage::new().embed(render_countdown(&countdown, now)),
+                    )
+                    .await
+                    .map_err(|e| CommandResponse::ComplexFailure {
+                        response: format!(
+                            "I couldn't post in <#{}>, check my permissions",
+                            channel
+                        ),
+                        kind: FailureMessageKind::Warn,
+                        log_message: format!("failed to post countdown: {}", e),
+                    })?;
+                countdown.message_id = message.id.into();
+
+                app_state
+                    .update_guild_settings(guild_id, |s| s.countdowns.push(countdown))
+                    .await
+                    .map_err(|e| {
+                        CommandResponse::InternalFailure(format!("failed to save countdown: {}", e))
+                    })?;
+
+                Ok(CommandResponse::BasicSuccess(format!(
+                    "Started a countdown in <#{}> to <t:{}:F>, I'll announce it there when it's reached",
+                    channel,
+                    target.timestamp()
+                )))
+            }
+            Self::List => {
+                let pages = Self::get_pages(
+                    interaction.user.id,
+                    interaction.guild_id,
+                    app_state,
+                    context,
+                )
+                .await?;
+                Ok(CommandResponse::ComplexSuccess(
+                    CreateInteractionResponse::Message(pages.page(Self::name(), 0).ephemeral(true)),
+                ))
+            }
+            Self::Cancel(id) => {
+                let message_id = id.trim().parse::<u64>().unwrap_or_default();
+                // anyone able to manage messages can clean up other people's countdowns
+                let moderator = interaction
+                    .member
+                    .as_ref()
+                    .and_then(|m| m.permissions)
+                    .is_some_and(|p| p.manage_messages());
+
+                let mut cancelled = None;
+                app_state
+                    .update_guild_settings(guild_id, |s| {
+                        if let Some(i) = s.countdowns.iter().position(|c| {
+                            c.message_id == message_id && (moderator || c.user_id == user_id)
+                        }) {
+                            cancelled = Some(s.countdowns.remove(i));
+                        }
+                    })
+                    .await
+                    .map_err(|e| {
+                        CommandResponse::InternalFailure(format!(
+                            "failed to cancel countdown: {}",
+                            e
+                        ))
+                    })?;
+
+                let countdown = cancelled.ok_or_else(|| {
+                    CommandResponse::BasicFailure(String::from(
+                        "You don't have a countdown like that, use `/countdown list` to see the countdowns running",
+                    ))
+                })?;
+
+                if let Err(e) = ChannelId::new(countdown.channel_id)
+                    .delete_message(context, MessageId::new(countdown.message_id))
+                    .await
+                {
+                    warn!("failed to delete countdown {}: {}", countdown.message_id, e);
+                }
+
+                Ok(CommandResponse::BasicSuccess(format!(
+                    "Cancelled the countdown to **{}**",
+                    countdown.label
+                )))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<'a> AutocompleteCommand<'a> for CountdownCommand<'a> {
+    async fn autocomplete<'c>(
+        interaction: &'c CommandInteraction,
+        option: &'c AutocompleteOption,
+        app_state: &'c AppState,
+        _: &'c Context,
+    ) -> Result<CreateAutocompleteResponse, CommandResponse> {
+        match option.name {
+            "countdown" => {
+                let Some(guild_id) = interaction.guild_id else {
+                    return Ok(CreateAutocompleteResponse::new());
+                };
+                let now = Utc::now();
+                let query = option.value.trim().to_lowercase();
+                Ok(app_state
+                    .guild_settings(guild_id.into())
+                    .await
+                    .countdowns
+                    .into_iter()
+                    .filter(|c| c.label.to_lowercase().contains(&query))
+                    .take(MAX_AUTOCOMPLETE_CHOICES)
+                    .fold(CreateAutocompleteResponse::new(), |resp, c| {
+                        let mut name = format!("{} (in {})", c.label, remaining(c.target - now));
+                        // discord rejects choices longer than 100 characters
+                        if name.chars().count() > 100 {
+                            name = name.chars().take(97).chain("...".chars()).collect();
+                        }
+                        resp.add_string_choice(name, c.message_id.to_string())
+                    }))
+            }
+            _ => Ok(timezone_autocomplete(option.value)),
+        }
+    }
+}
+
+#[async_trait]
+impl<'a> InteractionCommand<'a> for CountdownCommand<'a> {
+    async fn answerable<'b>(
+        interaction: &'b ComponentInteraction,
+        _: &'b AppState,
+        _: &'b Context,
+    ) -> bool {
+        pagination::answerable::<Self>(interaction)
+    }
+
+    async fn interaction<'b>(
+        interaction: &'b ComponentInteraction,
+        app_state: &'b AppState,
+        context: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        pagination::turn_page::<Self>(interaction, app_state, context).await
+    }
+}
+
+#[async_trait]
+impl<'a> PaginatedResponse<'a> for CountdownCommand<'a> {
+    async fn get_pages<'b>(
+        _: UserId,
+        guild_id: Option<GuildId>,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<Pages, CommandResponse> {
+        let guild_id = guild_id.ok_or_else(|| {
+            CommandResponse::BasicFailure(String::from("Countdowns can only be listed in a server"))
+        })?;
+        let mut countdowns = app_state.guild_settings(guild_id.into()).await.countdowns;
+        countdowns.sort_by_key(|c| c.target);
+        let entries = countdowns
+            .iter()
+            .map(|c| {
+                format!(
+                    "**{}** <t:{}:R> in <#{}>, started by <@{}>",
+                    c.label,
+                    c.target.timestamp(),
+                    c.channel_id,
+                    c.user_id
+                )
+            })
+            .collect();
+        Ok(Pages::new("Countdowns in this server", entries)
+            .empty("There are no countdowns running, start one with `/countdown to`"))
+    }
+}
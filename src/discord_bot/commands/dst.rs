@@ -0,0 +1,107 @@
+use chrono::{Duration, Utc};
+use serenity::{
+    all::CommandInteraction,
+    async_trait,
+    builder::{CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage},
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::dst::{describe_transitions, guild_timezones},
+    dst::transitions_between,
+    state::AppState,
+};
+
+use super::{
+    command::{parse_subcommand, Command, SubcommandSpec},
+    options::Options,
+    util::CommandResponse,
+};
+
+/// how far ahead `/dst upcoming` looks for changes
+const UPCOMING_DAYS: i64 = 180;
+
+/// the most changes listed by `/dst upcoming`
+const MAX_UPCOMING: usize = 5;
+
+/// look up daylight savings changes, which are announced in a guild's announcement channel
+pub enum DstCommand {
+    /// list the changes coming up for the timezones in the guild
+    Upcoming,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for DstCommand {
+    type Error = String;
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        parse_subcommand(Options::of(interaction))
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for DstCommand {
+    fn name() -> &'static str {
+        "dst"
+    }
+
+    fn description() -> &'static str {
+        "See the daylight savings changes coming up for the timezones of this server's members"
+    }
+
+    fn subcommands() -> Vec<SubcommandSpec<'a, Self>> {
+        vec![SubcommandSpec::new(
+            "upcoming",
+            "List the daylight savings changes coming up for this server's members",
+            |_| Ok(Self::Upcoming),
+        )]
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        context: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id = match interaction.guild_id {
+            Some(g_id) => g_id,
+            None => {
+                return Err(CommandResponse::BasicFailure(String::from(
+                    "Daylight savings changes can only be listed in a server",
+                )))
+            }
+        };
+
+        match self {
+            Self::Upcoming => {
+                let zones = guild_timezones(guild_id, context, app_state).await;
+                let now = Utc::now();
+                let transitions =
+                    transitions_between(&zones, now, now + Duration::days(UPCOMING_DAYS));
+
+                let description = if transitions.is_empty() {
+                    format!(
+                        "None of the {} timezones registered in this server change in the next {} days",
+                        zones.len(),
+                        UPCOMING_DAYS
+                    )
+                } else {
+                    describe_transitions(
+                        &transitions[..transitions.len().min(MAX_UPCOMING)],
+                        &zones,
+                    )
+                };
+
+                Ok(CommandResponse::ComplexSuccess(
+                    CreateInteractionResponse::Message(
+                        CreateInteractionResponseMessage::new()
+                            .embed(
+                                CreateEmbed::new()
+                                    .title("🕑 Upcoming daylight savings changes")
+                                    .description(description),
+                            )
+                            .ephemeral(true),
+                    ),
+                ))
+            }
+        }
+    }
+}
@@ -2,6 +2,7 @@ use serenity::{
     all::CommandInteraction,
     async_trait,
     builder::{CreateCommand, CreateInteractionResponse, CreateInteractionResponseMessage},
+    model::Permissions,
     prelude::Context,
 };
 
@@ -28,6 +29,10 @@ impl<'a> Command<'a> for HideCommand {
         "Creates a large message to hide previous messages in the chat"
     }
 
+    fn default_permissions() -> Option<Permissions> {
+        Some(Permissions::MANAGE_MESSAGES)
+    }
+
     fn get_application_command_options(i: CreateCommand) -> CreateCommand {
         i
     }
@@ -0,0 +1,470 @@
+use std::str::FromStr;
+
+use chrono::{Duration, NaiveDate, Utc, Weekday};
+use serenity::{
+    all::{
+        ActionRowComponent, CommandInteraction, ComponentInteraction, InputTextStyle,
+        ModalInteraction,
+    },
+    async_trait,
+    builder::{CreateActionRow, CreateInputText, CreateInteractionResponse, CreateModal},
+    prelude::Context,
+};
+
+use crate::{
+    interaction::{Failure, Invocation, Reply, Respond, Transport, UserRef},
+    state::AppState,
+    store::{DayHours, TimeRange, WorkingHours},
+    time_parse::parse_time_of_day,
+};
+
+use super::{
+    command::{parse_subcommand, Command, InteractionCommand, ModalSubmit, SubcommandSpec},
+    options::{Options, SlashCommand},
+    transport::{respond, Discord},
+    util::{guild_config, CommandResponse, FailureMessageKind},
+};
+
+/// the custom id of the modal used to set working hours
+const MODAL_ID: &str = "hours_set";
+
+/// the most days off which can be stored, so a typo in a range can't store thousands of dates,
+/// and few enough that all of them, at 12 characters each, fit in the form's 4000 characters
+pub(super) const MAX_DAYS_OFF: usize = 300;
+
+/// set and view working hours, and whether someone is working right now
+pub enum HoursCommand {
+    /// open a form to set the calling user's working hours
+    Set,
+    /// view the working hours and current status of the provided user, or the calling user
+    Get(Option<UserRef>),
+}
+
+/// the options of `/hours get`
+#[derive(SlashCommand)]
+pub struct GetOptions {
+    /// the user to look up
+    #[option(description = "The user to look up, defaults to yourself")]
+    user: Option<UserRef>,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for HoursCommand {
+    type Error = String;
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        Self::parse(Discord::invocation(interaction).options)
+    }
+}
+
+/// parse a span of time such as "09:00-17:00" or "9am-5:30pm"
+pub(super) fn parse_range(input: &str) -> Option<TimeRange> {
+    let (start, end) = input.split_once('-')?;
+    let range = TimeRange {
+        start: parse_time_of_day(start)?,
+        end: parse_time_of_day(end)?,
+    };
+    (range.start != range.end).then_some(range)
+}
+
+/// parse a list of weekdays such as "mon-fri", "mon,wed,fri" or "weekdays"
+pub(super) fn parse_days(input: &str) -> Option<Vec<Weekday>> {
+    let input = input.trim().to_ascii_lowercase();
+    match input.as_str() {
+        "weekdays" => return parse_days("mon-fri"),
+        "weekends" => return parse_days("sat-sun"),
+        "everyday" | "every day" | "all" => return parse_days("mon-sun"),
+        _ => {}
+    }
+
+    let mut days = Vec::new();
+    for part in input.split(',') {
+        match part.split_once('-') {
+            Some((from, to)) => {
+                let mut day = Weekday::from_str(from.trim()).ok()?;
+                let to = Weekday::from_str(to.trim()).ok()?;
+                while day != to {
+                    days.push(day);
+                    day = day.succ();
+                }
+                days.push(to);
+            }
+            None => days.push(Weekday::from_str(part.trim()).ok()?),
+        }
+    }
+
+    days.dedup();
+    Some(days)
+}
+
+/// parse hours for specific days, one per line, such as "fri 09:00-15:00"
+pub(super) fn parse_day_hours(input: &str) -> Result<Vec<DayHours>, String> {
+    input
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(|line| {
+            let invalid = || format!("I don't understand \"{}\", try \"fri 09:00-15:00\"", line);
+            let (day, hours) = line.split_once(char::is_whitespace).ok_or_else(invalid)?;
+            Ok(DayHours {
+                day: Weekday::from_str(day).map_err(|_| invalid())?,
+                hours: parse_range(hours.trim()).ok_or_else(invalid)?,
+            })
+        })
+        .collect()
+}
+
+/// parse a list of dates, or ranges of dates, such as "2026-12-24, 2026-12-28 to 2026-12-31"
+pub(super) fn parse_days_off(input: &str) -> Result<Vec<NaiveDate>, String> {
+    let date = |s: &str| {
+        NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d")
+            .map_err(|_| format!("\"{}\" isn't a date, write dates like 2026-12-24", s.trim()))
+    };
+
+    let mut days = Vec::new();
+    for entry in input.split([',', '\n']).map(str::trim) {
+        if entry.is_empty() {
+            continue;
+        }
+        match entry.split_once(" to ") {
+            Some((from, to)) => {
+                let (from, to) = (date(from)?, date(to)?);
+                // stop expanding one past the limit, so a range spanning centuries is refused quickly
+                days.extend(
+                    from.iter_days()
+                        .take_while(|d| *d <= to)
+                        .take(MAX_DAYS_OFF + 1 - days.len()),
+                );
+            }
+            None => days.push(date(entry)?),
+        }
+        if days.len() > MAX_DAYS_OFF {
+            return Err(format!("You can have at most {} days off", MAX_DAYS_OFF));
+        }
+    }
+
+    // days off which have already passed are no use to anyone
+    let yesterday = Utc::now().date_naive() - Duration::days(1);
+    days.retain(|d| *d >= yesterday);
+    days.sort();
+    days.dedup();
+    Ok(days)
+}
+
+/// format a list of weekdays as it can be entered into the form, e.g. "mon,tue,wed"
+fn format_days(days: &[Weekday]) -> String {
+    days.iter()
+        .map(|d| d.to_string().to_ascii_lowercase())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// a text input for the working hours form, with an example of what can be entered
+fn input(
+    style: InputTextStyle,
+    id: &str,
+    label: &str,
+    example: &str,
+    value: String,
+) -> CreateActionRow {
+    let input = CreateInputText::new(style, label, id)
+        .placeholder(example)
+        .required(false);
+    // discord rejects empty prefilled values
+    CreateActionRow::InputText(if value.is_empty() {
+        input
+    } else {
+        input.value(value)
+    })
+}
+
+/// the form used to set working hours, filled in with the user's current working hours
+pub(super) fn modal(current: &WorkingHours) -> CreateModal {
+    let day_hours = current
+        .day_hours
+        .iter()
+        .map(|d| format!("{} {}", d.day.to_string().to_ascii_lowercase(), d.hours))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let days_off = current
+        .days_off
+        .iter()
+        .map(|d| d.format("%Y-%m-%d").to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    CreateModal::new(MODAL_ID, "Working hours").components(vec![
+        input(
+            InputTextStyle::Short,
+            "days",
+            "Working days",
+            "mon-fri, or mon,wed,fri",
+            format_days(&current.days),
+        ),
+        input(
+            InputTextStyle::Short,
+            "hours",
+            "Usual hours",
+            "09:00-17:00, or 9am-5:30pm",
+            TimeRange {
+                start: current.start,
+                end: current.end,
+            }
+            .to_string(),
+        ),
+        input(
+            InputTextStyle::Paragraph,
+            "day_hours",
+            "Different hours on some days",
+            "fri 09:00-15:00, one day per line",
+            day_hours,
+        ),
+        input(
+            InputTextStyle::Short,
+            "lunch",
+            "Lunch break",
+            "12:00-13:00",
+            current.lunch.map(|l| l.to_string()).unwrap_or_default(),
+        ),
+        input(
+            InputTextStyle::Paragraph,
+            "days_off",
+            "Days off",
+            "2026-12-24, 2026-12-28 to 2026-12-31",
+            days_off,
+        ),
+    ])
+}
+
+/// read the working hours entered into the form
+fn parse_modal(modal: &ModalInteraction) -> Result<WorkingHours, String> {
+    let field = |id: &str| {
+        modal
+            .data
+            .components
+            .iter()
+            .flat_map(|row| row.components.iter())
+            .find_map(|c| match c {
+                ActionRowComponent::InputText(input) if input.custom_id == id => {
+                    input.value.as_deref()
+                }
+                _ => None,
+            })
+            .map(str::trim)
+            .unwrap_or_default()
+    };
+
+    let defaults = WorkingHours::default();
+    let days = match field("days") {
+        "" => defaults.days,
+        days => parse_days(days).ok_or_else(|| {
+            String::from("Please provide your working days such as mon-fri or mon,wed,fri")
+        })?,
+    };
+    let hours = match field("hours") {
+        "" => TimeRange {
+            start: defaults.start,
+            end: defaults.end,
+        },
+        hours => parse_range(hours).ok_or_else(|| {
+            String::from("Please provide your usual hours such as 09:00-17:00 or 9am-5:30pm")
+        })?,
+    };
+    let lunch =
+        match field("lunch") {
+            "" => None,
+            lunch => Some(parse_range(lunch).ok_or_else(|| {
+                String::from("Please provide your lunch break such as 12:00-13:00")
+            })?),
+        };
+
+    // giving a day its own hours implies it is worked
+    let day_hours = parse_day_hours(field("day_hours"))?;
+    let mut days = days;
+    days.extend(day_hours.iter().map(|d| d.day));
+    days.sort_by_key(|d| d.num_days_from_monday());
+    days.dedup();
+
+    Ok(WorkingHours {
+        start: hours.start,
+        end: hours.end,
+        days,
+        day_hours,
+        lunch,
+        days_off: parse_days_off(field("days_off"))?,
+    })
+}
+
+#[async_trait]
+impl<'a> Command<'a> for HoursCommand {
+    fn name() -> &'static str {
+        "hours"
+    }
+
+    fn description() -> &'static str {
+        "Set your working hours, or check if someone is working right now"
+    }
+
+    fn dm_permission() -> bool {
+        true
+    }
+
+    fn subcommands() -> Vec<SubcommandSpec<'a, Self>> {
+        vec![
+            SubcommandSpec::new(
+                "set",
+                "Set the hours you usually work, your lunch break and days off",
+                |_| Ok(Self::Set),
+            ),
+            SubcommandSpec::new(
+                "get",
+                "Show the working hours of yourself or another user, and if they are working now",
+                |options| Ok(Self::Get(GetOptions::parse(options)?.user)),
+            )
+            .options(GetOptions::options()),
+        ]
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        match self {
+            Self::Set => {
+                let current = app_state
+                    .user_profile(interaction.user.id.into())
+                    .await
+                    .and_then(|p| p.working_hours)
+                    .unwrap_or_default();
+                Ok(CommandResponse::ComplexSuccess(
+                    CreateInteractionResponse::Modal(modal(&current)),
+                ))
+            }
+            command => respond(command, interaction, app_state).await,
+        }
+    }
+}
+
+#[async_trait]
+impl<'a> Respond<'a> for HoursCommand {
+    fn parse(options: Options<'a>) -> Result<Self, String> {
+        parse_subcommand(options)
+    }
+
+    async fn respond(
+        self,
+        invocation: &Invocation<'_>,
+        app_state: &AppState,
+    ) -> Result<Reply, Failure> {
+        let caller = invocation.user;
+        match self {
+            // working hours are entered into a form, which only discord can show
+            Self::Set => Err(Failure::Refused(String::from(
+                "Working hours can only be set from discord",
+            ))),
+            Self::Get(user) => {
+                let target: u64 = user.map(u64::from).unwrap_or(caller);
+                let profile = app_state.user_profile(target).await.unwrap_or_default();
+                let who = if target == caller {
+                    String::from("You")
+                } else {
+                    format!("<@{}>", target)
+                };
+
+                let working_hours =
+                    match profile.working_hours {
+                        Some(working_hours) => working_hours,
+                        None if target == caller => return Err(Failure::Refused(String::from(
+                            "You haven't set your working hours yet, use `/hours set` to add them",
+                        ))),
+                        None => {
+                            return Err(Failure::Refused(format!(
+                                "<@{}> hasn't set their working hours yet",
+                                target
+                            )))
+                        }
+                    };
+                let hours = working_hours.describe();
+                let time_format = guild_config(invocation.guild, app_state)
+                    .await
+                    .clock
+                    .time_format();
+                match profile.timezone {
+                    Some(tz) => {
+                        let now = Utc::now().with_timezone(&tz);
+                        Ok(Reply::text(format!(
+                            "{} {} {} ({} {}), working {}",
+                            who,
+                            if target == caller { "are" } else { "is" },
+                            working_hours.status(&now),
+                            now.format(time_format),
+                            tz.name(),
+                            hours
+                        )))
+                    }
+                    None => Ok(Reply::text(format!(
+                        "{} {} {}, but without a registered timezone I can't tell if that's now",
+                        who,
+                        if target == caller { "work" } else { "works" },
+                        hours
+                    ))),
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<'a> InteractionCommand<'a> for HoursCommand {
+    async fn answerable<'b>(_: &'b ComponentInteraction, _: &'b AppState, _: &'b Context) -> bool {
+        // the only followup to this command is the modal, which has no other components
+        false
+    }
+
+    async fn interaction<'b>(
+        _: &'b ComponentInteraction,
+        _: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        Err(CommandResponse::InternalFailure(String::from(
+            "hours command has no component interactions",
+        )))
+    }
+}
+
+#[async_trait]
+impl<'a> ModalSubmit<'a> for HoursCommand {
+    async fn modal_submit<'b>(
+        modal: &'b ModalInteraction,
+        _: &'b AppState,
+        _: &'b Context,
+    ) -> bool {
+        modal.data.custom_id == MODAL_ID
+    }
+
+    async fn handle_modal_submit<'b>(
+        modal: &'b ModalInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let caller: u64 = modal.user.id.into();
+        let working_hours = parse_modal(modal).map_err(CommandResponse::BasicFailure)?;
+        let description = working_hours.describe();
+
+        if let Err(e) = app_state
+            .set_user_working_hours(caller, working_hours)
+            .await
+        {
+            return Err(CommandResponse::ComplexFailure {
+                response: String::from("Failed to save your working hours, please try again"),
+                kind: FailureMessageKind::Error,
+                log_message: format!("failed to save working hours for {}: {}", caller, e),
+            });
+        }
+
+        Ok(CommandResponse::BasicSuccess(format!(
+            "Your working hours are now {}",
+            description
+        )))
+    }
+}
@@ -0,0 +1,65 @@
+use serenity::{
+    all::{CommandInteraction, CommandType, ResolvedTarget},
+    async_trait,
+    model::id::UserId,
+    prelude::Context,
+};
+
+use crate::{
+    interaction::{Transport, Value},
+    state::AppState,
+};
+
+use super::{
+    command::Command,
+    time::TimeCommand,
+    transport::{respond_to, Discord},
+    util::CommandResponse,
+};
+
+/// show the local time of a user, picked from the user's context menu
+pub struct LocalTimeCommand {
+    /// the user the command was used on
+    user: UserId,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for LocalTimeCommand {
+    type Error = String;
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        match interaction.data.target() {
+            Some(ResolvedTarget::User(user, _)) => Ok(Self { user: user.id }),
+            _ => Err(String::from("No user provided")),
+        }
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for LocalTimeCommand {
+    fn name() -> &'static str {
+        "Show user's local time"
+    }
+
+    fn description() -> &'static str {
+        "Show the local time of a user, and whether they are working"
+    }
+
+    fn kind() -> CommandType {
+        CommandType::User
+    }
+
+    fn dm_permission() -> bool {
+        true
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        // the same as `/time user`, with the user picked from the menu
+        let invocation =
+            Discord::invocation(interaction).option("user", Value::User(self.user.into()));
+        respond_to::<TimeCommand>(invocation, app_state).await
+    }
+}
@@ -0,0 +1,324 @@
+use chrono::{Duration, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serenity::{
+    all::CommandInteraction,
+    async_trait,
+    model::id::{GuildId, RoleId, UserId},
+    prelude::Context,
+};
+
+use crate::{
+    interaction::{Embed, Reply, Transport},
+    meeting::{find_slots, hourly_availability, Participant},
+    state::AppState,
+    store::Feature,
+    time_parse::ParseContext,
+};
+
+use super::{
+    command::{parse_subcommand, Command, SubcommandSpec},
+    options::{Options, SlashCommand},
+    transport::Discord,
+    util::{parse_time_option, CommandResponse},
+};
+
+/// the number of meeting times to propose
+const PROPOSED_SLOTS: usize = 5;
+
+/// the number of days searched if none is provided
+const DEFAULT_DAYS: i64 = 5;
+
+/// the most days which can be searched, and shown on the heatmap
+const MAX_DAYS: i64 = 14;
+
+/// the most people which can be invited to a meeting, including members of roles
+const MAX_PARTICIPANTS: usize = 100;
+
+/// the most people mentioned in a single field, so it stays within discord's 1024 character limit
+const MAX_LISTED_MENTIONS: usize = 20;
+
+/// plan meetings across the timezones of the participants
+pub enum MeetingCommand<'a> {
+    /// find the best times for a meeting
+    Find(FindOptions<'a>),
+}
+
+/// the options of `/meeting find`
+#[derive(SlashCommand)]
+pub struct FindOptions<'a> {
+    /// the members and roles to invite, as mentions
+    #[option(description = "The members and roles to invite, e.g. \"@alice @bob @engineering\"")]
+    participants: &'a str,
+    /// how long the meeting lasts, e.g. "1h"
+    #[option(description = "How long the meeting lasts, e.g. \"30m\" or \"1h\"")]
+    duration: &'a str,
+    /// the number of days to search
+    #[option(
+        description = "How many days ahead to search, defaults to 5",
+        min = 1,
+        max = MAX_DAYS
+    )]
+    days: Option<i64>,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for MeetingCommand<'a> {
+    type Error = String;
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        parse_subcommand(Options::of(interaction))
+    }
+}
+
+/// find the user and role mentions in a string, e.g. "<@123> <@&456>"
+fn parse_mentions(input: &str) -> (Vec<UserId>, Vec<RoleId>) {
+    let mut users = Vec::new();
+    let mut roles = Vec::new();
+    for mention in input.split('<').skip(1) {
+        let Some((mention, _)) = mention.split_once('>') else {
+            continue;
+        };
+        if let Some(id) = mention.strip_prefix("@&") {
+            roles.extend(
+                id.parse::<u64>()
+                    .ok()
+                    .filter(|id| *id != 0)
+                    .map(RoleId::new),
+            );
+        } else if let Some(id) = mention.strip_prefix('@') {
+            let id = id.strip_prefix('!').unwrap_or(id);
+            users.extend(
+                id.parse::<u64>()
+                    .ok()
+                    .filter(|id| *id != 0)
+                    .map(UserId::new),
+            );
+        }
+    }
+    (users, roles)
+}
+
+/// mention a list of users, naming at most [MAX_LISTED_MENTIONS] of them
+fn mention_list(users: &[u64]) -> String {
+    let mut mentions = users
+        .iter()
+        .take(MAX_LISTED_MENTIONS)
+        .map(|id| format!("<@{}>", id))
+        .collect::<Vec<_>>()
+        .join(" ");
+    if users.len() > MAX_LISTED_MENTIONS {
+        mentions.push_str(&format!(" and {} more", users.len() - MAX_LISTED_MENTIONS));
+    }
+    mentions
+}
+
+/// the heatmap square representing the share of participants available
+fn heat(share: f64) -> char {
+    match share {
+        s if s >= 1.0 => '🟩',
+        s if s >= 0.5 => '🟨',
+        s if s > 0.0 => '🟧',
+        _ => '⬛',
+    }
+}
+
+/// draw a heatmap of availability for each hour of the next `days` days in the provided timezone
+fn heatmap(participants: &[Participant], tz: Tz, days: i64) -> String {
+    let today = Utc::now().with_timezone(&tz).date_naive();
+    today
+        .iter_days()
+        .take(days as usize)
+        .filter_map(|date| {
+            let midnight = tz
+                .from_local_datetime(&date.and_time(NaiveTime::MIN))
+                .earliest()?
+                .with_timezone(&Utc);
+            let hours = hourly_availability(participants, midnight, 24);
+            Some(format!(
+                "`{}` {}",
+                date.format("%a %d"),
+                hours.into_iter().map(heat).collect::<String>()
+            ))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl MeetingCommand<'_> {
+    /// collect the users invited to a meeting, expanding roles into their members
+    fn invited(
+        context: &Context,
+        guild_id: GuildId,
+        caller: UserId,
+        participants: &str,
+    ) -> Result<Vec<UserId>, String> {
+        let (mut users, roles) = parse_mentions(participants);
+        users.push(caller);
+
+        if !roles.is_empty() {
+            let guild = guild_id
+                .to_guild_cached(&context.cache)
+                .ok_or_else(|| String::from("I can't see the members of this server right now, try mentioning people instead of roles"))?;
+            users.extend(
+                guild
+                    .members
+                    .values()
+                    .filter(|m| !m.user.bot && m.roles.iter().any(|r| roles.contains(r)))
+                    .map(|m| m.user.id),
+            );
+        }
+
+        users.sort();
+        users.dedup();
+        if users.len() > MAX_PARTICIPANTS {
+            return Err(format!(
+                "A meeting can have at most {} participants",
+                MAX_PARTICIPANTS
+            ));
+        }
+        Ok(users)
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for MeetingCommand<'a> {
+    fn name() -> &'static str {
+        "meeting"
+    }
+
+    fn description() -> &'static str {
+        "Plan meetings around everyone's timezones and working hours"
+    }
+
+    fn feature() -> Option<Feature> {
+        Some(Feature::Meetings)
+    }
+
+    fn subcommands() -> Vec<SubcommandSpec<'a, Self>> {
+        vec![SubcommandSpec::new(
+            "find",
+            "Find the times when the most participants are working",
+            |options| Ok(Self::Find(FindOptions::parse(options)?)),
+        )
+        .options(FindOptions::options())]
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        context: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let Self::Find(FindOptions {
+            participants,
+            duration,
+            days,
+        }) = self;
+
+        let guild_id = interaction.guild_id.ok_or_else(|| {
+            CommandResponse::BasicFailure(String::from("Meetings can only be planned in a server"))
+        })?;
+
+        let now = Utc::now();
+        let duration = duration.trim();
+        let duration = duration.strip_prefix("in ").unwrap_or(duration);
+        let parsed = parse_time_option(&format!("in {}", duration), &ParseContext::new(now, None))
+            .map_err(CommandResponse::BasicFailure)?;
+        let duration = parsed.time.with_timezone(&Utc) - now;
+        if !parsed.relative || duration < Duration::minutes(15) || duration > Duration::hours(8) {
+            return Err(CommandResponse::BasicFailure(String::from(
+                "Meetings must last between 15 minutes and 8 hours, e.g. \"30m\" or \"1h\"",
+            )));
+        }
+        let days = days.unwrap_or(DEFAULT_DAYS).clamp(1, MAX_DAYS);
+
+        let invited = Self::invited(context, guild_id, interaction.user.id, participants)
+            .map_err(CommandResponse::BasicFailure)?;
+
+        let mut found = Vec::new();
+        let mut unknown = Vec::new();
+        for user in invited.iter() {
+            let profile = app_state.user_profile((*user).into()).await;
+            // members without a timezone of their own are assumed to be in the server's default
+            match app_state
+                .effective_timezone((*user).into(), Some(guild_id.into()))
+                .await
+            {
+                Some(tz) => found.push(Participant {
+                    user_id: (*user).into(),
+                    tz,
+                    working_hours: profile.and_then(|p| p.working_hours).unwrap_or_default(),
+                }),
+                None => unknown.push((*user).into()),
+            }
+        }
+        if found.is_empty() {
+            return Err(CommandResponse::BasicFailure(String::from(
+                "None of the participants have registered a timezone, they can add one with `/timezone set`",
+            )));
+        }
+
+        let slots = find_slots(
+            &found,
+            now,
+            now + Duration::days(days),
+            duration,
+            PROPOSED_SLOTS,
+        );
+
+        // the heatmap is drawn in the timezone of the caller, so the rows line up with their days
+        let display_tz = app_state
+            .effective_timezone(interaction.user.id.into(), Some(guild_id.into()))
+            .await
+            .unwrap_or(Tz::UTC);
+
+        let mut embed = Embed::new(format!(
+            "Best times for a {} minute meeting with {} people",
+            duration.num_minutes(),
+            found.len()
+        ))
+        .description(heatmap(&found, display_tz, days))
+        .footer(format!(
+            "Each square is an hour from midnight {}. 🟩 everyone working, 🟨 at least half, 🟧 some, ⬛ nobody",
+            display_tz.name()
+        ));
+
+        if slots.is_empty() {
+            embed = embed.field(
+                "No times found",
+                format!(
+                    "Nobody is working for a whole {} minutes in the next {} days",
+                    duration.num_minutes(),
+                    days
+                ),
+                false,
+            );
+        }
+        for (i, slot) in slots.iter().enumerate() {
+            let missing = found
+                .iter()
+                .filter(|p| !slot.available.contains(&p.user_id))
+                .map(|p| p.user_id)
+                .collect::<Vec<_>>();
+            let mut value = format!(
+                "<t:{}:F> to <t:{}:t>\n{}/{} working",
+                slot.start.timestamp(),
+                slot.end.timestamp(),
+                slot.available.len(),
+                found.len()
+            );
+            if !missing.is_empty() {
+                value.push_str(&format!(", missing {}", mention_list(&missing)));
+            }
+            embed = embed.field(format!("Option {}", i + 1), value, false);
+        }
+        if !unknown.is_empty() {
+            embed = embed.field(
+                "Without a registered timezone",
+                mention_list(&unknown),
+                false,
+            );
+        }
+
+        // the proposed times are shared with everyone invited
+        Discord::deliver(Ok(Reply::embed(embed).public()))
+    }
+}
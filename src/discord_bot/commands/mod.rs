@@ -1,9 +1,30 @@
 mod command;
+mod options;
+mod pagination;
+mod transport;
 mod util;
 
+mod config;
+mod convert;
+mod convert_message;
+mod countdown;
+mod dst;
 mod hide;
+mod hours;
+mod local_time;
+mod meeting;
 mod ping;
+mod remind;
 mod say;
 mod time;
+mod timestamp;
+mod timezone;
+mod worldclock;
 
-pub use command::{application_command, autocomplete, command, handle_modal, interaction};
+#[cfg(test)]
+mod tests;
+
+pub use command::{
+    application_command, autocomplete, command, global_application_command, handle_modal,
+    interaction,
+};
@@ -0,0 +1,218 @@
+//! Reading the options provided with an application command into their transport agnostic
+//! [Options], and registering the options with discord.
+//! Every option is declared by deriving [SlashCommand], on the command itself or on a struct
+//! holding the options of one of its subcommands. Subcommands and their groups are declared with
+//! [SubcommandSpec](super::command::SubcommandSpec), which reads the subcommand's struct.
+
+use serenity::{
+    all::{CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType},
+    builder::{CreateCommand, CreateCommandOption},
+    model::id::{ChannelId, RoleId, UserId},
+};
+
+pub use crate::interaction::{Options, Subcommand};
+pub use time_bot_derive::SlashCommand;
+
+use crate::interaction::{FromValue, UserRef, Value};
+
+/// A command, or the options of a subcommand, declared on its fields with `#[derive(SlashCommand)]`
+pub trait SlashCommand<'a>: Sized {
+    /// the options to register with discord, required options first
+    fn options() -> Vec<CreateCommandOption>;
+
+    /// read the fields from the options provided
+    fn parse(options: Options<'a>) -> Result<Self, String>;
+
+    /// register the options of the command, for use in `get_application_command_options`
+    fn register_options(command: CreateCommand) -> CreateCommand {
+        Self::options()
+            .into_iter()
+            .fold(command, CreateCommand::add_option)
+    }
+}
+
+/// A type which can be read from the value of a command option, and registered as one with discord
+pub trait OptionValue<'a>: FromValue<'a> {
+    /// the type of option discord should ask for
+    const OPTION_TYPE: CommandOptionType;
+}
+
+impl<'a> OptionValue<'a> for &'a str {
+    const OPTION_TYPE: CommandOptionType = CommandOptionType::String;
+}
+
+impl<'a> OptionValue<'a> for bool {
+    const OPTION_TYPE: CommandOptionType = CommandOptionType::Boolean;
+}
+
+impl<'a> OptionValue<'a> for i64 {
+    const OPTION_TYPE: CommandOptionType = CommandOptionType::Integer;
+}
+
+impl<'a> OptionValue<'a> for f64 {
+    const OPTION_TYPE: CommandOptionType = CommandOptionType::Number;
+}
+
+impl<'a> OptionValue<'a> for UserRef {
+    const OPTION_TYPE: CommandOptionType = CommandOptionType::User;
+}
+
+impl<'a> FromValue<'a> for UserId {
+    const KIND: &'static str = "a user";
+    fn from_value(value: &Value<'a>) -> Option<Self> {
+        match value {
+            Value::User(id) => Some(Self::new(*id)),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> OptionValue<'a> for UserId {
+    const OPTION_TYPE: CommandOptionType = CommandOptionType::User;
+}
+
+impl<'a> FromValue<'a> for ChannelId {
+    const KIND: &'static str = "a channel";
+    fn from_value(value: &Value<'a>) -> Option<Self> {
+        match value {
+            Value::Channel(id) => Some(Self::new(*id)),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> OptionValue<'a> for ChannelId {
+    const OPTION_TYPE: CommandOptionType = CommandOptionType::Channel;
+}
+
+impl<'a> FromValue<'a> for RoleId {
+    const KIND: &'static str = "a role";
+    fn from_value(value: &Value<'a>) -> Option<Self> {
+        match value {
+            Value::Role(id) => Some(Self::new(*id)),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> OptionValue<'a> for RoleId {
+    const OPTION_TYPE: CommandOptionType = CommandOptionType::Role;
+}
+
+/// A type which an option can be limited to a set of choices of
+pub trait OptionChoice: Sized {
+    /// the type of the choices, which the derive checks a choice fits in when it is compiled
+    type Choice;
+
+    /// add a choice to an option
+    fn choice(
+        option: CreateCommandOption,
+        name: impl Into<String>,
+        value: Self::Choice,
+    ) -> CreateCommandOption;
+}
+
+impl OptionChoice for &str {
+    type Choice = &'static str;
+
+    fn choice(
+        option: CreateCommandOption,
+        name: impl Into<String>,
+        value: Self::Choice,
+    ) -> CreateCommandOption {
+        option.add_string_choice(name, value)
+    }
+}
+
+impl OptionChoice for i64 {
+    type Choice = i32;
+
+    fn choice(
+        option: CreateCommandOption,
+        name: impl Into<String>,
+        value: Self::Choice,
+    ) -> CreateCommandOption {
+        option.add_int_choice(name, value)
+    }
+}
+
+impl OptionChoice for f64 {
+    type Choice = f64;
+
+    fn choice(
+        option: CreateCommandOption,
+        name: impl Into<String>,
+        value: Self::Choice,
+    ) -> CreateCommandOption {
+        option.add_number_choice(name, value)
+    }
+}
+
+/// A numeric type which an option can be bounded to a range of
+pub trait OptionBounds: Sized {
+    /// the type of the bounds, which the derive checks a bound fits in when it is compiled
+    type Bound;
+
+    /// set the smallest value accepted
+    fn min(option: CreateCommandOption, value: Self::Bound) -> CreateCommandOption;
+
+    /// set the largest value accepted
+    fn max(option: CreateCommandOption, value: Self::Bound) -> CreateCommandOption;
+}
+
+impl OptionBounds for i64 {
+    type Bound = u64;
+
+    fn min(option: CreateCommandOption, value: Self::Bound) -> CreateCommandOption {
+        option.min_int_value(value)
+    }
+
+    fn max(option: CreateCommandOption, value: Self::Bound) -> CreateCommandOption {
+        option.max_int_value(value)
+    }
+}
+
+impl OptionBounds for f64 {
+    type Bound = f64;
+
+    fn min(option: CreateCommandOption, value: Self::Bound) -> CreateCommandOption {
+        option.min_number_value(value)
+    }
+
+    fn max(option: CreateCommandOption, value: Self::Bound) -> CreateCommandOption {
+        option.max_number_value(value)
+    }
+}
+
+impl<'a> Options<'a> {
+    /// the top level options of a command
+    pub fn of(interaction: &'a CommandInteraction) -> Self {
+        Self::convert(&interaction.data.options)
+    }
+
+    /// read discord's options into their transport agnostic form
+    fn convert(options: &'a [CommandDataOption]) -> Self {
+        options
+            .iter()
+            .map(|option| {
+                let value = match &option.value {
+                    CommandDataOptionValue::String(text) => Value::Text(text),
+                    CommandDataOptionValue::Integer(n) => Value::Integer(*n),
+                    CommandDataOptionValue::Number(n) => Value::Number(*n),
+                    CommandDataOptionValue::Boolean(b) => Value::Boolean(*b),
+                    CommandDataOptionValue::User(id) => Value::User(u64::from(*id)),
+                    CommandDataOptionValue::Channel(id) => Value::Channel(u64::from(*id)),
+                    CommandDataOptionValue::Role(id) => Value::Role(u64::from(*id)),
+                    CommandDataOptionValue::SubCommand(options) => {
+                        Value::Subcommand(Self::convert(options))
+                    }
+                    CommandDataOptionValue::SubCommandGroup(subcommands) => {
+                        Value::SubcommandGroup(Self::convert(subcommands))
+                    }
+                    _ => Value::Other,
+                };
+                (option.name.as_str(), value)
+            })
+            .collect()
+    }
+}
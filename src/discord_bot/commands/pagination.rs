@@ -0,0 +1,290 @@
+//! Responses which show a long list a page at a time, with buttons to move between the pages.
+//! The page being shown is stored in the custom ids of the buttons, and the list is rebuilt from
+//! the current state whenever one is clicked, so the buttons keep working across restarts.
+
+use serenity::{
+    all::{ButtonStyle, ComponentInteraction, ComponentInteractionDataKind},
+    builder::{
+        CreateActionRow, CreateButton, CreateEmbed, CreateInteractionResponse,
+        CreateInteractionResponseMessage, CreateSelectMenu, CreateSelectMenuKind,
+        CreateSelectMenuOption,
+    },
+    prelude::Context,
+};
+
+use crate::state::AppState;
+
+use super::{command::PaginatedResponse, util::CommandResponse};
+
+/// the prefix of the custom id of every pagination component
+const PREFIX: &str = "page";
+
+/// the number of entries shown on a page unless the list asks for another amount
+const DEFAULT_PER_PAGE: usize = 10;
+
+/// the longest description discord accepts in an embed, in characters
+const MAX_DESCRIPTION_LENGTH: usize = 4096;
+
+/// the most pages discord will list in the jump menu
+const MAX_JUMP_OPTIONS: usize = 25;
+
+/// A list of entries which is shown a page at a time
+#[derive(Debug, Clone)]
+pub struct Pages {
+    /// the title shown above every page
+    title: String,
+    /// the entries of the list, one line each
+    entries: Vec<String>,
+    /// the number of entries on each page
+    per_page: usize,
+    /// shown instead of a page when the list has no entries
+    empty: String,
+}
+
+impl Pages {
+    /// a list with the default number of entries on each page
+    pub fn new(title: impl Into<String>, entries: Vec<String>) -> Self {
+        Self {
+            title: title.into(),
+            entries,
+            per_page: DEFAULT_PER_PAGE,
+            empty: String::from("There's nothing here yet"),
+        }
+    }
+
+    /// set the number of entries on each page
+    pub fn per_page(mut self, per_page: usize) -> Self {
+        self.per_page = per_page.max(1);
+        self
+    }
+
+    /// set the message shown when the list has no entries
+    pub fn empty(mut self, empty: impl Into<String>) -> Self {
+        self.empty = empty.into();
+        self
+    }
+
+    /// the description of each page, holding up to `per_page` entries as long as they fit in
+    /// an embed. entries too long to fit on a page by themselves are cut short
+    fn descriptions(&self) -> Vec<String> {
+        // each page, along with its length in characters and the number of entries on it
+        let mut pages: Vec<(String, usize, usize)> = Vec::new();
+        for entry in self.entries.iter() {
+            let entry = truncate(entry, MAX_DESCRIPTION_LENGTH);
+            let length = entry.chars().count();
+            match pages.last_mut() {
+                Some((description, page_length, entries))
+                    if *entries < self.per_page
+                        && *page_length + 1 + length <= MAX_DESCRIPTION_LENGTH =>
+                {
+                    description.push('\n');
+                    description.push_str(&entry);
+                    *page_length += 1 + length;
+                    *entries += 1;
+                }
+                _ => pages.push((entry, length, 1)),
+            }
+        }
+        pages
+            .into_iter()
+            .map(|(description, _, _)| description)
+            .collect()
+    }
+
+    /// render a page of the list, along with the buttons to move to the others.
+    /// pages past the end show the last page, as the list may have shrunk since the buttons were made
+    pub fn page(&self, list: &str, page: usize) -> CreateInteractionResponseMessage {
+        let mut descriptions = self.descriptions();
+        let count = descriptions.len().max(1);
+        let page = page.min(count - 1);
+
+        let description = match descriptions.is_empty() {
+            true => self.empty.clone(),
+            false => descriptions.swap_remove(page),
+        };
+        let embed = CreateEmbed::new()
+            .title(&self.title)
+            .description(description);
+
+        // the components are always set, so that they are removed if the list shrinks to one page
+        CreateInteractionResponseMessage::new()
+            .embed(embed)
+            .components(match count {
+                1 => Vec::new(),
+                _ => Self::components(list, page, count),
+            })
+    }
+
+    /// the buttons and jump menu for a page
+    fn components(list: &str, page: usize, count: usize) -> Vec<CreateActionRow> {
+        let last = count - 1;
+        let button = |action: PageAction, target: usize, label: &str, disabled: bool| {
+            CreateButton::new(PageRequest::custom_id(list, action, target))
+                .label(label)
+                .style(ButtonStyle::Secondary)
+                .disabled(disabled)
+        };
+        let buttons = vec![
+            button(PageAction::First, 0, "⏮", page == 0),
+            button(PageAction::Previous, page.saturating_sub(1), "◀", page == 0),
+            button(
+                PageAction::Current,
+                page,
+                &format!("Page {} of {}", page + 1, count),
+                true,
+            )
+            .style(ButtonStyle::Primary),
+            button(PageAction::Next, (page + 1).min(last), "▶", page == last),
+            button(PageAction::Last, last, "⏭", page == last),
+        ];
+
+        // only the pages around the current one fit in the menu of a long list
+        let first_listed = page
+            .saturating_sub(MAX_JUMP_OPTIONS / 2)
+            .min(count.saturating_sub(MAX_JUMP_OPTIONS));
+        let options = (first_listed..count.min(first_listed + MAX_JUMP_OPTIONS))
+            .map(|p| {
+                CreateSelectMenuOption::new(format!("Page {}", p + 1), p.to_string())
+                    .default_selection(p == page)
+            })
+            .collect();
+        let jump = CreateSelectMenu::new(
+            PageRequest::custom_id(list, PageAction::Jump, page),
+            CreateSelectMenuKind::String { options },
+        )
+        .placeholder("Jump to a page");
+
+        vec![
+            CreateActionRow::Buttons(buttons),
+            CreateActionRow::SelectMenu(jump),
+        ]
+    }
+}
+
+/// cut an entry short with an ellipsis if it has more than `max` characters
+fn truncate(entry: &str, max: usize) -> String {
+    match entry.char_indices().nth(max) {
+        Some(_) => {
+            let end = entry
+                .char_indices()
+                .nth(max - 1)
+                .map_or(entry.len(), |(i, _)| i);
+            format!("{}…", &entry[..end])
+        }
+        None => entry.to_string(),
+    }
+}
+
+/// The component of a paginated list which was used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageAction {
+    /// go to the first page
+    First,
+    /// go back a page
+    Previous,
+    /// the label showing the current page, which can't be clicked
+    Current,
+    /// go forward a page
+    Next,
+    /// go to the last page
+    Last,
+    /// the menu listing the pages to jump to
+    Jump,
+}
+
+impl PageAction {
+    /// every action, used when parsing custom ids
+    const ALL: [Self; 6] = [
+        Self::First,
+        Self::Previous,
+        Self::Current,
+        Self::Next,
+        Self::Last,
+        Self::Jump,
+    ];
+
+    /// the id of the action in custom ids
+    fn id(self) -> &'static str {
+        match self {
+            Self::First => "first",
+            Self::Previous => "prev",
+            Self::Current => "current",
+            Self::Next => "next",
+            Self::Last => "last",
+            Self::Jump => "jump",
+        }
+    }
+}
+
+/// A request to show a page of a list, read from the custom id of the component which was used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageRequest<'a> {
+    /// the name of the command the list belongs to
+    pub list: &'a str,
+    /// the component which was used
+    pub action: PageAction,
+    /// the page to show, which for the jump menu is the page it was shown on
+    pub page: usize,
+}
+
+impl<'a> PageRequest<'a> {
+    /// the custom id of a component, e.g. "page:remind:next:3".
+    /// the action keeps the ids unique, as several buttons can lead to the same page
+    pub fn custom_id(list: &str, action: PageAction, page: usize) -> String {
+        format!("{}:{}:{}:{}", PREFIX, list, action.id(), page)
+    }
+
+    /// read a request from a custom id, if it belongs to a paginated list
+    pub fn parse(custom_id: &'a str) -> Option<Self> {
+        let mut parts = custom_id.split(':');
+        if parts.next()? != PREFIX {
+            return None;
+        }
+        let list = parts.next()?;
+        let action = parts.next()?;
+        let action = PageAction::ALL.into_iter().find(|a| a.id() == action)?;
+        let page = parts.next()?.parse().ok()?;
+        match parts.next() {
+            Some(_) => None,
+            None => Some(Self { list, action, page }),
+        }
+    }
+
+    /// the request made by a component interaction, using the page chosen in the jump menu
+    pub fn of(interaction: &'a ComponentInteraction) -> Option<Self> {
+        let mut request = Self::parse(&interaction.data.custom_id)?;
+        if let ComponentInteractionDataKind::StringSelect { values } = &interaction.data.kind {
+            request.page = values.first()?.parse().ok()?;
+        }
+        Some(request)
+    }
+}
+
+/// whether a component interaction is moving between the pages of a command's list
+pub fn answerable<'a, T: PaginatedResponse<'a>>(interaction: &ComponentInteraction) -> bool {
+    PageRequest::parse(&interaction.data.custom_id).is_some_and(|r| r.list == T::name())
+}
+
+/// rebuild a command's list, and show the page which was asked for in place of the current one
+pub async fn turn_page<'a, T: PaginatedResponse<'a>>(
+    interaction: &ComponentInteraction,
+    app_state: &AppState,
+    context: &Context,
+) -> Result<CommandResponse, CommandResponse> {
+    let request = PageRequest::of(interaction).ok_or_else(|| {
+        CommandResponse::InternalFailure(format!(
+            "invalid page request {}",
+            interaction.data.custom_id
+        ))
+    })?;
+    let pages = T::get_pages(
+        interaction.user.id,
+        interaction.guild_id,
+        app_state,
+        context,
+    )
+    .await?;
+    Ok(CommandResponse::ComplexSuccess(
+        CreateInteractionResponse::UpdateMessage(pages.page(T::name(), request.page)),
+    ))
+}
@@ -28,6 +28,10 @@ impl<'a> Command<'a> for PingCommand {
         "Pings the bot, expect a pong response."
     }
 
+    fn dm_permission() -> bool {
+        true
+    }
+
     fn get_application_command_options(i: CreateCommand) -> CreateCommand {
         i
     }
@@ -0,0 +1,468 @@
+use chrono::Utc;
+use serenity::{
+    all::{AutocompleteOption, CommandInteraction, ComponentInteraction},
+    async_trait,
+    builder::{CreateAutocompleteResponse, CreateInteractionResponse},
+    model::id::{GuildId, UserId},
+    prelude::Context,
+};
+
+use crate::{
+    interaction::{Failure, Invocation, Reply, Respond, Transport},
+    reminders::Recurrence,
+    state::AppState,
+    store::{Feature, Reminder, ReminderTarget},
+    time_parse::ParseContext,
+};
+
+use super::{
+    command::{
+        parse_subcommand, AutocompleteCommand, Command, InteractionCommand, PaginatedResponse,
+        SubcommandSpec,
+    },
+    options::{Options, SlashCommand},
+    pagination::{self, Pages},
+    transport::{respond, Discord},
+    util::{
+        invocation_parse_context, parse_time_option, timezone_autocomplete, CommandResponse,
+        MAX_AUTOCOMPLETE_CHOICES,
+    },
+};
+
+/// the most reminders a single user can have pending at once
+const MAX_REMINDERS_PER_USER: usize = 25;
+
+/// the longest message a reminder can contain
+const MAX_MESSAGE_LENGTH: usize = 1500;
+
+/// create, view and cancel reminders
+pub enum RemindCommand<'a> {
+    /// remind the user after a duration, e.g. "2h30m"
+    In(InOptions<'a>),
+    /// remind the user at a time, e.g. "9am tomorrow"
+    At(AtOptions<'a>),
+    /// remind the user repeatedly, e.g. "every weekday at 09:30"
+    Every(EveryOptions<'a>),
+    /// list the user's pending reminders
+    List,
+    /// cancel one of the user's pending reminders
+    Cancel(u64),
+}
+
+/// the options of `/remind in`
+#[derive(SlashCommand)]
+pub struct InOptions<'a> {
+    /// how long until the reminder is due
+    #[option(
+        description = "How long until the reminder, e.g. \"2h\", \"1 day\" or \"90 minutes\""
+    )]
+    duration: &'a str,
+    /// what to remind the user of
+    #[option(description = "What to remind you of")]
+    message: &'a str,
+    /// whether to deliver the reminder as a direct message
+    #[option(
+        description = "Send the reminder as a direct message instead of in this channel",
+        default
+    )]
+    dm: bool,
+}
+
+/// the options of `/remind at`
+#[derive(SlashCommand)]
+pub struct AtOptions<'a> {
+    /// when the reminder is due
+    #[option(description = "When to remind you, e.g. \"9am tomorrow\" or \"friday 16:30\"")]
+    time: &'a str,
+    /// the timezone the time is in, if not given in the time itself
+    #[option(
+        description = "The timezone the time is in, defaults to your registered timezone",
+        autocomplete
+    )]
+    timezone: Option<&'a str>,
+    /// what to remind the user of
+    #[option(description = "What to remind you of")]
+    message: &'a str,
+    /// whether to deliver the reminder as a direct message
+    #[option(
+        description = "Send the reminder as a direct message instead of in this channel",
+        default
+    )]
+    dm: bool,
+}
+
+/// the options of `/remind every`
+#[derive(SlashCommand)]
+pub struct EveryOptions<'a> {
+    /// the rule describing when the reminder repeats
+    #[option(
+        description = "When to remind you, e.g. \"weekday at 09:30\" or \"first monday of the month\""
+    )]
+    rule: &'a str,
+    /// the timezone the rule is in, if not given in the rule itself
+    #[option(
+        description = "The timezone the rule is in, defaults to your registered timezone",
+        autocomplete
+    )]
+    timezone: Option<&'a str>,
+    /// what to remind the user of
+    #[option(description = "What to remind you of")]
+    message: &'a str,
+    /// whether to deliver the reminder as a direct message
+    #[option(
+        description = "Send the reminder as a direct message instead of in this channel",
+        default
+    )]
+    dm: bool,
+}
+
+/// the options of `/remind cancel`
+#[derive(SlashCommand)]
+pub struct CancelOptions {
+    /// the id of the reminder
+    #[option(description = "The reminder to cancel", min = 0, autocomplete)]
+    id: i64,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for RemindCommand<'a> {
+    type Error = String;
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        Self::parse(Discord::invocation(interaction).options)
+    }
+}
+
+/// describe a pending reminder in a single line
+fn describe(reminder: &Reminder) -> String {
+    let target = match reminder.target {
+        ReminderTarget::Channel(channel_id) => format!("in <#{}>", channel_id),
+        ReminderTarget::DirectMessage => String::from("by direct message"),
+    };
+    let repeats = match &reminder.recurrence {
+        Some(recurrence) => format!(", repeating {}", recurrence),
+        None => String::new(),
+    };
+    format!(
+        "`#{}` <t:{}:f> (<t:{}:R>) {}{}: {}",
+        reminder.id,
+        reminder.due.timestamp(),
+        reminder.due.timestamp(),
+        target,
+        repeats,
+        reminder.message
+    )
+}
+
+impl RemindCommand<'_> {
+    /// store a new reminder for the calling user, which they created at `now`
+    async fn create(
+        invocation: &Invocation<'_>,
+        app_state: &AppState,
+        now: chrono::DateTime<Utc>,
+        due: chrono::DateTime<Utc>,
+        message: &str,
+        dm: bool,
+        recurrence: Option<Recurrence>,
+    ) -> Result<Reply, Failure> {
+        let user_id = invocation.user;
+        if due <= now {
+            return Err(Failure::Refused(format!(
+                "<t:{}:f> is in the past, I can only remind you of things in the future",
+                due.timestamp()
+            )));
+        }
+        let message = message.trim();
+        let message = message.strip_prefix("to ").unwrap_or(message);
+        if message.chars().count() > MAX_MESSAGE_LENGTH {
+            return Err(Failure::Refused(format!(
+                "Reminders can be at most {} characters long",
+                MAX_MESSAGE_LENGTH
+            )));
+        }
+        if app_state.user_reminders(user_id).await.len() >= MAX_REMINDERS_PER_USER {
+            return Err(Failure::Refused(format!(
+                "You already have {} reminders, cancel some with `/remind cancel` first",
+                MAX_REMINDERS_PER_USER
+            )));
+        }
+
+        // reminders set outside of a guild can only be delivered as direct messages
+        let target = match (invocation.guild, invocation.channel) {
+            (Some(_), Some(channel_id)) if !dm => ReminderTarget::Channel(channel_id),
+            _ => ReminderTarget::DirectMessage,
+        };
+
+        let reminder = app_state
+            .add_reminder(
+                user_id,
+                target,
+                due,
+                now,
+                message.to_string(),
+                recurrence.clone(),
+            )
+            .await
+            .map_err(|e| Failure::Error {
+                response: String::from("Failed to save your reminder, please try again"),
+                log_message: format!("failed to save reminder for {}: {}", user_id, e),
+            })?;
+
+        let repeats = match recurrence {
+            Some(recurrence) => format!(", and then {}", recurrence),
+            None => String::new(),
+        };
+        Ok(Reply::text(format!(
+            "I'll remind you <t:{}:R>, at <t:{}:f>{} (reminder `#{}`)",
+            due.timestamp(),
+            due.timestamp(),
+            repeats,
+            reminder.id
+        )))
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for RemindCommand<'a> {
+    fn name() -> &'static str {
+        "remind"
+    }
+
+    fn description() -> &'static str {
+        "Set, view and cancel reminders"
+    }
+
+    fn dm_permission() -> bool {
+        true
+    }
+
+    fn feature() -> Option<Feature> {
+        Some(Feature::Reminders)
+    }
+
+    fn subcommands() -> Vec<SubcommandSpec<'a, Self>> {
+        vec![
+            SubcommandSpec::new("in", "Remind you after an amount of time", |options| {
+                Ok(Self::In(InOptions::parse(options)?))
+            })
+            .options(InOptions::options()),
+            SubcommandSpec::new("at", "Remind you at a specific time", |options| {
+                Ok(Self::At(AtOptions::parse(options)?))
+            })
+            .options(AtOptions::options()),
+            SubcommandSpec::new("every", "Remind you repeatedly", |options| {
+                Ok(Self::Every(EveryOptions::parse(options)?))
+            })
+            .options(EveryOptions::options()),
+            SubcommandSpec::new("list", "List your pending reminders", |_| Ok(Self::List)),
+            SubcommandSpec::new(
+                "cancel",
+                "Cancel one of your pending reminders",
+                |options| {
+                    Ok(Self::Cancel(
+                        u64::try_from(CancelOptions::parse(options)?.id)
+                            .map_err(|_| String::from("Reminder ids can't be negative"))?,
+                    ))
+                },
+            )
+            .options(CancelOptions::options()),
+        ]
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        context: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        match self {
+            Self::List => {
+                let pages = Self::get_pages(
+                    interaction.user.id,
+                    interaction.guild_id,
+                    app_state,
+                    context,
+                )
+                .await?;
+                Ok(CommandResponse::ComplexSuccess(
+                    CreateInteractionResponse::Message(pages.page(Self::name(), 0).ephemeral(true)),
+                ))
+            }
+            command => respond(command, interaction, app_state).await,
+        }
+    }
+}
+
+#[async_trait]
+impl<'a> Respond<'a> for RemindCommand<'a> {
+    fn parse(options: Options<'a>) -> Result<Self, String> {
+        parse_subcommand(options)
+    }
+
+    async fn respond(
+        self,
+        invocation: &Invocation<'_>,
+        app_state: &AppState,
+    ) -> Result<Reply, Failure> {
+        let user_id = invocation.user;
+        match self {
+            Self::In(InOptions {
+                duration,
+                message,
+                dm,
+            }) => {
+                let duration = duration.trim();
+                let duration = duration.strip_prefix("in ").unwrap_or(duration);
+                let context = ParseContext::new(Utc::now(), None);
+                let parsed = parse_time_option(&format!("in {}", duration), &context)
+                    .map_err(Failure::Refused)?;
+                if !parsed.relative {
+                    return Err(Failure::Refused(format!(
+                        "\"{}\" isn't an amount of time, try something like \"2h\" or use `/remind at`",
+                        duration
+                    )));
+                }
+                Self::create(
+                    invocation,
+                    app_state,
+                    context.now,
+                    parsed.time.with_timezone(&Utc),
+                    message,
+                    dm,
+                    None,
+                )
+                .await
+            }
+            Self::At(AtOptions {
+                time,
+                timezone,
+                message,
+                dm,
+            }) => {
+                let context = invocation_parse_context(invocation, app_state, timezone)
+                    .await
+                    .map_err(Failure::Refused)?
+                    .prefer_future();
+                let parsed = parse_time_option(time, &context).map_err(Failure::Refused)?;
+                Self::create(
+                    invocation,
+                    app_state,
+                    context.now,
+                    parsed.time.with_timezone(&Utc),
+                    message,
+                    dm,
+                    None,
+                )
+                .await
+            }
+            Self::Every(EveryOptions {
+                rule,
+                timezone,
+                message,
+                dm,
+            }) => {
+                let timezone = invocation_parse_context(invocation, app_state, timezone)
+                    .await
+                    .map_err(Failure::Refused)?
+                    .default_tz;
+                let rule = rule.trim();
+                let rule = rule.strip_prefix("every ").unwrap_or(rule);
+                let recurrence = Recurrence::parse(rule, timezone).map_err(Failure::Refused)?;
+                let now = Utc::now();
+                let due = recurrence
+                    .next_occurrence(now)
+                    .ok_or_else(|| Failure::Refused(format!("\"{}\" never happens", rule)))?;
+                Self::create(
+                    invocation,
+                    app_state,
+                    now,
+                    due,
+                    message,
+                    dm,
+                    Some(recurrence),
+                )
+                .await
+            }
+            // the list is paginated with buttons, which only discord can show
+            Self::List => Err(Failure::Refused(String::from(
+                "Reminders can only be listed from discord",
+            ))),
+            Self::Cancel(id) => match app_state.cancel_reminder(user_id, id).await {
+                Ok(Some(reminder)) => Ok(Reply::text(format!(
+                    "Cancelled reminder {}",
+                    describe(&reminder)
+                ))),
+                Ok(None) => Err(Failure::Refused(format!(
+                    "You don't have a reminder `#{}`, use `/remind list` to see your reminders",
+                    id
+                ))),
+                Err(e) => Err(Failure::Error {
+                    response: String::from("Failed to cancel your reminder, please try again"),
+                    log_message: format!("failed to cancel reminder for {}: {}", user_id, e),
+                }),
+            },
+        }
+    }
+}
+
+#[async_trait]
+impl<'a> AutocompleteCommand<'a> for RemindCommand<'a> {
+    async fn autocomplete<'c>(
+        interaction: &'c CommandInteraction,
+        option: &'c AutocompleteOption,
+        app_state: &'c AppState,
+        _: &'c Context,
+    ) -> Result<CreateAutocompleteResponse, CommandResponse> {
+        match option.name {
+            "id" => Ok(app_state
+                .user_reminders(interaction.user.id.into())
+                .await
+                .into_iter()
+                .filter(|r| r.id.to_string().starts_with(option.value.trim()))
+                .take(MAX_AUTOCOMPLETE_CHOICES)
+                .fold(CreateAutocompleteResponse::new(), |resp, r| {
+                    let mut name = format!("#{}: {}", r.id, r.message);
+                    // discord rejects choices longer than 100 characters
+                    if name.chars().count() > 100 {
+                        name = name.chars().take(97).chain("...".chars()).collect();
+                    }
+                    resp.add_int_choice(name, r.id as i64)
+                })),
+            _ => Ok(timezone_autocomplete(option.value)),
+        }
+    }
+}
+
+#[async_trait]
+impl<'a> InteractionCommand<'a> for RemindCommand<'a> {
+    async fn answerable<'b>(
+        interaction: &'b ComponentInteraction,
+        _: &'b AppState,
+        _: &'b Context,
+    ) -> bool {
+        pagination::answerable::<Self>(interaction)
+    }
+
+    async fn interaction<'b>(
+        interaction: &'b ComponentInteraction,
+        app_state: &'b AppState,
+        context: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        pagination::turn_page::<Self>(interaction, app_state, context).await
+    }
+}
+
+#[async_trait]
+impl<'a> PaginatedResponse<'a> for RemindCommand<'a> {
+    async fn get_pages<'b>(
+        user: UserId,
+        _: Option<GuildId>,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<Pages, CommandResponse> {
+        let reminders = app_state.user_reminders(user.into()).await;
+        Ok(Pages::new(
+            "Your pending reminders",
+            reminders.iter().map(describe).collect(),
+        )
+        .empty("You don't have any pending reminders"))
+    }
+}
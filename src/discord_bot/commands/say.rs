@@ -1,39 +1,28 @@
 use serenity::{
-    all::{CommandInteraction, CommandOptionType},
+    all::CommandInteraction,
     async_trait,
-    builder::{
-        CreateCommand, CreateCommandOption, CreateInteractionResponse,
-        CreateInteractionResponseMessage, CreateMessage,
-    },
+    builder::{CreateCommand, CreateMessage},
+    model::Permissions,
     prelude::Context,
 };
 
-use crate::state::AppState;
-
-use super::{
-    command::Command,
-    util::{CommandResponse, FailureMessageKind},
+use crate::{
+    interaction::{Failure, Reply, Transport},
+    state::AppState,
 };
 
+use super::{command::Command, options::SlashCommand, transport::Discord, util::CommandResponse};
+
+#[derive(SlashCommand)]
 pub struct SayCommand<'a> {
+    #[option(
+        name = "text",
+        description = "What you want the bot to say",
+        max_length = 1900
+    )]
     message: &'a str,
 }
 
-impl<'a> TryFrom<&'a CommandInteraction> for SayCommand<'a> {
-    type Error = String;
-    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
-        let message = interaction
-            .data
-            .options
-            .get(0)
-            .ok_or("No message provided")?
-            .value
-            .as_str()
-            .ok_or("No message provided")?;
-        Ok(Self { message })
-    }
-}
-
 #[async_trait]
 impl<'a> Command<'a> for SayCommand<'a> {
     fn name() -> &'static str {
@@ -44,17 +33,12 @@ impl<'a> Command<'a> for SayCommand<'a> {
         "Says whatever you want!"
     }
 
+    fn default_permissions() -> Option<Permissions> {
+        Some(Permissions::MANAGE_MESSAGES)
+    }
+
     fn get_application_command_options(i: CreateCommand) -> CreateCommand {
-        i.add_option(
-            CreateCommandOption::new(
-                CommandOptionType::String,
-                "text",
-                "What you want the bot to say",
-            )
-            .required(true)
-            .max_length(1900)
-            .to_owned(),
-        )
+        Self::register_options(i)
     }
 
     async fn handle_application_command<'b>(
@@ -68,19 +52,12 @@ impl<'a> Command<'a> for SayCommand<'a> {
             .send_message(ctx, CreateMessage::new().content(self.message))
             .await
         {
-            return Err(CommandResponse::ComplexFailure {
+            return Discord::deliver(Err(Failure::Error {
                 response: String::from("Failed to use /say due to error"),
-                kind: FailureMessageKind::Error,
                 log_message: e.to_string(),
-            });
+            }));
         }
 
-        Ok(CommandResponse::ComplexSuccess(
-            CreateInteractionResponse::Message(
-                CreateInteractionResponseMessage::new()
-                    .content(format!("I will send: {}", self.message))
-                    .ephemeral(true),
-            ),
-        ))
+        Discord::deliver(Ok(Reply::text(format!("I will send: {}", self.message))))
     }
 }
@@ -0,0 +1,674 @@
+use std::{collections::HashMap, sync::Arc};
+
+use chrono::{Duration, NaiveTime, TimeZone, Utc, Weekday};
+use serde_json::{json, Value};
+use serenity::{
+    builder::{CreateCommand, CreateCommandOption},
+    model::{
+        id::{ChannelId, UserId},
+        Permissions,
+    },
+};
+
+use super::{
+    application_command,
+    command::parse_subcommand,
+    config::{ClockOptions, ConfigCommand, RoleChange},
+    convert::ConvertCommand,
+    global_application_command,
+    hours::{self, parse_day_hours, parse_days, parse_days_off, parse_range},
+    meeting::FindOptions,
+    options::SlashCommand,
+    pagination::{PageAction, PageRequest, Pages},
+    remind::RemindCommand,
+    time::TimeCommand,
+    timestamp::TimestampCommand,
+    timezone::TimezoneCommand,
+};
+use crate::{
+    interaction::{self, Failure, Invocation, Options, Reply, Respond},
+    state::AppState,
+    store::{DayHours, MemoryStore, ReminderTarget, TimeRange, WorkingHours},
+};
+
+const GUILD: u64 = 10;
+const ALICE: u64 = 30;
+const BOB: u64 = 31;
+const CHANNEL: u64 = 20;
+
+/// the options as they are sent to discord
+fn registered(options: Vec<CreateCommandOption>) -> Vec<Value> {
+    options
+        .into_iter()
+        .map(|o| serde_json::to_value(o).expect("options can be serialized"))
+        .collect()
+}
+
+#[test]
+fn derived_options_are_registered_with_bounds() {
+    let options = registered(FindOptions::options());
+    let names = options.iter().map(|o| &o["name"]).collect::<Vec<_>>();
+    assert_eq!(names, ["participants", "duration", "days"]);
+
+    assert_eq!(options[0]["required"], true);
+    assert_eq!(options[2]["type"], 4);
+    assert_eq!(options[2]["min_value"], 1);
+    assert_eq!(options[2]["max_value"], 14);
+    assert_eq!(options[2]["required"], false);
+}
+
+/// a command with an option of each kind, to test the parsing derived for it
+#[derive(SlashCommand)]
+struct RollCommand<'a> {
+    #[option(description = "The dice to roll")]
+    dice: &'a str,
+    #[option(description = "How many times to roll", min = 1, max = 10)]
+    times: Option<i64>,
+    #[option(description = "Show every roll", default)]
+    verbose: bool,
+}
+
+#[test]
+fn derived_options_are_parsed() {
+    let roll = RollCommand::parse(
+        Options::default()
+            .with("dice", interaction::Value::Text("2d6"))
+            .with("times", interaction::Value::Integer(3))
+            .with("verbose", interaction::Value::Boolean(true)),
+    )
+    .unwrap_or_else(|e| panic!("the options should parse: {}", e));
+    assert_eq!(roll.dice, "2d6");
+    assert_eq!(roll.times, Some(3));
+    assert!(roll.verbose);
+}
+
+#[test]
+fn derived_options_fall_back_to_their_defaults() {
+    let roll = RollCommand::parse(Options::default().with("dice", interaction::Value::Text("2d6")))
+        .unwrap_or_else(|e| panic!("the options should parse: {}", e));
+    assert_eq!(roll.times, None);
+    assert!(!roll.verbose);
+}
+
+#[test]
+fn derived_options_must_be_provided_with_the_right_type() {
+    let missing =
+        RollCommand::parse(Options::default().with("times", interaction::Value::Integer(3)));
+    assert_eq!(missing.err(), Some(String::from("No dice provided")));
+
+    let wrong_type = RollCommand::parse(
+        Options::default()
+            .with("dice", interaction::Value::Text("2d6"))
+            .with("times", interaction::Value::Text("three")),
+    );
+    assert_eq!(
+        wrong_type.err(),
+        Some(String::from("The times option should be a whole number"))
+    );
+}
+
+#[test]
+fn derived_options_are_registered_with_choices() {
+    let options = registered(ClockOptions::options());
+    assert_eq!(options[0]["type"], 3);
+    assert_eq!(
+        options[0]["choices"],
+        json!([
+            { "name": "12 hour", "value": "12" },
+            { "name": "24 hour", "value": "24" },
+        ])
+    );
+}
+
+/// a command with repeated options, and options limited to choices or kinds of channel
+#[derive(SlashCommand)]
+struct GameCommand<'a> {
+    #[option(
+        description = "A player",
+        repeat(count = 3, description = "Another player")
+    )]
+    players: Vec<UserId>,
+    #[option(
+        description = "The channel to play in",
+        channel_types(Text, PublicThread)
+    )]
+    channel: Option<ChannelId>,
+    #[option(
+        description = "The size of the board",
+        choices = ["small", "large"].map(|size| (size.to_uppercase(), size))
+    )]
+    board: Option<&'a str>,
+}
+
+#[test]
+fn repeated_options_are_registered_after_the_required_options() {
+    let options = registered(GameCommand::options());
+    let names = options.iter().map(|o| &o["name"]).collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        ["players", "channel", "board", "players2", "players3"]
+    );
+
+    assert_eq!(options[0]["required"], true);
+    assert_eq!(options[0]["description"], "A player");
+    assert_eq!(options[3]["required"], false);
+    assert_eq!(options[3]["description"], "Another player");
+    assert_eq!(options[1]["channel_types"], json!([0, 11]));
+    assert_eq!(
+        options[2]["choices"],
+        json!([
+            { "name": "SMALL", "value": "small" },
+            { "name": "LARGE", "value": "large" },
+        ])
+    );
+}
+
+#[test]
+fn repeated_options_are_read_from_each_option_provided() {
+    let game = GameCommand::parse(
+        Options::default()
+            .with("players", interaction::Value::User(ALICE))
+            .with("players3", interaction::Value::User(BOB)),
+    )
+    .unwrap_or_else(|e| panic!("the options should parse: {}", e));
+    assert_eq!(game.players, [UserId::new(ALICE), UserId::new(BOB)]);
+    assert_eq!(game.channel, None);
+    assert_eq!(game.board, None);
+
+    let missing =
+        GameCommand::parse(Options::default().with("players2", interaction::Value::User(ALICE)));
+    assert_eq!(missing.err(), Some(String::from("No players provided")));
+}
+
+#[test]
+fn page_requests_round_trip_through_custom_ids() {
+    let id = PageRequest::custom_id("remind", PageAction::Next, 3);
+    assert_eq!(id, "page:remind:next:3");
+    assert_eq!(
+        PageRequest::parse(&id),
+        Some(PageRequest {
+            list: "remind",
+            action: PageAction::Next,
+            page: 3,
+        })
+    );
+
+    assert_eq!(PageRequest::parse("hours-modal"), None);
+    assert_eq!(PageRequest::parse("page:remind:sideways:3"), None);
+    assert_eq!(PageRequest::parse("page:remind:next:3:extra"), None);
+}
+
+#[test]
+fn pages_are_clamped_to_the_list() {
+    let entries = (1..=25).map(|n| n.to_string()).collect::<Vec<_>>();
+    let pages = Pages::new("Numbers", entries);
+
+    // the list may have shrunk since the buttons were made, so late pages show the last one
+    let page = serde_json::to_value(pages.page("numbers", 7)).expect("pages can be serialized");
+    assert_eq!(page["embeds"][0]["description"], "21\n22\n23\n24\n25");
+    let buttons = &page["components"][0]["components"];
+    assert_eq!(buttons[2]["label"], "Page 3 of 3");
+    assert_eq!(buttons[3]["disabled"], true);
+
+    // a single page has no buttons to move between pages
+    let empty = Pages::new("Numbers", Vec::new()).empty("No numbers");
+    let page = serde_json::to_value(empty.page("numbers", 0)).expect("pages can be serialized");
+    assert_eq!(page["embeds"][0]["description"], "No numbers");
+    assert_eq!(page["components"], json!([]));
+}
+
+#[test]
+fn pages_are_cut_short_to_fit_in_an_embed() {
+    let entries = (0..10).map(|_| "a".repeat(1000)).collect::<Vec<_>>();
+    let pages = Pages::new("Long", entries);
+
+    let page = serde_json::to_value(pages.page("long", 0)).expect("pages can be serialized");
+    let description = page["embeds"][0]["description"].as_str().unwrap();
+    assert_eq!(description.lines().count(), 4);
+    assert_eq!(
+        page["components"][0]["components"][2]["label"],
+        "Page 1 of 3"
+    );
+
+    let pages = Pages::new("Long", vec![String::from("short"), "é".repeat(5000)]);
+    let page = serde_json::to_value(pages.page("long", 1)).expect("pages can be serialized");
+    assert_eq!(
+        page["components"][0]["components"][2]["label"],
+        "Page 2 of 2"
+    );
+    let description = page["embeds"][0]["description"].as_str().unwrap();
+    assert_eq!(description.chars().count(), 4096);
+    assert!(description.ends_with('…'));
+}
+
+#[test]
+fn context_menu_commands_are_registered_without_descriptions() {
+    let commands = application_command()
+        .into_iter()
+        .chain(global_application_command())
+        .map(|c| serde_json::to_value(c).expect("commands can be serialized"))
+        .collect::<Vec<_>>();
+    let find = |name: &str| {
+        commands
+            .iter()
+            .find(|c| c["name"] == name)
+            .unwrap_or_else(|| panic!("{} is registered", name))
+    };
+
+    let message = find("Convert times in this message");
+    assert_eq!(message["type"], 3);
+    assert_eq!(message.get("description"), None);
+
+    let user = find("Show user's local time");
+    assert_eq!(user["type"], 2);
+    assert_eq!(user.get("description"), None);
+
+    assert_eq!(find("time")["type"], 1);
+    assert!(find("time")["description"].is_string());
+}
+
+#[test]
+fn commands_posting_in_channels_need_manage_messages() {
+    let commands = application_command()
+        .into_iter()
+        .map(|c| serde_json::to_value(c).expect("commands can be serialized"))
+        .collect::<Vec<_>>();
+    let permissions = |name: &str| {
+        commands
+            .iter()
+            .find(|c| c["name"] == name)
+            .unwrap_or_else(|| panic!("{} is registered", name))["default_member_permissions"]
+            .clone()
+    };
+
+    let manage_messages = Permissions::MANAGE_MESSAGES.bits().to_string();
+    assert_eq!(permissions("countdown"), manage_messages.as_str());
+    assert_eq!(permissions("worldclock"), manage_messages.as_str());
+    assert_eq!(permissions("meeting"), Value::Null);
+}
+
+#[test]
+fn personal_commands_are_registered_globally() {
+    // whether each command can be used in direct messages (context 1), by name
+    let registered = |commands: Vec<CreateCommand>| {
+        commands
+            .into_iter()
+            .map(|c| serde_json::to_value(c).expect("commands can be serialized"))
+            .map(|c| {
+                (
+                    c["name"].as_str().unwrap().to_string(),
+                    c["contexts"]
+                        .as_array()
+                        .is_some_and(|contexts| contexts.contains(&json!(1))),
+                )
+            })
+            .collect::<HashMap<_, _>>()
+    };
+    let guild = registered(application_command());
+    let global = registered(global_application_command());
+
+    assert_eq!(guild.get("config"), Some(&false));
+    assert_eq!(global.get("timezone"), Some(&true));
+    assert_eq!(global.get("Convert times in this message"), Some(&true));
+    assert!(guild.values().all(|dm| !dm) && global.values().all(|dm| *dm));
+    assert!(guild.keys().all(|name| !global.contains_key(name)));
+}
+
+/// state kept in memory, for commands used without discord
+async fn app_state() -> AppState {
+    AppState::new(Arc::new(MemoryStore::default()))
+        .await
+        .unwrap()
+}
+
+/// read a command from an invocation and carry it out
+async fn respond<'a, T: Respond<'a>>(
+    invocation: &Invocation<'a>,
+    app_state: &AppState,
+) -> Result<Reply, Failure> {
+    T::parse(invocation.options.clone())?
+        .respond(invocation, app_state)
+        .await
+}
+
+#[tokio::test]
+async fn registered_timezones_are_shown_to_other_users() {
+    let app_state = app_state().await;
+    let set = Invocation::new(ALICE).guild(GUILD).option(
+        "set",
+        interaction::Value::Subcommand(
+            Options::default().with("zone", interaction::Value::Text("london")),
+        ),
+    );
+    let reply = respond::<TimezoneCommand>(&set, &app_state).await.unwrap();
+    assert!(reply
+        .content
+        .unwrap()
+        .starts_with("Your timezone is now Europe/London"));
+
+    let time = Invocation::new(BOB)
+        .guild(GUILD)
+        .option("user", interaction::Value::User(ALICE));
+    let reply = respond::<TimeCommand>(&time, &app_state).await.unwrap();
+    assert!(reply
+        .content
+        .unwrap()
+        .contains(&format!("for <@{}> (Europe/London", ALICE)));
+
+    let own = Invocation::new(BOB);
+    assert_eq!(
+        respond::<TimeCommand>(&own, &app_state).await,
+        Err(Failure::Refused(String::from(
+            "You haven't registered a timezone yet, use `/timezone set` to add one"
+        )))
+    );
+}
+
+#[tokio::test]
+async fn times_are_converted_from_the_callers_timezone() {
+    let app_state = app_state().await;
+    app_state
+        .set_user_timezone(ALICE, chrono_tz::America::New_York)
+        .await
+        .unwrap();
+
+    let convert = Invocation::new(ALICE)
+        .option("time", interaction::Value::Text("2026-01-15 15:00"))
+        .option("to", interaction::Value::Text("Europe/London"))
+        .option("to2", interaction::Value::Text("Asia/Tokyo"));
+    let reply = respond::<ConvertCommand>(&convert, &app_state)
+        .await
+        .unwrap();
+    assert!(!reply.public);
+
+    let embed = &reply.embeds[0];
+    assert_eq!(
+        embed.title.as_deref(),
+        Some("3:00pm on Thu 15 Jan 2026 in America/New_York")
+    );
+    let fields = embed
+        .fields
+        .iter()
+        .map(|(name, value, _)| (name.as_str(), value.lines().next().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        fields,
+        [
+            ("Europe/London", "**8:00pm Thu 15 Jan**"),
+            ("Asia/Tokyo", "**5:00am Fri 16 Jan** (+1 day)"),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn unknown_timezones_are_refused() {
+    let app_state = app_state().await;
+    let convert = Invocation::new(ALICE)
+        .option("time", interaction::Value::Text("3pm UTC"))
+        .option("to", interaction::Value::Text("Atlantis"));
+    assert_eq!(
+        respond::<ConvertCommand>(&convert, &app_state).await,
+        Err(Failure::Refused(String::from(
+            "I don't know the timezone \"Atlantis\", try picking one of the suggestions"
+        )))
+    );
+
+    let missing = Invocation::new(ALICE).option("time", interaction::Value::Text("3pm UTC"));
+    assert_eq!(
+        respond::<ConvertCommand>(&missing, &app_state).await,
+        Err(Failure::Refused(String::from("No to provided")))
+    );
+}
+
+#[tokio::test]
+async fn timestamps_are_generated_in_every_style() {
+    let app_state = app_state().await;
+    let timestamp = Invocation::new(ALICE)
+        .option("time", interaction::Value::Text("2026-01-15 15:00"))
+        .option("timezone", interaction::Value::Text("UTC"));
+    let reply = respond::<TimestampCommand>(&timestamp, &app_state)
+        .await
+        .unwrap();
+
+    let unix = Utc
+        .with_ymd_and_hms(2026, 1, 15, 15, 0, 0)
+        .unwrap()
+        .timestamp();
+    let embed = &reply.embeds[0];
+    assert_eq!(embed.fields.len(), 7);
+    assert_eq!(
+        embed.fields[0],
+        (
+            String::from("Short time"),
+            format!("<t:{}:t>\n`<t:{}:t>`", unix, unix),
+            true
+        )
+    );
+    assert_eq!(embed.fields[6].0, "Relative");
+}
+
+#[test]
+fn subcommands_are_read_by_the_parser_they_were_declared_with() {
+    let set = Options::default().with(
+        "set",
+        interaction::Value::Subcommand(
+            Options::default().with("zone", interaction::Value::Text("london")),
+        ),
+    );
+    assert!(matches!(
+        parse_subcommand::<TimezoneCommand>(set),
+        Ok(TimezoneCommand::Set("london"))
+    ));
+
+    let clear = Options::default().with(
+        "roles",
+        interaction::Value::SubcommandGroup(Options::default().with(
+            "clear",
+            interaction::Value::Subcommand(
+                Options::default().with("command", interaction::Value::Text("remind")),
+            ),
+        )),
+    );
+    assert!(matches!(
+        parse_subcommand::<ConfigCommand>(clear),
+        Ok(ConfigCommand::Roles("remind", RoleChange::Clear))
+    ));
+}
+
+#[test]
+fn undeclared_subcommands_are_rejected() {
+    let unknown =
+        Options::default().with("rename", interaction::Value::Subcommand(Options::default()));
+    assert_eq!(
+        parse_subcommand::<TimezoneCommand>(unknown).err(),
+        Some(String::from("Unknown subcommand rename"))
+    );
+
+    let ungrouped = Options::default().with(
+        "clear",
+        interaction::Value::Subcommand(
+            Options::default().with("command", interaction::Value::Text("remind")),
+        ),
+    );
+    assert_eq!(
+        parse_subcommand::<ConfigCommand>(ungrouped).err(),
+        Some(String::from("Unknown subcommand clear"))
+    );
+}
+
+/// a local time of day
+fn hm(hour: u32, minute: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+}
+
+#[test]
+fn working_days_are_parsed_as_lists_and_ranges() {
+    use Weekday::*;
+    assert_eq!(parse_days("mon-fri"), Some(vec![Mon, Tue, Wed, Thu, Fri]));
+    assert_eq!(parse_days("Mon,wed, fri"), Some(vec![Mon, Wed, Fri]));
+    assert_eq!(parse_days("sat-mon"), Some(vec![Sat, Sun, Mon]));
+    assert_eq!(parse_days("weekends"), Some(vec![Sat, Sun]));
+    assert_eq!(parse_days("mon-someday"), None);
+}
+
+#[test]
+fn working_hours_are_parsed_in_either_clock() {
+    assert_eq!(
+        parse_range("9am-5:30pm"),
+        Some(TimeRange {
+            start: hm(9, 0),
+            end: hm(17, 30)
+        })
+    );
+    assert_eq!(
+        parse_range("22:00-06:00"),
+        Some(TimeRange {
+            start: hm(22, 0),
+            end: hm(6, 0)
+        })
+    );
+    assert_eq!(parse_range("09:00-09:00"), None);
+    assert_eq!(parse_range("09:00"), None);
+    assert_eq!(parse_range("nine-five"), None);
+}
+
+#[test]
+fn hours_for_specific_days_are_parsed_a_line_at_a_time() {
+    assert_eq!(
+        parse_day_hours("fri 09:00-15:00\n\n sat 10am-2pm"),
+        Ok(vec![
+            DayHours {
+                day: Weekday::Fri,
+                hours: TimeRange {
+                    start: hm(9, 0),
+                    end: hm(15, 0)
+                }
+            },
+            DayHours {
+                day: Weekday::Sat,
+                hours: TimeRange {
+                    start: hm(10, 0),
+                    end: hm(14, 0)
+                }
+            },
+        ])
+    );
+    assert!(parse_day_hours("fri").is_err());
+    assert!(parse_day_hours("someday 09:00-15:00").is_err());
+}
+
+#[test]
+fn days_off_are_expanded_and_past_days_dropped() {
+    let today = Utc::now().date_naive();
+    let format = |days: i64| {
+        (today + Duration::days(days))
+            .format("%Y-%m-%d")
+            .to_string()
+    };
+
+    assert_eq!(
+        parse_days_off(&format!("{}, {} to {}", format(10), format(2), format(4))),
+        Ok(vec![
+            today + Duration::days(2),
+            today + Duration::days(3),
+            today + Duration::days(4),
+            today + Duration::days(10),
+        ])
+    );
+    assert_eq!(parse_days_off("2000-01-01 to 2000-01-05"), Ok(vec![]));
+    assert!(parse_days_off("2026-02-30").is_err());
+    assert!(parse_days_off("2026-12-24 to christmas").is_err());
+    assert!(parse_days_off("2000-01-01 to 9999-12-31").is_err());
+}
+
+#[test]
+fn the_most_days_off_fit_in_the_form() {
+    let today = Utc::now().date_naive();
+    let days_off = (0..hours::MAX_DAYS_OFF as i64)
+        .map(|d| today + Duration::days(d))
+        .collect();
+    let form = serde_json::to_value(hours::modal(&WorkingHours {
+        days_off,
+        ..WorkingHours::default()
+    }))
+    .unwrap();
+
+    let inputs: Vec<&Value> = form["components"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .flat_map(|row| row["components"].as_array().unwrap())
+        .collect();
+    let days_off = inputs
+        .iter()
+        .find(|input| input["custom_id"] == "days_off")
+        .unwrap();
+    assert!(days_off["value"].as_str().unwrap().len() <= 4000);
+    for input in inputs {
+        assert!(input["label"].as_str().unwrap().len() <= 45);
+    }
+}
+
+#[tokio::test]
+async fn automatic_conversion_is_configured_in_the_current_channel() {
+    let app_state = app_state().await;
+    let autoconvert = Invocation::new(ALICE).guild(GUILD).channel(CHANNEL).option(
+        "autoconvert",
+        interaction::Value::Subcommand(
+            Options::default().with("enabled", interaction::Value::Boolean(true)),
+        ),
+    );
+    let reply = respond::<ConfigCommand>(&autoconvert, &app_state)
+        .await
+        .unwrap();
+    assert_eq!(
+        reply.content.as_deref(),
+        Some("Automatic time conversion is now enabled in <#20>")
+    );
+    assert!(app_state
+        .guild_config(GUILD)
+        .await
+        .auto_convert_channels
+        .contains(&CHANNEL));
+
+    let direct =
+        Invocation::new(ALICE).option("show", interaction::Value::Subcommand(Options::default()));
+    assert_eq!(
+        respond::<ConfigCommand>(&direct, &app_state).await,
+        Err(Failure::Refused(String::from(
+            "This command can only be used in a server"
+        )))
+    );
+}
+
+#[tokio::test]
+async fn reminders_are_delivered_where_they_were_set() {
+    let app_state = app_state().await;
+    let reminder = |dm| {
+        Invocation::new(ALICE).guild(GUILD).channel(CHANNEL).option(
+            "in",
+            interaction::Value::Subcommand(
+                Options::default()
+                    .with("duration", interaction::Value::Text("2h"))
+                    .with("message", interaction::Value::Text("to stretch"))
+                    .with("dm", interaction::Value::Boolean(dm)),
+            ),
+        )
+    };
+    respond::<RemindCommand>(&reminder(false), &app_state)
+        .await
+        .unwrap();
+    respond::<RemindCommand>(&reminder(true), &app_state)
+        .await
+        .unwrap();
+
+    let reminders = app_state.user_reminders(ALICE).await;
+    assert_eq!(
+        reminders.iter().map(|r| r.target).collect::<Vec<_>>(),
+        [
+            ReminderTarget::Channel(CHANNEL),
+            ReminderTarget::DirectMessage
+        ]
+    );
+    assert_eq!(reminders[0].message, "stretch");
+}
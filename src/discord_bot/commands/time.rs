@@ -1,65 +1,156 @@
-use std::str::FromStr;
-
 use serenity::{
-    all::CommandInteraction,
+    all::{AutocompleteOption, CommandInteraction},
     async_trait,
-    builder::{CreateCommand, CreateCommandOption},
+    builder::{CreateAutocompleteResponse, CreateCommand},
     prelude::Context,
 };
 
-use crate::state::AppState;
+use crate::{
+    interaction::{Failure, Invocation, Options, Reply, Respond, UserRef},
+    state::AppState,
+    timezones,
+};
+
+use super::{
+    command::{AutocompleteCommand, Command},
+    options::SlashCommand,
+    transport::respond,
+    util::{guild_config, resolve_timezone_option, timezone_autocomplete, CommandResponse},
+};
+
+#[derive(SlashCommand)]
+pub struct TimeCommand<'a> {
+    /// the location to get the time for
+    #[option(
+        description = "The timezone, city or country to get the time for",
+        autocomplete
+    )]
+    location: Option<&'a str>,
+    /// the user to get the local time of
+    #[option(description = "The user to get the local time of")]
+    user: Option<UserRef>,
+}
 
-use super::{command::Command, util::CommandResponse};
+impl TimeCommand<'_> {
+    /// describe the current local time of a user, including whether they are within their working hours
+    async fn user_time(
+        user: u64,
+        invocation: &Invocation<'_>,
+        app_state: &AppState,
+    ) -> Result<Reply, Failure> {
+        let is_caller = user == invocation.user;
+        let time_format = guild_config(invocation.guild, app_state)
+            .await
+            .clock
+            .time_format();
 
-pub struct TimeCommand;
+        let profile = app_state.user_profile(user).await.unwrap_or_default();
+        let tz = match profile.timezone {
+            Some(tz) => tz,
+            None if is_caller => {
+                return Err(Failure::Refused(String::from(
+                    "You haven't registered a timezone yet, use `/timezone set` to add one",
+                )))
+            }
+            None => {
+                return Err(Failure::Refused(format!(
+                    "<@{}> hasn't registered a timezone yet",
+                    user
+                )))
+            }
+        };
 
-impl<'a> TryFrom<&'a CommandInteraction> for TimeCommand {
-    type Error = String;
-    fn try_from(_: &'a CommandInteraction) -> Result<Self, Self::Error> {
-        Ok(Self)
+        let now = chrono::Utc::now().with_timezone(&tz);
+        // only users who have set their hours are known to be working, or not
+        let working = profile
+            .working_hours
+            .map(|hours| format!(", {} (working {})", hours.status(&now), hours.describe()))
+            .unwrap_or_default();
+        Ok(Reply::text(format!(
+            "It is {} on a {} for <@{}> ({}, {}){}",
+            now.format(time_format),
+            now.format("%A"),
+            user,
+            tz.name(),
+            timezones::format_utc_offset(&now),
+            working
+        )))
     }
 }
 
 #[async_trait]
-impl<'a> Command<'a> for TimeCommand {
+impl<'a> Command<'a> for TimeCommand<'a> {
     fn name() -> &'static str {
         "time"
     }
 
     fn description() -> &'static str {
-        "Creates a large message to hide previous messages in the chat"
+        "Get the current time anywhere in the world, or for another user"
+    }
+
+    fn dm_permission() -> bool {
+        true
     }
 
     fn get_application_command_options(i: CreateCommand) -> CreateCommand {
-        i
-            .add_option(CreateCommandOption::new(
-                serenity::all::CommandOptionType::String,
-                "location",
-                "The location to get the time for",
-            )
-            .required(true)
-            .add_string_choice("London", "Europe/London")
-            .add_string_choice("Auckland", "Pacific/Auckland")
-        )
+        Self::register_options(i)
     }
 
-    #[allow(clippy::invisible_characters)]
     async fn handle_application_command<'b>(
         self,
         interaction: &'b CommandInteraction,
-        _: &'b AppState,
+        app_state: &'b AppState,
         _: &'b Context,
     ) -> Result<CommandResponse, CommandResponse> {
-        // load the location from the command
-        let location = &interaction.data.options.get(0).unwrap().value;
-        let location = location.as_str().unwrap();
+        respond(self, interaction, app_state).await
+    }
+}
+
+#[async_trait]
+impl<'a> Respond<'a> for TimeCommand<'a> {
+    fn parse(options: Options<'a>) -> Result<Self, String> {
+        <Self as SlashCommand>::parse(options)
+    }
+
+    async fn respond(
+        self,
+        invocation: &Invocation<'_>,
+        app_state: &AppState,
+    ) -> Result<Reply, Failure> {
+        let location = match (self.location, self.user) {
+            (Some(location), _) => location,
+            (None, Some(user)) => return Self::user_time(user.into(), invocation, app_state).await,
+            // with no arguments, show the time for the caller
+            (None, None) => return Self::user_time(invocation.user, invocation, app_state).await,
+        };
+
+        let tz = resolve_timezone_option(location)?;
 
         // get the current time
-        let now = chrono::Local::now().with_timezone(&chrono_tz::Tz::from_str(location).unwrap());
+        let now = chrono::Utc::now().with_timezone(&tz);
 
-        // create the response, should be "The time in Auckland is 3:34pm on a Tuesday"
-        let response_str = format!("The time in {} is{} on a {}", location, now.format("%l:%M%P"), now.format("%A"));
+        // create the response, should be "The time in Pacific/Auckland is 3:34pm on a Tuesday"
+        let time_format = guild_config(invocation.guild, app_state)
+            .await
+            .clock
+            .time_format();
+        Ok(Reply::text(format!(
+            "The time in {} is {} on a {}",
+            tz.name(),
+            now.format(time_format),
+            now.format("%A")
+        )))
+    }
+}
 
-        Ok(CommandResponse::BasicSuccess(response_str))
+#[async_trait]
+impl<'a> AutocompleteCommand<'a> for TimeCommand<'a> {
+    async fn autocomplete<'c>(
+        _: &'c CommandInteraction,
+        option: &'c AutocompleteOption,
+        _: &'c AppState,
+        _: &'c Context,
+    ) -> Result<CreateAutocompleteResponse, CommandResponse> {
+        Ok(timezone_autocomplete(option.value))
     }
 }
@@ -0,0 +1,127 @@
+use serenity::{
+    all::{AutocompleteOption, CommandInteraction},
+    async_trait,
+    builder::{CreateAutocompleteResponse, CreateCommand},
+    prelude::Context,
+};
+
+use crate::{
+    interaction::{Embed, Failure, Invocation, Options, Reply, Respond},
+    state::AppState,
+};
+
+use super::{
+    command::{AutocompleteCommand, Command},
+    options::SlashCommand,
+    transport::respond,
+    util::{invocation_parse_context, parse_time_option, timezone_autocomplete, CommandResponse},
+};
+
+/// every timestamp style supported by discord, with a description of how it is rendered
+const STYLES: &[(char, &str)] = &[
+    ('t', "Short time"),
+    ('T', "Long time"),
+    ('d', "Short date"),
+    ('D', "Long date"),
+    ('f', "Short date and time"),
+    ('F', "Long date and time"),
+    ('R', "Relative"),
+];
+
+#[derive(SlashCommand)]
+pub struct TimestampCommand<'a> {
+    /// the time to generate timestamps for
+    #[option(
+        description = "The time, e.g. \"friday 3pm\", \"2026-11-03 09:00\" or \"in 2 hours\""
+    )]
+    time: &'a str,
+    /// the timezone the time is in, if not given in the time itself
+    #[option(
+        description = "The timezone the time is in, defaults to your registered timezone",
+        autocomplete
+    )]
+    timezone: Option<&'a str>,
+}
+
+#[async_trait]
+impl<'a> Command<'a> for TimestampCommand<'a> {
+    fn name() -> &'static str {
+        "timestamp"
+    }
+
+    fn description() -> &'static str {
+        "Generate discord timestamps for a time, which show in every reader's own timezone"
+    }
+
+    fn dm_permission() -> bool {
+        true
+    }
+
+    fn get_application_command_options(i: CreateCommand) -> CreateCommand {
+        Self::register_options(i)
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        _: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        respond(self, interaction, app_state).await
+    }
+}
+
+#[async_trait]
+impl<'a> Respond<'a> for TimestampCommand<'a> {
+    fn parse(options: Options<'a>) -> Result<Self, String> {
+        <Self as SlashCommand>::parse(options)
+    }
+
+    async fn respond(
+        self,
+        invocation: &Invocation<'_>,
+        app_state: &AppState,
+    ) -> Result<Reply, Failure> {
+        let context = invocation_parse_context(invocation, app_state, self.timezone)
+            .await?
+            .prefer_future();
+        let parsed = parse_time_option(self.time, &context)?;
+        let unix = parsed.time.timestamp();
+
+        let mut embed = Embed::new(format!(
+            "{} ({})",
+            parsed.time.format("%-I:%M%P on %a %-d %b %Y"),
+            parsed.time.timezone().name()
+        ));
+        for (style, description) in STYLES {
+            embed = embed.field(
+                *description,
+                format!("<t:{}:{}>\n`<t:{}:{}>`", unix, style, unix, style),
+                true,
+            );
+        }
+        if parsed.is_ambiguous() {
+            let notes = parsed
+                .ambiguities
+                .iter()
+                .map(|a| a.to_string())
+                .collect::<Vec<_>>()
+                .join("\n");
+            embed = embed.footer(notes);
+        }
+
+        Ok(Reply::embed(embed))
+    }
+}
+
+#[async_trait]
+impl<'a> AutocompleteCommand<'a> for TimestampCommand<'a> {
+    async fn autocomplete<'c>(
+        _: &'c CommandInteraction,
+        option: &'c AutocompleteOption,
+        _: &'c AppState,
+        _: &'c Context,
+    ) -> Result<CreateAutocompleteResponse, CommandResponse> {
+        Ok(timezone_autocomplete(option.value))
+    }
+}
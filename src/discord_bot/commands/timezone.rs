@@ -0,0 +1,329 @@
+use std::collections::HashMap;
+
+use chrono::Offset;
+use chrono_tz::Tz;
+use serenity::{
+    all::{AutocompleteOption, CommandInteraction, ComponentInteraction},
+    async_trait,
+    builder::{CreateAutocompleteResponse, CreateInteractionResponse},
+    model::id::{GuildId, UserId},
+    prelude::Context,
+};
+
+use crate::{
+    interaction::{Failure, Invocation, Options, Reply, Respond, Transport, UserRef},
+    state::AppState,
+    timezones,
+};
+
+use super::{
+    command::{
+        parse_subcommand, AutocompleteCommand, Command, InteractionCommand, PaginatedResponse,
+        SubcommandSpec,
+    },
+    options::SlashCommand,
+    pagination::{self, Pages},
+    transport::{respond, Discord},
+    util::{guild_config, resolve_timezone_option, timezone_autocomplete, CommandResponse},
+};
+
+/// the most members mentioned against a single timezone in the directory
+const MAX_LISTED_MEMBERS: usize = 10;
+
+/// the number of timezones on each page of the directory, which is less than most lists as
+/// every timezone can mention several members
+const DIRECTORY_PER_PAGE: usize = 8;
+
+/// register, view and remove the timezone associated with a user
+pub enum TimezoneCommand<'a> {
+    /// register the calling user's timezone
+    Set(&'a str),
+    /// view the timezone of the provided user, or the calling user if none is provided
+    Get(Option<UserRef>),
+    /// remove the calling user's timezone
+    Clear,
+    /// list the timezones registered by the members of the guild
+    List,
+}
+
+/// the options of `/timezone set`
+#[derive(SlashCommand)]
+pub struct SetOptions<'a> {
+    /// the timezone to register
+    #[option(description = "The timezone, city or country you are in", autocomplete)]
+    zone: &'a str,
+}
+
+/// the options of `/timezone get`
+#[derive(SlashCommand)]
+pub struct GetOptions {
+    /// the user to look up
+    #[option(description = "The user to look up, defaults to yourself")]
+    user: Option<UserRef>,
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for TimezoneCommand<'a> {
+    type Error = String;
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        Self::parse(Discord::invocation(interaction).options)
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for TimezoneCommand<'a> {
+    fn name() -> &'static str {
+        "timezone"
+    }
+
+    fn description() -> &'static str {
+        "Register your timezone so others can see your local time"
+    }
+
+    fn dm_permission() -> bool {
+        true
+    }
+
+    fn subcommands() -> Vec<SubcommandSpec<'a, Self>> {
+        vec![
+            SubcommandSpec::new("set", "Register your timezone", |options| {
+                Ok(Self::Set(SetOptions::parse(options)?.zone))
+            })
+            .options(SetOptions::options()),
+            SubcommandSpec::new(
+                "get",
+                "Show the timezone registered for yourself or another user",
+                |options| Ok(Self::Get(GetOptions::parse(options)?.user)),
+            )
+            .options(GetOptions::options()),
+            SubcommandSpec::new("clear", "Remove your registered timezone", |_| {
+                Ok(Self::Clear)
+            }),
+            SubcommandSpec::new(
+                "list",
+                "List the timezones of this server's members and their local times",
+                |_| Ok(Self::List),
+            ),
+        ]
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        context: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        match self {
+            Self::List => {
+                let pages = Self::get_pages(
+                    interaction.user.id,
+                    interaction.guild_id,
+                    app_state,
+                    context,
+                )
+                .await?;
+                Ok(CommandResponse::ComplexSuccess(
+                    CreateInteractionResponse::Message(pages.page(Self::name(), 0)),
+                ))
+            }
+            command => respond(command, interaction, app_state).await,
+        }
+    }
+}
+
+#[async_trait]
+impl<'a> Respond<'a> for TimezoneCommand<'a> {
+    fn parse(options: Options<'a>) -> Result<Self, String> {
+        parse_subcommand(options)
+    }
+
+    async fn respond(
+        self,
+        invocation: &Invocation<'_>,
+        app_state: &AppState,
+    ) -> Result<Reply, Failure> {
+        let caller = invocation.user;
+        let time_format = guild_config(invocation.guild, app_state)
+            .await
+            .clock
+            .time_format();
+
+        match self {
+            Self::Set(zone) => {
+                let tz = resolve_timezone_option(zone)?;
+
+                if let Err(e) = app_state.set_user_timezone(caller, tz).await {
+                    return Err(Failure::Error {
+                        response: String::from("Failed to save your timezone, please try again"),
+                        log_message: format!("failed to save timezone for {}: {}", caller, e),
+                    });
+                }
+
+                let now = chrono::Utc::now().with_timezone(&tz);
+                Ok(Reply::text(format!(
+                    "Your timezone is now {} ({}), where it is currently {}",
+                    tz.name(),
+                    timezones::format_utc_offset(&now),
+                    now.format(time_format)
+                )))
+            }
+            Self::Get(user) => {
+                let target: u64 = user.map(u64::from).unwrap_or(caller);
+                let Some(tz) = app_state.user_timezone(target).await else {
+                    return Ok(Reply::text(match target == caller {
+                        true => String::from(
+                            "You haven't registered a timezone yet, use `/timezone set` to add one",
+                        ),
+                        false => format!("<@{}> hasn't registered a timezone yet", target),
+                    }));
+                };
+
+                let now = chrono::Utc::now().with_timezone(&tz);
+                let who = if target == caller {
+                    String::from("Your")
+                } else {
+                    format!("<@{}>'s", target)
+                };
+                Ok(Reply::text(format!(
+                    "{} timezone is {} ({}), where it is currently {} on a {}",
+                    who,
+                    tz.name(),
+                    timezones::format_utc_offset(&now),
+                    now.format(time_format),
+                    now.format("%A")
+                )))
+            }
+            Self::Clear => match app_state.clear_user_timezone(caller).await {
+                Ok(Some(tz)) => Ok(Reply::text(format!(
+                    "Removed your timezone ({})",
+                    tz.name()
+                ))),
+                Ok(None) => Ok(Reply::text("You didn't have a timezone registered")),
+                Err(e) => Err(Failure::Error {
+                    response: String::from("Failed to remove your timezone, please try again"),
+                    log_message: format!("failed to clear timezone for {}: {}", caller, e),
+                }),
+            },
+            // the directory is built from a guild's member list, which only discord can provide
+            Self::List => Err(Failure::Refused(String::from(
+                "The timezone directory can only be listed in a server",
+            ))),
+        }
+    }
+}
+
+#[async_trait]
+impl<'a> AutocompleteCommand<'a> for TimezoneCommand<'a> {
+    async fn autocomplete<'c>(
+        _: &'c CommandInteraction,
+        option: &'c AutocompleteOption,
+        _: &'c AppState,
+        _: &'c Context,
+    ) -> Result<CreateAutocompleteResponse, CommandResponse> {
+        Ok(timezone_autocomplete(option.value))
+    }
+}
+
+#[async_trait]
+impl<'a> InteractionCommand<'a> for TimezoneCommand<'a> {
+    async fn answerable<'b>(
+        interaction: &'b ComponentInteraction,
+        _: &'b AppState,
+        _: &'b Context,
+    ) -> bool {
+        pagination::answerable::<Self>(interaction)
+    }
+
+    async fn interaction<'b>(
+        interaction: &'b ComponentInteraction,
+        app_state: &'b AppState,
+        context: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        pagination::turn_page::<Self>(interaction, app_state, context).await
+    }
+}
+
+#[async_trait]
+impl<'a> PaginatedResponse<'a> for TimezoneCommand<'a> {
+    async fn get_pages<'b>(
+        _: UserId,
+        guild_id: Option<GuildId>,
+        app_state: &'b AppState,
+        context: &'b Context,
+    ) -> Result<Pages, CommandResponse> {
+        let guild_id = guild_id.ok_or_else(|| {
+            CommandResponse::BasicFailure(String::from(
+                "The timezone directory can only be shown in a server",
+            ))
+        })?;
+        let members = guild_id
+            .to_guild_cached(&context.cache)
+            .map(|guild| {
+                guild
+                    .members
+                    .keys()
+                    .map(|id| u64::from(*id))
+                    .collect::<Vec<_>>()
+            })
+            .ok_or_else(|| {
+                CommandResponse::BasicFailure(String::from(
+                    "I can't see the members of this server right now, please try again later",
+                ))
+            })?;
+
+        let mut zones: HashMap<Tz, Vec<u64>> = HashMap::new();
+        app_state
+            .read(|db| {
+                for member in members {
+                    if let Some(tz) = db.users.get(&member).and_then(|u| u.timezone) {
+                        zones.entry(tz).or_default().push(member);
+                    }
+                }
+            })
+            .await;
+
+        let time_format = app_state
+            .guild_config(guild_id.into())
+            .await
+            .clock
+            .time_format();
+        let now = chrono::Utc::now();
+        let mut zones = zones
+            .into_iter()
+            .map(|(tz, members)| (now.with_timezone(&tz), members))
+            .collect::<Vec<_>>();
+        // listed from west to east, so that neighbouring entries are close in time
+        zones.sort_by_key(|(local, _)| {
+            (
+                local.offset().fix().local_minus_utc(),
+                local.timezone().name(),
+            )
+        });
+
+        let entries = zones
+            .into_iter()
+            .map(|(local, mut members)| {
+                members.sort_unstable();
+                let mut mentions = members
+                    .iter()
+                    .take(MAX_LISTED_MEMBERS)
+                    .map(|id| format!("<@{}>", id))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                if members.len() > MAX_LISTED_MEMBERS {
+                    mentions.push_str(&format!(" and {} more", members.len() - MAX_LISTED_MEMBERS));
+                }
+                format!(
+                    "**{}** ({}) {}: {}",
+                    local.timezone().name(),
+                    timezones::format_utc_offset(&local),
+                    local.format(time_format),
+                    mentions
+                )
+            })
+            .collect();
+
+        Ok(Pages::new("Timezones of this server's members", entries)
+            .per_page(DIRECTORY_PER_PAGE)
+            .empty("Nobody in this server has registered a timezone yet, use `/timezone set` to add one"))
+    }
+}
@@ -0,0 +1,104 @@
+//! Discord as a [Transport], so that commands written against [Respond] can be used as
+//! application commands
+
+use serenity::{
+    all::CommandInteraction,
+    builder::{
+        CreateEmbed, CreateEmbedFooter, CreateInteractionResponse, CreateInteractionResponseMessage,
+    },
+};
+
+use crate::{
+    interaction::{Embed, Failure, Invocation, Reply, Respond, Transport},
+    state::AppState,
+};
+
+use super::{
+    options::Options,
+    util::{CommandResponse, FailureMessageKind},
+};
+
+/// Application commands used through discord
+pub struct Discord;
+
+impl Transport for Discord {
+    type Incoming = CommandInteraction;
+    type Outgoing = Result<CommandResponse, CommandResponse>;
+
+    fn invocation(interaction: &CommandInteraction) -> Invocation<'_> {
+        let invocation = Invocation::new(interaction.user.id.into())
+            .channel(interaction.channel_id.into())
+            .options(Options::of(interaction));
+        match interaction.guild_id {
+            Some(guild) => invocation.guild(guild.into()),
+            None => invocation,
+        }
+    }
+
+    fn deliver(result: Result<Reply, Failure>) -> Self::Outgoing {
+        match result {
+            Ok(reply) => Ok(CommandResponse::ComplexSuccess(
+                CreateInteractionResponse::Message(message(reply)),
+            )),
+            Err(Failure::Refused(message)) => Err(CommandResponse::BasicFailure(message)),
+            Err(Failure::Error {
+                response,
+                log_message,
+            }) => Err(CommandResponse::ComplexFailure {
+                response,
+                kind: FailureMessageKind::Error,
+                log_message,
+            }),
+        }
+    }
+}
+
+/// the message showing a reply
+fn message(reply: Reply) -> CreateInteractionResponseMessage {
+    let mut message = CreateInteractionResponseMessage::new().ephemeral(!reply.public);
+    if let Some(content) = reply.content {
+        message = message.content(content);
+    }
+    if !reply.embeds.is_empty() {
+        message = message.embeds(reply.embeds.into_iter().map(embed).collect());
+    }
+    message
+}
+
+/// the discord embed showing an embed of a reply
+fn embed(embed: Embed) -> CreateEmbed {
+    let mut created = CreateEmbed::new().fields(embed.fields);
+    if let Some(title) = embed.title {
+        created = created.title(title);
+    }
+    if let Some(url) = embed.url {
+        created = created.url(url);
+    }
+    if let Some(description) = embed.description {
+        created = created.description(description);
+    }
+    if let Some(footer) = embed.footer {
+        created = created.footer(CreateEmbedFooter::new(footer));
+    }
+    created
+}
+
+/// answer an application command with a command written against [Respond]
+pub async fn respond<'a, T: Respond<'a>>(
+    command: T,
+    interaction: &CommandInteraction,
+    app_state: &AppState,
+) -> Result<CommandResponse, CommandResponse> {
+    let invocation = Discord::invocation(interaction);
+    Discord::deliver(command.respond(&invocation, app_state).await)
+}
+
+/// answer an application command with a command written against [Respond], read from an
+/// invocation built by the caller, e.g. with the target of a context menu command as an option
+pub async fn respond_to<'a, T: Respond<'a>>(
+    invocation: Invocation<'a>,
+    app_state: &AppState,
+) -> Result<CommandResponse, CommandResponse> {
+    let command = T::parse(invocation.options.clone()).map_err(CommandResponse::BasicFailure)?;
+    Discord::deliver(command.respond(&invocation, app_state).await)
+}
@@ -1,7 +1,27 @@
 //! Various utilities to assist with writing application commands for the DIANA bot
 
+use chrono::Utc;
+use chrono_tz::Tz;
 use log::{debug, error, info, warn};
-use serenity::builder::{CreateInteractionResponse, CreateInteractionResponseMessage};
+use serenity::{
+    all::CommandInteraction,
+    builder::{
+        CreateAutocompleteResponse, CreateInteractionResponse, CreateInteractionResponseMessage,
+    },
+};
+
+use crate::{
+    interaction::{Invocation, Transport},
+    state::AppState,
+    store::GuildConfig,
+    time_parse::{self, ParseContext, ParsedTime},
+    timezones,
+};
+
+use super::transport::Discord;
+
+/// the maximum number of choices discord will accept in an autocomplete response
+pub const MAX_AUTOCOMPLETE_CHOICES: usize = 25;
 
 #[derive(Debug, Clone, Copy)]
 #[allow(dead_code, clippy::missing_docs_in_private_items)]
@@ -103,3 +123,86 @@ impl CommandResponse {
         }
     }
 }
+
+/// generate an autocomplete response suggesting timezones which fuzzy-match the provided input,
+/// each suggestion is labelled with the current time in that zone
+pub fn timezone_autocomplete(input: &str) -> CreateAutocompleteResponse {
+    let now = chrono::Utc::now();
+    timezones::search(input, MAX_AUTOCOMPLETE_CHOICES)
+        .into_iter()
+        .fold(CreateAutocompleteResponse::new(), |resp, m| {
+            let local = now.with_timezone(&m.tz);
+            resp.add_string_choice(
+                format!("{} - {}", m.label(), local.format("%H:%M")),
+                m.tz.name(),
+            )
+        })
+}
+
+/// parse a time provided by a user as a command option,
+/// failing with a message explaining the problem to the user
+pub fn parse_time_option(input: &str, context: &ParseContext) -> Result<ParsedTime, String> {
+    time_parse::parse(input, context).map_err(|e| match e {
+        time_parse::ParseError::MissingTimezone => String::from(
+            "I don't know which timezone that time is in, pick one with the `timezone` option, \
+            include it in the time or register your own with `/timezone set`",
+        ),
+        e => format!("I couldn't understand \"{}\": {}", input, e),
+    })
+}
+
+/// resolve a timezone provided by a user as a command option,
+/// failing with a message for the user if it is unknown
+pub fn resolve_timezone_option(input: &str) -> Result<Tz, String> {
+    timezones::resolve(input).ok_or_else(|| {
+        format!(
+            "I don't know the timezone \"{}\", try picking one of the suggestions",
+            input
+        )
+    })
+}
+
+/// the configuration of the guild a command was used in, or the defaults outside of a guild
+pub async fn interaction_config(
+    interaction: &CommandInteraction,
+    app_state: &AppState,
+) -> GuildConfig {
+    guild_config(interaction.guild_id.map(u64::from), app_state).await
+}
+
+/// the configuration of a guild, or the defaults for commands used outside of one
+pub async fn guild_config(guild: Option<u64>, app_state: &AppState) -> GuildConfig {
+    match guild {
+        Some(guild_id) => app_state.guild_config(guild_id).await,
+        None => GuildConfig::default(),
+    }
+}
+
+/// the context to read a time given to a command in: in the timezone from the command's `timezone`
+/// option if there is one, or else the user's own or the guild's default, with dates read the way
+/// the guild's locale writes them
+pub async fn user_parse_context(
+    interaction: &CommandInteraction,
+    app_state: &AppState,
+    timezone: Option<&str>,
+) -> Result<ParseContext, String> {
+    invocation_parse_context(&Discord::invocation(interaction), app_state, timezone).await
+}
+
+/// the context to read a time given to a command in, as for [user_parse_context]
+pub async fn invocation_parse_context(
+    invocation: &Invocation<'_>,
+    app_state: &AppState,
+    timezone: Option<&str>,
+) -> Result<ParseContext, String> {
+    let config = guild_config(invocation.guild, app_state).await;
+    let timezone = match timezone {
+        Some(timezone) => Some(resolve_timezone_option(timezone)?),
+        None => {
+            app_state
+                .effective_timezone(invocation.user, invocation.guild)
+                .await
+        }
+    };
+    Ok(ParseContext::new(Utc::now(), timezone).month_first(config.locale.month_first()))
+}
@@ -0,0 +1,232 @@
+use chrono::Utc;
+use log::warn;
+use serenity::{
+    all::{AutocompleteOption, CommandInteraction},
+    async_trait,
+    builder::{CreateAutocompleteResponse, CreateMessage},
+    model::{id::ChannelId, Permissions},
+    prelude::Context,
+};
+
+use crate::{
+    discord_bot::worldclock::render_board,
+    state::AppState,
+    store::{ClockFormat, ClockZone, Feature, WorldClock},
+    timezones,
+};
+
+use super::{
+    command::{parse_subcommand, AutocompleteCommand, Command, SubcommandSpec},
+    options::{Options, SlashCommand},
+    util::{CommandResponse, FailureMessageKind, MAX_AUTOCOMPLETE_CHOICES},
+};
+
+/// the most timezones that can be shown on a single board
+const MAX_ZONES: usize = 20;
+
+/// the most boards that can be posted in a guild at once
+const MAX_BOARDS_PER_GUILD: usize = 10;
+
+/// manage boards showing the current time in several timezones
+pub enum WorldClockCommand<'a> {
+    /// post a new board
+    Create(CreateOptions<'a>),
+}
+
+/// the options of `/worldclock create`
+#[derive(SlashCommand)]
+pub struct CreateOptions<'a> {
+    /// a comma separated list of timezones, each optionally labelled as "label=zone"
+    #[option(
+        description = "Comma separated timezones, optionally labelled, e.g. \"HQ=London, Tokyo\"",
+        autocomplete
+    )]
+    zones: &'a str,
+    /// the channel to post the board in, defaults to the current channel
+    #[option(
+        description = "The channel to post the world clock in, defaults to this channel",
+        channel_types(Text)
+    )]
+    channel: Option<ChannelId>,
+    /// whether to show times in 24 hour format, defaults to the guild's clock format
+    #[option(
+        name = "24_hour",
+        description = "Show times in 24 hour format, defaults to the server's clock format"
+    )]
+    twenty_four_hour: Option<bool>,
+}
+
+/// parse a comma separated list of timezones, each optionally labelled, e.g. "HQ=London, Tokyo"
+fn parse_zones(input: &str) -> Result<Vec<ClockZone>, String> {
+    let zones = input
+        .split(',')
+        .map(str::trim)
+        .filter(|z| !z.is_empty())
+        .map(|entry| {
+            let (label, zone) = match entry.split_once('=') {
+                Some((label, zone)) => (Some(label.trim().to_string()), zone.trim()),
+                None => (None, entry),
+            };
+            let tz = timezones::resolve(zone)
+                .ok_or_else(|| format!("I don't know the timezone \"{}\"", zone))?;
+            Ok(ClockZone { tz, label })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    match zones.len() {
+        0 => Err(String::from("A world clock needs at least one timezone")),
+        n if n > MAX_ZONES => Err(format!(
+            "A world clock can show at most {} timezones",
+            MAX_ZONES
+        )),
+        _ => Ok(zones),
+    }
+}
+
+impl<'a> TryFrom<&'a CommandInteraction> for WorldClockCommand<'a> {
+    type Error = String;
+    fn try_from(interaction: &'a CommandInteraction) -> Result<Self, Self::Error> {
+        parse_subcommand(Options::of(interaction))
+    }
+}
+
+#[async_trait]
+impl<'a> Command<'a> for WorldClockCommand<'a> {
+    fn name() -> &'static str {
+        "worldclock"
+    }
+
+    fn description() -> &'static str {
+        "Post a board showing the current time in several timezones, updated every minute"
+    }
+
+    fn default_permissions() -> Option<Permissions> {
+        Some(Permissions::MANAGE_MESSAGES)
+    }
+
+    fn feature() -> Option<Feature> {
+        Some(Feature::WorldClocks)
+    }
+
+    fn subcommands() -> Vec<SubcommandSpec<'a, Self>> {
+        vec![
+            SubcommandSpec::new("create", "Post a new world clock", |options| {
+                Ok(Self::Create(CreateOptions::parse(options)?))
+            })
+            .options(CreateOptions::options()),
+        ]
+    }
+
+    async fn handle_application_command<'b>(
+        self,
+        interaction: &'b CommandInteraction,
+        app_state: &'b AppState,
+        context: &'b Context,
+    ) -> Result<CommandResponse, CommandResponse> {
+        let guild_id: u64 = match interaction.guild_id {
+            Some(g_id) => g_id.into(),
+            None => {
+                return Err(CommandResponse::BasicFailure(String::from(
+                    "World clocks can only be posted in a server",
+                )))
+            }
+        };
+
+        match self {
+            Self::Create(CreateOptions {
+                zones,
+                channel,
+                twenty_four_hour,
+            }) => {
+                if app_state.guild_settings(guild_id).await.world_clocks.len()
+                    >= MAX_BOARDS_PER_GUILD
+                {
+                    return Err(CommandResponse::BasicFailure(format!(
+                        "This server already has {} world clocks, delete one of their messages first",
+                        MAX_BOARDS_PER_GUILD
+                    )));
+                }
+                let zones = parse_zones(zones).map_err(CommandResponse::BasicFailure)?;
+                let channel = channel.unwrap_or(interaction.channel_id);
+                let twenty_four_hour = match twenty_four_hour {
+                    Some(twenty_four_hour) => twenty_four_hour,
+                    None => {
+                        app_state.guild_config(guild_id).await.clock == ClockFormat::TwentyFourHour
+                    }
+                };
+
+                let mut board = WorldClock {
+                    channel_id: channel.into(),
+                    message_id: 0,
+                    zones,
+                    twenty_four_hour,
+                };
+                let message = channel
+                    .send_message(
+                        context,
+                        CreateMessage::new().embed(render_board(&board, Utc::now())),
+                    )
+                    .await
+                    .map_err(|e| CommandResponse::ComplexFailure {
+                        response: format!(
+                            "I couldn't post in <#{}>, check my permissions",
+                            channel
+                        ),
+                        kind: FailureMessageKind::Warn,
+                        log_message: format!("failed to post world clock: {}", e),
+                    })?;
+                board.message_id = message.id.into();
+
+                // pinning is a nicety, the board still works without the permission to do so
+                if let Err(e) = message.pin(context).await {
+                    warn!("failed to pin world clock {}: {}", message.id, e);
+                }
+
+                app_state
+                    .update_guild_settings(guild_id, |s| s.world_clocks.push(board))
+                    .await
+                    .map_err(|e| {
+                        CommandResponse::InternalFailure(format!(
+                            "failed to save world clock: {}",
+                            e
+                        ))
+                    })?;
+
+                Ok(CommandResponse::BasicSuccess(format!(
+                    "Posted a world clock in <#{}>, it will update every minute",
+                    channel
+                )))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<'a> AutocompleteCommand<'a> for WorldClockCommand<'a> {
+    async fn autocomplete<'c>(
+        _: &'c CommandInteraction,
+        option: &'c AutocompleteOption,
+        _: &'c AppState,
+        _: &'c Context,
+    ) -> Result<CreateAutocompleteResponse, CommandResponse> {
+        // only the last entry in the list is being typed, so suggest completions of it
+        // while keeping the entries before it
+        let (prefix, last) = match option.value.rsplit_once(',') {
+            Some((before, last)) => (format!("{}, ", before.trim_end()), last.trim()),
+            None => (String::new(), option.value.trim()),
+        };
+        let (prefix, query) = match last.split_once('=') {
+            Some((label, query)) => (format!("{}{}=", prefix, label.trim()), query.trim()),
+            None => (prefix, last),
+        };
+
+        Ok(timezones::search(query, MAX_AUTOCOMPLETE_CHOICES)
+            .into_iter()
+            .map(|m| format!("{}{}", prefix, m.tz.name()))
+            // discord rejects choices longer than 100 characters
+            .filter(|choice| choice.len() <= 100)
+            .fold(CreateAutocompleteResponse::new(), |resp, choice| {
+                resp.add_string_choice(choice.clone(), choice)
+            }))
+    }
+}
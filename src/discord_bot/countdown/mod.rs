@@ -0,0 +1,169 @@
+//! Countdowns, messages counting down to an instant which are announced in their channel once the
+//! instant is reached. Live countdowns are also edited by the guild handler as time passes, but only
+//! when the coarse time remaining they display changes, to keep the number of edits low.
+//! Countdowns are stored in the guild settings, so they are picked back up after a restart.
+
+#[cfg(test)]
+mod tests;
+
+use chrono::{DateTime, Duration, Utc};
+use log::{error, info, trace};
+use serenity::{
+    builder::{CreateAllowedMentions, CreateEmbed, CreateEmbedFooter, CreateMessage, EditMessage},
+    client::Context,
+    model::id::{ChannelId, GuildId, MessageId, UserId},
+};
+
+use super::utils::is_missing_message;
+use crate::{state::AppState, store::Countdown};
+
+/// how often the guild handler checks countdowns
+fn tick() -> Duration {
+    Duration::minutes(1)
+}
+
+/// describe the time remaining until a countdown is reached, more coarsely the further away it is,
+/// e.g. "3 days, 4 hours", "5 hours, 20 minutes" or "7 minutes"
+pub fn remaining(remaining: Duration) -> String {
+    let plural = |n: i64, unit: &str| format!("{} {}{}", n, unit, if n == 1 { "" } else { "s" });
+
+    // round up, so that "1 minute" is shown until the countdown is reached
+    let minutes = (remaining.num_seconds() + 59).div_euclid(60).max(0);
+    let (days, hours, minutes) = (minutes / (24 * 60), minutes / 60 % 24, minutes % 60);
+    match (days, hours) {
+        (0, 0) => plural(minutes, "minute"),
+        // minutes are shown to the nearest 10 when there are hours to go
+        (0, _) => format!(
+            "{}, {}",
+            plural(hours, "hour"),
+            plural(minutes / 10 * 10, "minute")
+        ),
+        _ => format!("{}, {}", plural(days, "day"), plural(hours, "hour")),
+    }
+}
+
+/// render the embed displaying a countdown at the provided instant
+pub fn render_countdown(countdown: &Countdown, now: DateTime<Utc>) -> CreateEmbed {
+    let target = countdown.target.timestamp();
+    if countdown.target <= now {
+        return CreateEmbed::new()
+            .title(format!("🎉 {}", countdown.label))
+            .description(format!("Reached <t:{}:F>", target))
+            .timestamp(countdown.target);
+    }
+
+    let mut description = format!("<t:{}:R>, <t:{}:F>", target, target);
+    if countdown.live {
+        description = format!(
+            "**{}** to go\n{}",
+            remaining(countdown.target - now),
+            description
+        );
+    }
+    CreateEmbed::new()
+        .title(format!("⏳ {}", countdown.label))
+        .description(description)
+        .footer(CreateEmbedFooter::new(
+            "Shown in your local time, I'll announce it here when it's reached",
+        ))
+        .timestamp(countdown.target)
+}
+
+/// announce that a countdown has been reached, replacing its message with the final result
+async fn announce(countdown: &Countdown, context: &Context) {
+    let channel = ChannelId::new(countdown.channel_id);
+    let edit = EditMessage::new().embed(render_countdown(countdown, countdown.target));
+    if let Err(e) = channel
+        .edit_message(context, MessageId::new(countdown.message_id), edit)
+        .await
+    {
+        // the message may have been deleted, but the countdown is still announced
+        trace!(
+            "failed to edit finished countdown {}: {}",
+            countdown.message_id,
+            e
+        );
+    }
+
+    let announcement = CreateMessage::new()
+        .content(format!(
+            "⏰ <@{}> **{}** is here!",
+            countdown.user_id, countdown.label
+        ))
+        .allowed_mentions(CreateAllowedMentions::new().users([UserId::new(countdown.user_id)]));
+    if let Err(e) = channel.send_message(context, announcement).await {
+        error!(
+            "failed to announce countdown {}: {}",
+            countdown.message_id, e
+        );
+    }
+}
+
+/// announce every countdown in a guild which has been reached, and edit the live countdowns whose
+/// time remaining has changed since the last tick, forgetting any whose message has been deleted.
+/// When `refresh` is set every live countdown is edited, e.g. to catch up after a restart
+pub async fn update_countdowns(
+    guild_id: GuildId,
+    context: Context,
+    app_state: AppState,
+    refresh: bool,
+) {
+    let guild: u64 = guild_id.into();
+    let countdowns = app_state.guild_settings(guild).await.countdowns;
+    if countdowns.is_empty() {
+        return;
+    }
+
+    let now = Utc::now();
+    if countdowns.iter().any(|c| c.target <= now) {
+        match app_state.take_finished_countdowns(guild, now).await {
+            Ok(finished) => {
+                for countdown in finished.iter() {
+                    announce(countdown, &context).await;
+                }
+            }
+            Err(e) => error!("failed to remove finished countdowns: {}", e),
+        }
+    }
+
+    let mut missing = Vec::new();
+    for countdown in countdowns.iter().filter(|c| c.live && c.target > now) {
+        if !refresh
+            && remaining(countdown.target - now) == remaining(countdown.target - now + tick())
+        {
+            continue;
+        }
+
+        trace!(
+            "updating countdown {} in guild {}",
+            countdown.message_id,
+            guild
+        );
+        let edit = EditMessage::new().embed(render_countdown(countdown, now));
+        if let Err(e) = ChannelId::new(countdown.channel_id)
+            .edit_message(&context, MessageId::new(countdown.message_id), edit)
+            .await
+        {
+            if is_missing_message(&e) {
+                info!(
+                    "countdown {} in guild {} was deleted, no longer updating it",
+                    countdown.message_id, guild
+                );
+                missing.push(countdown.message_id);
+            } else {
+                error!("failed to update countdown {}: {}", countdown.message_id, e);
+            }
+        }
+    }
+
+    if !missing.is_empty() {
+        if let Err(e) = app_state
+            .update_guild_settings(guild, |s| {
+                s.countdowns.retain(|c| !missing.contains(&c.message_id))
+            })
+            .await
+        {
+            error!("failed to remove deleted countdowns: {}", e);
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use chrono::Duration;
+
+use super::{remaining, tick};
+
+#[test]
+fn remaining_time_is_coarser_further_away() {
+    let cases = [
+        (Duration::seconds(30), "1 minute"),
+        (Duration::minutes(7), "7 minutes"),
+        (Duration::minutes(7) + Duration::seconds(1), "8 minutes"),
+        (Duration::minutes(60), "1 hour, 0 minutes"),
+        (Duration::minutes(5 * 60 + 27), "5 hours, 20 minutes"),
+        (Duration::hours(24), "1 day, 0 hours"),
+        (
+            Duration::days(3) + Duration::minutes(4 * 60 + 59),
+            "3 days, 4 hours",
+        ),
+        (Duration::seconds(-5), "0 minutes"),
+    ];
+    for (duration, expected) in cases {
+        assert_eq!(remaining(duration), expected, "{:?}", duration);
+    }
+}
+
+#[test]
+fn distant_countdowns_are_edited_rarely() {
+    // count how often the displayed time remaining changes from tick to tick over two days
+    let edits = (0..2 * 24 * 60)
+        .map(|m| Duration::days(2) - Duration::minutes(m))
+        .filter(|left| remaining(*left) != remaining(*left + tick()))
+        .count();
+    // hourly for the first day, every 10 minutes for the next 23 hours, and then every minute
+    assert_eq!(edits, 24 + 23 * 6 + 59);
+}
@@ -0,0 +1,172 @@
+//! A handler for interactions in direct messages with the bot, which don't belong to any guild, and
+//! in guilds the bot isn't in, reached through a user's own install of the bot.
+//! Only personal commands, such as `/timezone` and `/remind`, can be used here.
+
+use std::time::Duration;
+
+use log::{error, info, trace, warn};
+use serenity::{
+    all::{InstallationContext, Interaction},
+    builder::CreateCommand,
+    client::Context,
+    futures::{stream::FuturesUnordered, StreamExt},
+};
+use tokio::{
+    select,
+    sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    task::JoinHandle,
+    time::{timeout_at, Instant},
+};
+
+use super::{
+    commands::global_application_command,
+    guilds::{describe, finish, handle_slash_command, NamedTask, CLOSE_GRACE},
+    manager::DiscordEvent,
+    utils::is_refused,
+};
+use crate::state::AppState;
+
+/// the personal commands as sent to discord, marked as installable by users as well as guilds
+fn personal_commands() -> Vec<CreateCommand> {
+    global_application_command()
+        .into_iter()
+        .map(|command| {
+            command.integration_types(vec![InstallationContext::Guild, InstallationContext::User])
+        })
+        .collect()
+}
+
+/// register the personal commands globally, retrying until discord accepts them, unless discord
+/// refuses them outright
+async fn register_commands(context: Context) {
+    let commands = personal_commands();
+    while let Err(e) = context
+        .http
+        .create_global_application_commands(&commands)
+        .await
+    {
+        error!("failed to register global commands: {}", e);
+        if is_refused(&e) {
+            return;
+        }
+        tokio::time::sleep(Duration::from_secs(10)).await;
+    }
+}
+
+/// a handler which responds to interactions in direct messages
+pub struct DirectHandler {
+    /// access to the general bot context, used to respond to interactions
+    context: Context,
+    /// a handle to the database connected to the bot
+    app_state: AppState,
+    /// a handle to the internal task handling direct messages once started
+    handle: Option<JoinHandle<Vec<String>>>,
+    /// the receiving end of the internal communication channel, until the handler is started
+    internal_rx: Option<UnboundedReceiver<DiscordEvent>>,
+    /// the sending end of the internal communication channel
+    pub internal_tx: UnboundedSender<DiscordEvent>,
+}
+
+impl DirectHandler {
+    /// create a handler for direct messages, reading the state from the data stored on the context
+    pub async fn create(context: Context) -> Result<Self, String> {
+        let app_state = context
+            .data
+            .read()
+            .await
+            .get::<AppState>()
+            .ok_or("AppState not found in context")?
+            .clone();
+        let (tx, rx) = unbounded_channel();
+        Ok(Self {
+            context,
+            app_state,
+            handle: None,
+            internal_rx: Some(rx),
+            internal_tx: tx,
+        })
+    }
+
+    /// whether the handler has been started, and is still running
+    pub fn is_running(&self) -> bool {
+        self.handle.as_ref().is_some_and(|h| !h.is_finished())
+    }
+
+    /// close this handler, letting the interactions it is handling finish until the deadline, when
+    /// any still running are aborted. returns the names of the aborted tasks
+    pub async fn close(
+        &mut self,
+        deadline: Instant,
+    ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let Some(mut handle) = self.handle.take() else {
+            return Ok(Vec::new());
+        };
+        self.internal_tx.send(DiscordEvent::Shutdown(deadline))?;
+        match timeout_at(deadline + CLOSE_GRACE, &mut handle).await {
+            Ok(aborted) => Ok(aborted?),
+            Err(_) => {
+                warn!("failed to close the direct message handler within timeout period, aborting");
+                handle.abort();
+                Ok(vec![String::from("direct message handler")])
+            }
+        }
+    }
+
+    /// begin responding to interactions in direct messages, registering the commands which can be
+    /// used in them
+    pub fn start(&mut self) {
+        let Some(mut internal_rx) = self.internal_rx.take() else {
+            warn!("already handling direct messages");
+            return;
+        };
+        let context = self.context.clone();
+        let app_state = self.app_state.clone();
+
+        info!("Handling direct messages");
+
+        self.handle = Some(tokio::task::spawn(async move {
+            let mut task_handles = FuturesUnordered::<NamedTask>::new();
+            task_handles.push(NamedTask::spawn(
+                "global command registration",
+                register_commands(context.clone()),
+            ));
+            // set when the handler is asked to shut down, after which unfinished tasks are aborted
+            let mut deadline = None;
+
+            loop {
+                select! {
+                    message = internal_rx.recv() => {
+                        match message {
+                            Some(DiscordEvent::Shutdown(at)) => {
+                                internal_rx.close();
+                                deadline = Some(at);
+                                break;
+                            },
+                            Some(DiscordEvent::Interaction(interaction)) => {
+                                if matches!(*interaction, Interaction::Ping(_)) {
+                                    trace!("ignoring ping interaction");
+                                    continue;
+                                }
+
+                                let t_ctx = context.clone();
+                                let t_app_state = app_state.clone();
+                                task_handles.push(NamedTask::spawn(describe(&interaction), async move {
+                                    handle_slash_command(*interaction, t_ctx, t_app_state).await;
+                                }))
+                            },
+                            Some(e) => error!("direct message handler ignoring unexpected event: {:?}", e),
+                            // every sender has been dropped, so no more events can arrive
+                            None => break,
+                        }
+                    },
+                    // drain task handles as they complete
+                    _ = task_handles.next(), if !task_handles.is_empty() => {},
+                }
+            }
+
+            let aborted = finish(task_handles, deadline).await;
+            info!("No longer handling direct messages");
+            aborted
+        }));
+    }
+}
@@ -0,0 +1,177 @@
+//! Advance notice of daylight savings changes, posted in a guild's configured channel for the
+//! timezones registered by its members, so that meetings between offices aren't silently moved.
+
+use chrono::{Duration, Utc};
+use chrono_tz::Tz;
+use log::{error, trace, warn};
+use serenity::{
+    builder::{CreateEmbed, CreateEmbedFooter, CreateMessage},
+    client::Context,
+    model::id::{ChannelId, GuildId},
+};
+
+use crate::{
+    dst::{transitions_between, Transition},
+    state::AppState,
+    store::{Feature, GuildConfig},
+};
+
+/// how long before a daylight savings change it is announced
+pub fn notice() -> Duration {
+    Duration::days(7)
+}
+
+/// the most other timezones listed against a single change
+const MAX_RELATIVE_ZONES: usize = 10;
+
+/// the longest description an embed can have, leaving room to say how many changes were left out
+const MAX_DESCRIPTION_LENGTH: usize = 4000;
+
+/// the timezones registered by the members of a guild, ordered by name
+pub async fn guild_timezones(
+    guild_id: GuildId,
+    context: &Context,
+    app_state: &AppState,
+) -> Vec<Tz> {
+    let members = match guild_id.to_guild_cached(&context.cache) {
+        Some(guild) => guild
+            .members
+            .keys()
+            .map(|id| u64::from(*id))
+            .collect::<Vec<_>>(),
+        None => return Vec::new(),
+    };
+
+    let mut zones = app_state
+        .read(|db| {
+            members
+                .iter()
+                .filter_map(|id| db.users.get(id)?.timezone)
+                .collect::<Vec<_>>()
+        })
+        .await;
+    zones.sort_by_key(|tz| tz.name());
+    zones.dedup();
+    zones
+}
+
+/// describe a change, and how it moves the other timezones relative to it
+fn describe_transition(transition: &Transition, zones: &[Tz]) -> String {
+    let mut relative = zones
+        .iter()
+        .filter(|tz| **tz != transition.tz)
+        .filter_map(|tz| transition.relative_to(*tz))
+        .collect::<Vec<_>>();
+    if relative.len() > MAX_RELATIVE_ZONES {
+        let more = relative.len() - MAX_RELATIVE_ZONES;
+        relative.truncate(MAX_RELATIVE_ZONES);
+        relative.push(format!("and {} more", more));
+    }
+
+    let mut description = format!(
+        "**{}** (<t:{}:R>)",
+        transition.describe(),
+        transition.at.timestamp()
+    );
+    for line in relative {
+        description.push_str(&format!("\n- {}", line));
+    }
+    description
+}
+
+/// describe several changes, to fit in the description of an embed
+pub fn describe_transitions(transitions: &[Transition], zones: &[Tz]) -> String {
+    let mut description = String::new();
+    for (i, transition) in transitions.iter().enumerate() {
+        let next = describe_transition(transition, zones);
+        if description.len() + next.len() + 2 > MAX_DESCRIPTION_LENGTH {
+            description.push_str(&format!("\n\nand {} more", transitions.len() - i));
+            break;
+        }
+        if !description.is_empty() {
+            description.push_str("\n\n");
+        }
+        description.push_str(&next);
+    }
+    description
+}
+
+/// announce the daylight savings changes coming up within the notice period for the timezones of
+/// a guild's members, if the guild has the announcements turned on and a channel to post them in.
+/// Each change is only announced once, even across restarts
+pub async fn announce_dst_changes(
+    guild_id: GuildId,
+    context: Context,
+    app_state: AppState,
+    config: GuildConfig,
+) {
+    let Some(channel) = config
+        .announcement_channel
+        .filter(|_| config.enabled(Feature::DstAnnouncements))
+    else {
+        return;
+    };
+
+    let guild: u64 = guild_id.into();
+    let settings = app_state.guild_settings(guild).await;
+
+    let now = Utc::now();
+    let until = now + notice();
+    // changes which were due while the bot was offline are no longer worth announcing
+    let from = settings
+        .dst_announced_until
+        .filter(|t| *t > now)
+        .unwrap_or(now);
+    if from >= until {
+        return;
+    }
+
+    let zones = guild_timezones(guild_id, &context, &app_state).await;
+    if zones.is_empty() {
+        // the members may not be cached yet, so the window is left to be checked again later
+        return;
+    }
+    let transitions = transitions_between(&zones, from, until);
+    trace!(
+        "found {} daylight savings changes for {} timezones in guild {}",
+        transitions.len(),
+        zones.len(),
+        guild
+    );
+
+    if !transitions.is_empty() {
+        let embed = CreateEmbed::new()
+            .title("🕑 Daylight savings changes coming up")
+            .description(describe_transitions(&transitions, &zones))
+            .footer(CreateEmbedFooter::new(
+                "Double check the times of any meetings between these timezones",
+            ));
+
+        if let Err(e) = ChannelId::new(channel)
+            .send_message(&context, CreateMessage::new().embed(embed))
+            .await
+        {
+            // the changes are left unannounced, so they are tried again on the next check
+            warn!(
+                "failed to announce daylight savings changes in guild {}: {}",
+                guild, e
+            );
+            return;
+        }
+    }
+
+    // saving every check would rewrite the store each time the guild is checked, so an empty
+    // window is only recorded once it has moved on by a day
+    let moved = settings
+        .dst_announced_until
+        .is_none_or(|announced| until - announced >= Duration::days(1));
+    if transitions.is_empty() && !moved {
+        return;
+    }
+    if let Err(e) = app_state
+        .update_guild_settings(guild, |s| s.dst_announced_until = Some(until))
+        .await
+    {
+        error!("failed to save announced daylight savings changes: {}", e);
+    }
+}
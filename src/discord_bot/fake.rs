@@ -0,0 +1,560 @@
+//! An offline stand-in for discord's gateway and REST api, so the whole bot can be run in tests.
+//! Events are injected into the gateway as though discord had sent them, and every request the bot
+//! makes to the REST api is recorded so tests can check what was sent back.
+
+use std::{
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use serde_json::{json, Value};
+use serenity::futures::{SinkExt, StreamExt};
+use tokio::sync::{mpsc, Notify};
+use warp::{
+    http::StatusCode,
+    hyper::body::Bytes,
+    ws::{Message, WebSocket},
+    Filter, Reply,
+};
+
+/// the id of the bot's own user
+pub const BOT_ID: u64 = 1;
+
+/// the id of the bot's application, which its commands are registered under
+pub const APPLICATION_ID: u64 = 2;
+
+/// how long a test waits for the bot to make a request before failing
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A request the bot made to the REST api
+#[derive(Debug, Clone)]
+pub struct Request {
+    /// the http method, e.g. "POST"
+    pub method: String,
+    /// the path requested, without the api version, e.g. "/channels/10/messages"
+    pub path: String,
+    /// the json body of the request, or null if there wasn't one
+    pub body: Value,
+}
+
+/// The requests made to the REST api so far, shared with the server recording them
+#[derive(Default)]
+struct Requests {
+    /// every request, in the order they were made
+    made: Mutex<Vec<Request>>,
+    /// notified whenever a request is recorded
+    recorded: Notify,
+    /// how long to wait before replying to each request, in milliseconds
+    delay: AtomicU64,
+    /// whether requests to register commands are refused, as when the bot isn't in the guild
+    refuse_commands: AtomicBool,
+}
+
+/// Whether the bot has closed its gateway session, shared with the gateway
+#[derive(Default)]
+struct Disconnect {
+    /// set once the bot has sent a close frame
+    closed: AtomicBool,
+    /// notified when the bot sends a close frame
+    notify: Notify,
+}
+
+/// A stand-in for discord, listening on a local port
+pub struct FakeDiscord {
+    /// the address the stand-in is listening on
+    address: SocketAddr,
+    /// events waiting to be sent to the bot over the gateway, as their name and data
+    events: mpsc::UnboundedSender<(&'static str, Value)>,
+    /// the requests the bot has made
+    requests: Arc<Requests>,
+    /// whether the bot has closed the gateway
+    disconnect: Arc<Disconnect>,
+    /// the last id handed out, so that every id is unique
+    last_id: AtomicU64,
+}
+
+impl FakeDiscord {
+    /// start listening on a free local port
+    pub async fn start() -> Self {
+        Self::start_in(&[]).await
+    }
+
+    /// start listening on a free local port, telling the bot it is in the provided guilds when it
+    /// connects. discord sends each of them later, as they become available
+    pub async fn start_in(guilds: &[u64]) -> Self {
+        let guilds: Arc<[u64]> = guilds.into();
+        let (events, events_rx) = mpsc::unbounded_channel();
+        // the events are held by whichever gateway session is connected
+        let events_rx = Arc::new(tokio::sync::Mutex::new(events_rx));
+        let requests = Arc::new(Requests::default());
+        let disconnect = Arc::new(Disconnect::default());
+
+        let gateway_disconnect = disconnect.clone();
+        let gateway = warp::path("gateway")
+            .and(warp::ws())
+            .map(move |ws: warp::ws::Ws| {
+                let events_rx = events_rx.clone();
+                let disconnect = gateway_disconnect.clone();
+                let guilds = guilds.clone();
+                ws.on_upgrade(move |socket| async move {
+                    let mut events = events_rx.lock().await;
+                    gateway_session(socket, &mut events, &disconnect, &guilds).await;
+                })
+            });
+
+        // the address isn't known until the server is bound, but the gateway url must include it
+        let gateway_url = Arc::new(std::sync::OnceLock::<String>::new());
+        let rest_requests = requests.clone();
+        let rest_gateway_url = gateway_url.clone();
+        let rest = warp::method()
+            .and(warp::path::full())
+            .and(warp::body::bytes())
+            .and_then(
+                move |method: warp::http::Method, path: warp::path::FullPath, body: Bytes| {
+                    let requests = rest_requests.clone();
+                    let gateway_url = rest_gateway_url.clone();
+                    async move {
+                        let request = Request {
+                            method: method.to_string(),
+                            path: path.as_str().trim_start_matches("/api/v10").to_string(),
+                            body: serde_json::from_slice(&body).unwrap_or(Value::Null),
+                        };
+                        let refused = request.method == "PUT"
+                            && request.path.ends_with("/commands")
+                            && requests.refuse_commands.load(Ordering::Relaxed);
+                        let reply = match refused {
+                            true => warp::reply::with_status(
+                                warp::reply::json(
+                                    &json!({ "code": 50001, "message": "Missing Access" }),
+                                ),
+                                StatusCode::FORBIDDEN,
+                            )
+                            .into_response(),
+                            false => {
+                                rest_reply(&request, gateway_url.get().expect("server is bound"))
+                            }
+                        };
+                        requests.made.lock().unwrap().push(request);
+                        requests.recorded.notify_waiters();
+
+                        let delay = requests.delay.load(Ordering::Relaxed);
+                        tokio::time::sleep(Duration::from_millis(delay)).await;
+                        Ok::<_, Infallible>(reply)
+                    }
+                },
+            );
+
+        let (address, server) = warp::serve(gateway.or(rest)).bind_ephemeral(([127, 0, 0, 1], 0));
+        gateway_url
+            .set(format!("ws://{}/gateway", address))
+            .expect("the gateway url is only set once");
+        tokio::task::spawn(server);
+
+        Self {
+            address,
+            events,
+            requests,
+            disconnect,
+            last_id: AtomicU64::new(1000),
+        }
+    }
+
+    /// the base url of the REST api, for [DiscordBotBuilder::api_url](super::DiscordBotBuilder::api_url)
+    pub fn api_url(&self) -> String {
+        format!("http://{}", self.address)
+    }
+
+    /// a new unique id, for anything which needs one
+    pub fn id(&self) -> u64 {
+        self.last_id.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// send an event to the bot over the gateway, it is held until the bot has connected
+    pub fn dispatch(&self, event: &'static str, data: Value) {
+        self.events
+            .send((event, data))
+            .expect("the gateway is running");
+    }
+
+    /// add the bot to a guild with the provided members
+    pub fn guild_create(&self, guild_id: u64, name: &str, members: &[u64]) {
+        self.dispatch("GUILD_CREATE", guild_json(guild_id, name, members));
+    }
+
+    /// remove the bot from a guild
+    pub fn guild_delete(&self, guild_id: u64) {
+        self.dispatch(
+            "GUILD_DELETE",
+            json!({ "id": guild_id.to_string(), "unavailable": false }),
+        );
+    }
+
+    /// use a slash command, returning the id of the interaction
+    pub fn command(
+        &self,
+        guild_id: u64,
+        channel_id: u64,
+        user_id: u64,
+        name: &str,
+        options: Value,
+    ) -> u64 {
+        let id = self.id();
+        let mut interaction = self.command_json(id, channel_id, name, options);
+        interaction["guild_id"] = json!(guild_id.to_string());
+        interaction["member"] = member_json(guild_id, user_id);
+        interaction["guild_locale"] = json!("en-GB");
+        self.dispatch("INTERACTION_CREATE", interaction);
+        id
+    }
+
+    /// use a slash command in direct messages with the bot, returning the id of the interaction
+    pub fn direct_command(&self, channel_id: u64, user_id: u64, name: &str, options: Value) -> u64 {
+        let id = self.id();
+        let mut interaction = self.command_json(id, channel_id, name, options);
+        interaction["user"] = user_json(user_id);
+        self.dispatch("INTERACTION_CREATE", interaction);
+        id
+    }
+
+    /// use a message's context menu command in a guild, returning the id of the interaction
+    pub fn message_command(
+        &self,
+        guild_id: u64,
+        channel_id: u64,
+        user_id: u64,
+        name: &str,
+        author_id: u64,
+        content: &str,
+    ) -> u64 {
+        let id = self.id();
+        let message_id = self.id();
+        let mut interaction = self.command_json(id, channel_id, name, json!([]));
+        interaction["data"]["type"] = json!(3);
+        interaction["data"]["target_id"] = json!(message_id.to_string());
+        interaction["data"]["resolved"] = json!({
+            "messages": {
+                message_id.to_string(): message_json(message_id, channel_id, author_id, content),
+            },
+        });
+        interaction["guild_id"] = json!(guild_id.to_string());
+        interaction["member"] = member_json(guild_id, user_id);
+        interaction["guild_locale"] = json!("en-GB");
+        self.dispatch("INTERACTION_CREATE", interaction);
+        id
+    }
+
+    /// a slash command interaction, without who used it or where
+    fn command_json(&self, id: u64, channel_id: u64, name: &str, options: Value) -> Value {
+        json!({
+            "id": id.to_string(),
+            "application_id": APPLICATION_ID.to_string(),
+            "type": 2,
+            "data": {
+                "id": self.id().to_string(),
+                "name": name,
+                "type": 1,
+                "options": options,
+            },
+            "channel_id": channel_id.to_string(),
+            "token": format!("token-{}", id),
+            "version": 1,
+            "app_permissions": "0",
+            "locale": "en-GB",
+            "entitlements": [],
+        })
+    }
+
+    /// send a message in a guild channel, returning the id of the message
+    pub fn message(&self, guild_id: u64, channel_id: u64, author_id: u64, content: &str) -> u64 {
+        let id = self.id();
+        let mut message = message_json(id, channel_id, author_id, content);
+        message["guild_id"] = json!(guild_id.to_string());
+        message["member"] = member_json(guild_id, author_id);
+        self.dispatch("MESSAGE_CREATE", message);
+        id
+    }
+
+    /// wait before replying to every request from now on, e.g. to keep the bot's tasks running
+    pub fn delay_responses(&self, delay: Duration) {
+        self.requests
+            .delay
+            .store(delay.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// refuse every request to register commands from now on
+    pub fn refuse_commands(&self) {
+        self.requests.refuse_commands.store(true, Ordering::Relaxed);
+    }
+
+    /// wait for the bot to close its gateway session, which it may already have done.
+    /// panics if the session isn't closed in time
+    pub async fn wait_for_disconnect(&self) {
+        let wait = async {
+            loop {
+                let notified = self.disconnect.notify.notified();
+                if self.disconnect.closed.load(Ordering::Relaxed) {
+                    return;
+                }
+                notified.await;
+            }
+        };
+        if tokio::time::timeout(REQUEST_TIMEOUT, wait).await.is_err() {
+            panic!("the bot didn't close the gateway");
+        }
+    }
+
+    /// every request the bot has made so far
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.made.lock().unwrap().clone()
+    }
+
+    /// wait for the bot to make a request matching the predicate, which may already have been made.
+    /// panics if no such request is made in time, so that tests fail rather than hang
+    pub async fn wait_for(&self, mut predicate: impl FnMut(&Request) -> bool) -> Request {
+        let wait = async {
+            loop {
+                // registered before checking, so a request recorded in between isn't missed
+                let recorded = self.requests.recorded.notified();
+                if let Some(request) = self.requests().into_iter().find(|r| predicate(r)) {
+                    return request;
+                }
+                recorded.await;
+            }
+        };
+        match tokio::time::timeout(REQUEST_TIMEOUT, wait).await {
+            Ok(request) => request,
+            Err(_) => panic!(
+                "the bot didn't make the expected request, it made {:#?}",
+                self.requests()
+            ),
+        }
+    }
+
+    /// wait for the response to an interaction
+    pub async fn response_to(&self, interaction_id: u64) -> Value {
+        let path = format!(
+            "/interactions/{}/token-{}/callback",
+            interaction_id, interaction_id
+        );
+        self.wait_for(|r| r.path == path).await.body
+    }
+}
+
+/// talk to a bot connected to the gateway: say hello, answer its heartbeats and identify as a
+/// member of the provided guilds, and then pass on every event as it is dispatched
+async fn gateway_session(
+    socket: WebSocket,
+    events: &mut mpsc::UnboundedReceiver<(&'static str, Value)>,
+    disconnect: &Disconnect,
+    guilds: &[u64],
+) {
+    let (mut tx, mut rx) = socket.split();
+    let send = |payload: Value| Message::text(payload.to_string());
+
+    if tx
+        .send(send(
+            json!({ "op": 10, "d": { "heartbeat_interval": 41250 } }),
+        ))
+        .await
+        .is_err()
+    {
+        return;
+    }
+
+    let mut sequence = 0;
+    let mut identified = false;
+    loop {
+        let payload = tokio::select! {
+            message = rx.next() => {
+                let Some(Ok(message)) = message else { break };
+                if message.is_close() {
+                    disconnect.closed.store(true, Ordering::Relaxed);
+                    disconnect.notify.notify_waiters();
+                    break;
+                }
+                let Ok(text) = message.to_str() else { continue };
+                let Ok(received) = serde_json::from_str::<Value>(text) else { continue };
+                match received["op"].as_u64() {
+                    // heartbeat
+                    Some(1) => json!({ "op": 11 }),
+                    // identify
+                    Some(2) => {
+                        identified = true;
+                        sequence += 1;
+                        json!({ "op": 0, "t": "READY", "s": sequence, "d": ready_json(guilds) })
+                    }
+                    _ => continue,
+                }
+            },
+            Some((event, data)) = events.recv(), if identified => {
+                sequence += 1;
+                json!({ "op": 0, "t": event, "s": sequence, "d": data })
+            },
+        };
+        if tx.send(send(payload)).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// the response to a request to the REST api
+fn rest_reply(request: &Request, gateway_url: &str) -> warp::reply::Response {
+    let segments = request
+        .path
+        .trim_matches('/')
+        .split('/')
+        .collect::<Vec<_>>();
+    match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["gateway"]) => warp::reply::json(&json!({ "url": gateway_url })).into_response(),
+        ("GET", ["gateway", "bot"]) => warp::reply::json(&json!({
+            "url": gateway_url,
+            "shards": 1,
+            "session_start_limit": {
+                "total": 1000,
+                "remaining": 1000,
+                "reset_after": 0,
+                "max_concurrency": 1,
+            },
+        }))
+        .into_response(),
+        // the registered commands aren't read back by the bot, so none are returned
+        ("PUT", ["applications", _, "commands"])
+        | ("PUT", ["applications", _, "guilds", _, "commands"]) => {
+            warp::reply::json(&json!([])).into_response()
+        }
+        ("POST", ["interactions", _, _, "callback"]) => StatusCode::NO_CONTENT.into_response(),
+        ("POST", ["channels", channel_id, "messages"]) => {
+            let content = request.body["content"].as_str().unwrap_or_default();
+            let channel_id = channel_id.parse().unwrap_or_default();
+            warp::reply::json(&message_json(0, channel_id, BOT_ID, content)).into_response()
+        }
+        ("PATCH", ["channels", channel_id, "messages", message_id]) => {
+            let content = request.body["content"].as_str().unwrap_or_default();
+            let channel_id = channel_id.parse().unwrap_or_default();
+            let message_id = message_id.parse().unwrap_or_default();
+            warp::reply::json(&message_json(message_id, channel_id, BOT_ID, content))
+                .into_response()
+        }
+        _ => warp::reply::with_status(
+            warp::reply::json(&json!({ "code": 0, "message": "404: Not Found" })),
+            StatusCode::NOT_FOUND,
+        )
+        .into_response(),
+    }
+}
+
+/// a user, as sent by discord
+fn user_json(user_id: u64) -> Value {
+    json!({
+        "id": user_id.to_string(),
+        "username": format!("user{}", user_id),
+        "discriminator": "0",
+        "global_name": null,
+        "avatar": null,
+        "bot": user_id == BOT_ID,
+    })
+}
+
+/// a member of a guild, as sent by discord
+fn member_json(guild_id: u64, user_id: u64) -> Value {
+    json!({
+        "user": user_json(user_id),
+        "guild_id": guild_id.to_string(),
+        "roles": [],
+        "joined_at": "2026-01-01T00:00:00.000000+00:00",
+        "deaf": false,
+        "mute": false,
+        "flags": 0,
+        "permissions": "0",
+    })
+}
+
+/// a message, as sent by discord
+fn message_json(message_id: u64, channel_id: u64, author_id: u64, content: &str) -> Value {
+    json!({
+        "id": message_id.to_string(),
+        "channel_id": channel_id.to_string(),
+        "author": user_json(author_id),
+        "content": content,
+        "timestamp": "2026-01-01T00:00:00.000000+00:00",
+        "edited_timestamp": null,
+        "tts": false,
+        "mention_everyone": false,
+        "mentions": [],
+        "mention_roles": [],
+        "attachments": [],
+        "embeds": [],
+        "pinned": false,
+        "type": 0,
+    })
+}
+
+/// the event sent once the bot has identified itself, listing the guilds it is in as unavailable
+fn ready_json(guilds: &[u64]) -> Value {
+    json!({
+        "v": 10,
+        "user": user_json(BOT_ID),
+        "guilds": guilds
+            .iter()
+            .map(|g| json!({ "id": g.to_string(), "unavailable": true }))
+            .collect::<Vec<_>>(),
+        "session_id": "offline",
+        "resume_gateway_url": "ws://127.0.0.1/gateway",
+        "shard": [0, 1],
+        "application": { "id": APPLICATION_ID.to_string(), "flags": 0 },
+    })
+}
+
+/// a guild the bot has been added to, as sent by discord
+fn guild_json(guild_id: u64, name: &str, members: &[u64]) -> Value {
+    json!({
+        "id": guild_id.to_string(),
+        "name": name,
+        "icon": null,
+        "icon_hash": null,
+        "splash": null,
+        "discovery_splash": null,
+        "owner_id": BOT_ID.to_string(),
+        "afk_channel_id": null,
+        "afk_timeout": 300,
+        "verification_level": 0,
+        "default_message_notifications": 0,
+        "explicit_content_filter": 0,
+        "roles": [],
+        "emojis": [],
+        "features": [],
+        "mfa_level": 0,
+        "application_id": null,
+        "system_channel_id": null,
+        "system_channel_flags": 0,
+        "rules_channel_id": null,
+        "vanity_url_code": null,
+        "description": null,
+        "banner": null,
+        "premium_tier": 0,
+        "preferred_locale": "en-GB",
+        "public_updates_channel_id": null,
+        "nsfw_level": 0,
+        "stickers": [],
+        "premium_progress_bar_enabled": false,
+        "joined_at": "2026-01-01T00:00:00.000000+00:00",
+        "large": false,
+        "member_count": members.len() + 1,
+        "voice_states": [],
+        "members": members
+            .iter()
+            .chain([&BOT_ID])
+            .map(|id| member_json(guild_id, *id))
+            .collect::<Vec<_>>(),
+        "channels": [],
+        "threads": [],
+        "presences": [],
+        "stage_instances": [],
+        "guild_scheduled_events": [],
+    })
+}
@@ -1,10 +1,14 @@
 //! A handler for a guild, each guild will have one handler instance to manage it
 
 use std::{
+    future::Future,
+    pin::Pin,
     sync::{atomic::Ordering, Arc},
+    task::Poll,
     time::Duration,
 };
 
+use chrono::{Timelike, Utc};
 use log::{error, info, trace, warn};
 use serenity::{
     all::Interaction,
@@ -16,21 +20,34 @@ use serenity::{
 use tokio::{
     select,
     sync::mpsc::{UnboundedReceiver, UnboundedSender},
-    sync::RwLock,
+    sync::{watch, RwLock},
     task::JoinHandle,
+    time::{interval, interval_at, timeout_at, Instant, MissedTickBehavior},
 };
 
-use super::manager::{DiscordEvent, InternalSender};
+use super::{
+    countdown::update_countdowns,
+    dst::announce_dst_changes,
+    manager::{DiscordEvent, InternalSender},
+    scanner::scan_message,
+    utils::{is_refused, BotDiscordId},
+    worldclock::update_world_clocks,
+};
 use crate::{
     discord_bot::commands::{
         application_command, autocomplete, command, handle_modal, interaction as handle_interaction,
     },
     state::AppState,
+    store::{Feature, GuildConfig},
 };
 
 /// handle an interaction generated by slash command.
 /// matches over the type of interaction and then handles it appropriately, generating a response that can be sent to the user
-async fn handle_slash_command(interaction: Interaction, context: Context, app_state: AppState) {
+pub(super) async fn handle_slash_command(
+    interaction: Interaction,
+    context: Context,
+    app_state: AppState,
+) {
     match interaction {
         Interaction::Command(raw_command) => {
             trace!("Received application command: {:?}", raw_command);
@@ -59,8 +76,17 @@ async fn handle_slash_command(interaction: Interaction, context: Context, app_st
         }
         Interaction::Component(component) => {
             trace!("Received component interaction: {:?}", component);
-            if let Err(e) = handle_interaction(&component, &app_state, &context).await {
-                error!("Unable to handle component interaction: {:?}", e);
+            let response = handle_interaction(&component, &app_state, &context)
+                .await
+                .unwrap_or_else(|response| {
+                    response.write_to_log();
+                    response
+                });
+
+            if let Some(resp) = response.generate_response() {
+                if let Err(e) = component.create_response(&context, resp).await {
+                    error!("Unable to send component response: {:?}", e);
+                }
             }
         }
         Interaction::Autocomplete(interaction) => {
@@ -81,8 +107,17 @@ async fn handle_slash_command(interaction: Interaction, context: Context, app_st
         }
         Interaction::Modal(submit) => {
             trace!("Received modal submit: {:?}", submit);
-            if let Err(e) = handle_modal(&submit, &app_state, &context).await {
-                error!("Unable to handle modal submit: {:?}", e);
+            let response = handle_modal(&submit, &app_state, &context)
+                .await
+                .unwrap_or_else(|response| {
+                    response.write_to_log();
+                    response
+                });
+
+            if let Some(resp) = response.generate_response() {
+                if let Err(e) = submit.create_response(&context, resp).await {
+                    error!("Unable to send modal response: {:?}", e);
+                }
             }
         }
         // ping commands should not get here
@@ -90,6 +125,84 @@ async fn handle_slash_command(interaction: Interaction, context: Context, app_st
     }
 }
 
+/// how long past the shutdown deadline a handler is given to abort its unfinished tasks and stop,
+/// before it is aborted itself
+pub(super) const CLOSE_GRACE: Duration = Duration::from_secs(1);
+
+/// describe an interaction, to name the task handling it
+pub(super) fn describe(interaction: &Interaction) -> String {
+    match interaction {
+        Interaction::Command(c) => format!("/{} command", c.data.name),
+        Interaction::Component(c) => format!("component {}", c.data.custom_id),
+        Interaction::Autocomplete(c) => format!("/{} autocomplete", c.data.name),
+        Interaction::Modal(m) => format!("modal {}", m.data.custom_id),
+        _ => String::from("interaction"),
+    }
+}
+
+/// A task spawned by a handler, named so that it can be reported if it is aborted at shutdown
+pub(super) struct NamedTask {
+    /// what the task is doing, e.g. "/remind command"
+    name: String,
+    /// the spawned task
+    handle: JoinHandle<()>,
+}
+
+impl NamedTask {
+    /// spawn a task
+    pub(super) fn spawn(
+        name: impl Into<String>,
+        task: impl Future<Output = ()> + Send + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            handle: tokio::task::spawn(task),
+        }
+    }
+}
+
+impl Future for NamedTask {
+    type Output = ();
+    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<()> {
+        // a task which panicked has still finished, and the panic was already reported by tokio
+        Pin::new(&mut self.handle).poll(cx).map(|_| ())
+    }
+}
+
+/// let the running tasks finish, aborting any which are still running at the deadline if there is one.
+/// returns the names of the aborted tasks
+pub(super) async fn finish(
+    mut task_handles: FuturesUnordered<NamedTask>,
+    deadline: Option<Instant>,
+) -> Vec<String> {
+    let drain = async { while task_handles.next().await.is_some() {} };
+    let finished = match deadline {
+        Some(deadline) => timeout_at(deadline, drain).await.is_ok(),
+        None => {
+            drain.await;
+            true
+        }
+    };
+    match finished {
+        true => Vec::new(),
+        false => task_handles
+            .iter()
+            .map(|task| {
+                task.handle.abort();
+                task.name.clone()
+            })
+            .collect(),
+    }
+}
+
+/// the instant the next minute starts, so that clocks tick over with the wall clock
+fn next_minute() -> Instant {
+    let now = Utc::now();
+    let into_minute = Duration::from_secs(now.second().into())
+        + Duration::from_millis(now.timestamp_subsec_millis().into());
+    Instant::now() + Duration::from_secs(60).saturating_sub(into_minute)
+}
+
 /// a handler which manages a guild, interacting with and responding to all events as required
 pub struct GuildHandler {
     /// the id of the guild being managed, generated by discord
@@ -106,11 +219,13 @@ pub struct GuildHandler {
     /// the user_id of the bot
     bot_user_id: u64,
     /// a handle to the internal task managing the guild once started
-    handle: Option<JoinHandle<()>>,
+    handle: Option<JoinHandle<Vec<String>>>,
     /// the receiving end of the internal communication channel
     internal_rx: Arc<RwLock<UnboundedReceiver<DiscordEvent>>>,
     /// the sending end of the internal communication channel
     pub internal_tx: UnboundedSender<DiscordEvent>,
+    /// the configuration of the guild, which changes whenever it is edited with `/config`
+    config: watch::Receiver<GuildConfig>,
 }
 
 impl GuildHandler {
@@ -125,6 +240,7 @@ impl GuildHandler {
         app_state: AppState,
         bot_user_id: u64,
         sender: InternalSender,
+        config: watch::Receiver<GuildConfig>,
     ) -> Self {
         let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
         GuildHandler {
@@ -137,24 +253,74 @@ impl GuildHandler {
             bot_user_id,
             internal_rx: Arc::new(RwLock::new(rx)),
             internal_tx: tx,
+            config,
         }
     }
 
-    /// close this handler, at first with a soft close but will force-kill after the provided timeout.
-    pub async fn close(&mut self, timeout: Duration) -> Result<(), Box<dyn std::error::Error>> {
-        if let Some(s) = self.handle.as_mut() {
-            tokio::pin!(s);
-            self.internal_tx.send(DiscordEvent::Shutdown)?;
-            if tokio::time::timeout(timeout, &mut s).await.is_err() {
+    /// create a handler for a guild, reading what it needs from the data stored on the context.
+    /// the handler is not started, the manager starts it once no other handler is running for the guild
+    pub async fn create(
+        context: Context,
+        guild_id: GuildId,
+        guild_name: String,
+    ) -> Result<Self, String> {
+        let data = context.data.read().await;
+        let bot_user_id = data
+            .get::<BotDiscordId>()
+            .ok_or("BotDiscordId not found in context")?
+            .get();
+        let sender = data
+            .get::<InternalSender>()
+            .ok_or("InternalSender not found in context")?
+            .clone();
+        let app_state = data
+            .get::<AppState>()
+            .ok_or("AppState not found in context")?
+            .clone();
+        drop(data);
+
+        // the guild's configuration is loaded as it becomes available, and kept up to date from then on
+        let config = app_state.watch_guild_config(guild_id.into()).await;
+
+        Ok(Self::new(
+            guild_id,
+            guild_name,
+            context,
+            app_state,
+            bot_user_id,
+            sender,
+            config,
+        ))
+    }
+
+    /// whether the handler has been started, and is still running
+    pub fn is_running(&self) -> bool {
+        self.handle.as_ref().is_some_and(|h| !h.is_finished())
+    }
+
+    /// close this handler, letting the tasks it is running finish until the deadline, when any still
+    /// running are aborted. returns the names of the aborted tasks
+    pub async fn close(
+        &mut self,
+        deadline: Instant,
+    ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let Some(mut handle) = self.handle.take() else {
+            return Ok(Vec::new());
+        };
+        self.internal_tx.send(DiscordEvent::Shutdown(deadline))?;
+        match timeout_at(deadline + CLOSE_GRACE, &mut handle).await {
+            Ok(aborted) => Ok(aborted?),
+            Err(_) => {
                 warn!(
                     "failed to close handler for {} within timeout period, aborting",
                     self.guild_id
                 );
-                s.abort();
+                handle.abort();
+                // the handler never reached the end of its task, where it would disconnect itself
+                self.app_state.num_connected.fetch_sub(1, Ordering::Relaxed);
+                Ok(vec![String::from("guild handler")])
             }
         }
-        self.handle = None;
-        Ok(())
     }
 
     /// begin monitoring a guild for interaction.
@@ -167,30 +333,65 @@ impl GuildHandler {
             let context = self.context.clone();
             let _bot_user_id = self.bot_user_id;
             let app_state = self.app_state.clone();
+            let mut config = self.config.clone();
 
             info!("Monitoring guild with id {:?}", guild);
 
             app_state.num_connected.fetch_add(1, Ordering::Relaxed);
 
             self.handle = Some(tokio::task::spawn(async move {
-                // register all commands
+                // register all commands. a refusal isn't retried, e.g. the bot isn't in the guild, and
+                // is only handling the commands of users who installed it to their accounts
                 while let Err(e) = guild
                     .set_application_commands(&context, application_command())
                     .await
                 {
                     error!("failed to register commands for guild {}: {}", guild, e);
+                    if is_refused(&e) {
+                        break;
+                    }
                     tokio::time::sleep(Duration::from_secs(10)).await;
                 }
 
                 let mut internal_rx = internal_rx.write().await;
-                let mut task_handles = FuturesUnordered::new();
+                let mut task_handles = FuturesUnordered::<NamedTask>::new();
+                // set when the handler is asked to shut down, after which unfinished tasks are aborted
+                let mut deadline = None;
+
+                // world clocks and countdowns are refreshed straight away, resuming anything from
+                // before a restart, and then at the start of every minute
+                let mut clock_tick = interval_at(next_minute(), Duration::from_secs(60));
+                clock_tick.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                let current = config.borrow_and_update().clone();
+                if current.enabled(Feature::WorldClocks) {
+                    task_handles.push(NamedTask::spawn(
+                        "world clock update",
+                        update_world_clocks(guild, context.clone(), app_state.clone()),
+                    ));
+                }
+                if current.enabled(Feature::Countdowns) {
+                    task_handles.push(NamedTask::spawn(
+                        "countdown update",
+                        update_countdowns(guild, context.clone(), app_state.clone(), true),
+                    ));
+                }
+
+                // upcoming daylight savings changes are checked for hourly, starting straight away
+                let mut dst_tick = interval(Duration::from_secs(60 * 60));
+                dst_tick.set_missed_tick_behavior(MissedTickBehavior::Delay);
 
                 loop {
                     select! {
-                        Some(message) = internal_rx.recv() => {
+                        message = internal_rx.recv() => {
+                            let message = match message {
+                                Some(message) => message,
+                                // every sender has been dropped, so no more events can arrive
+                                None => break,
+                            };
                             match message {
-                                DiscordEvent::Shutdown => {
+                                DiscordEvent::Shutdown(at) => {
                                     internal_rx.close();
+                                    deadline = Some(at);
                                     break;
                                 },
                                 DiscordEvent::Interaction(interaction) => {
@@ -202,36 +403,78 @@ impl GuildHandler {
 
                                     let t_ctx = context.clone();
                                     let t_app_state = app_state.clone();
-                                    task_handles.push(tokio::task::spawn(async move {
+                                    task_handles.push(NamedTask::spawn(describe(&interaction), async move {
                                         handle_slash_command(*interaction, t_ctx, t_app_state).await;
                                     }))
                                 },
-                                DiscordEvent::Message(_) => {
-                                    continue; //ignore messages :)
+                                DiscordEvent::Message(message) => {
+                                    let t_ctx = context.clone();
+                                    let t_app_state = app_state.clone();
+                                    let t_config = config.borrow().clone();
+                                    task_handles.push(NamedTask::spawn("message scan", async move {
+                                        scan_message(*message, t_ctx, t_app_state, t_config).await;
+                                    }))
                                 }
                                 e => {
                                     error!("bot ignoring unexpected event: {:?}", e);
                                 }
                             }
                         },
+                        _ = clock_tick.tick() => {
+                            let current = config.borrow().clone();
+                            if current.enabled(Feature::WorldClocks) {
+                                task_handles.push(NamedTask::spawn(
+                                    "world clock update",
+                                    update_world_clocks(guild, context.clone(), app_state.clone()),
+                                ));
+                            }
+                            if current.enabled(Feature::Countdowns) {
+                                task_handles.push(NamedTask::spawn(
+                                    "countdown update",
+                                    update_countdowns(guild, context.clone(), app_state.clone(), false),
+                                ));
+                            }
+                        },
+                        _ = dst_tick.tick() => {
+                            task_handles.push(NamedTask::spawn(
+                                "daylight savings check",
+                                announce_dst_changes(
+                                    guild,
+                                    context.clone(),
+                                    app_state.clone(),
+                                    config.borrow().clone(),
+                                ),
+                            ));
+                        },
+                        Ok(()) = config.changed() => {
+                            info!("applying new configuration for guild {}", guild);
+                            // check straight away, e.g. so a newly set announcement channel
+                            // hears about changes within the notice period
+                            task_handles.push(NamedTask::spawn(
+                                "daylight savings check",
+                                announce_dst_changes(
+                                    guild,
+                                    context.clone(),
+                                    app_state.clone(),
+                                    config.borrow_and_update().clone(),
+                                ),
+                            ));
+                        },
                         // drain task handles as they complete
                         _ = task_handles.next(), if !task_handles.is_empty() => {},
-                        else => break,
                     }
                 }
 
-                // complete all task_handles with a timeout
-                if !task_handles.is_empty() {
-                    //XXX: timeout is not implemented yet
-                    while task_handles.next().await.is_some() {}
-                }
+                // let the running tasks finish, aborting any which are still running at the deadline
+                let aborted = finish(task_handles, deadline).await;
 
-                println!("No longer monitoring server with id {:?}", guild);
+                info!("No longer monitoring guild with id {:?}", guild);
 
                 app_state.num_connected.fetch_sub(1, Ordering::Relaxed);
+                aborted
             }))
         } else {
-            eprintln!("Already monitoring guild");
+            warn!("Already monitoring guild with id {:?}", self.guild_id);
         }
     }
 }
@@ -13,7 +13,7 @@ use serenity::{
     },
 };
 
-use crate::{discord_bot::guilds::GuildHandler, state::AppState};
+use crate::discord_bot::guilds::GuildHandler;
 
 use super::{
     manager::{DiscordEvent, InternalSender},
@@ -63,23 +63,13 @@ impl EventHandler for Handler {
     }
 
     /// initalise a guild handler when the bot is added to a new guild
-    async fn guild_create(&self, ctx: Context, guild: Guild, _: Option<bool>) {
-        let data_read = ctx.data.read().await;
-
+    async fn guild_create(&self, ctx: Context, guild: Guild, is_new: Option<bool>) {
         //wait for the bot's discord id to exist in the context
-        while data_read.get::<BotDiscordId>().is_none() {
+        while ctx.data.read().await.get::<BotDiscordId>().is_none() {
             tokio::time::sleep(std::time::Duration::from_millis(1000)).await;
         }
 
-        let id = match data_read.get::<BotDiscordId>() {
-            Some(id) => id.get(),
-            None => {
-                error!("BotDiscordId not found in context");
-                return;
-            }
-        };
-
-        let internal_sender = match data_read.get::<InternalSender>() {
+        let internal_sender = match ctx.data.read().await.get::<InternalSender>() {
             Some(internal_sender) => internal_sender.clone(),
             None => {
                 error!("InternalSender not found in context");
@@ -87,25 +77,18 @@ impl EventHandler for Handler {
             }
         };
 
-        let app_state = match data_read.get::<AppState>() {
-            Some(app_state) => app_state.clone(),
-            None => {
-                error!("AppState not found in context");
+        // the manager starts the handler, unless the guild already has one running
+        let guild_handler = match GuildHandler::create(ctx.clone(), guild.id, guild.name).await {
+            Ok(guild_handler) => guild_handler,
+            Err(e) => {
+                error!("failed to create a handler for guild {}: {}", guild.id, e);
                 return;
             }
         };
 
-        let mut guild_handler = GuildHandler::new(
-            guild.id,
-            guild.name,
-            ctx.clone(),
-            app_state,
-            id,
-            internal_sender.clone(),
-        );
-        guild_handler.start();
-
-        if let Err(e) = internal_sender.send(DiscordEvent::NewGuild(guild_handler)) {
+        if let Err(e) =
+            internal_sender.send(DiscordEvent::NewGuild(Box::new(guild_handler), is_new))
+        {
             error!("Error sending new guild to internal sender: {:?}", e);
         }
     }
@@ -138,6 +121,17 @@ impl EventHandler for Handler {
             data_write.insert::<BotDiscordId>(BotDiscordId::new(ready.user.id.0.into()));
         }
 
+        // the manager can now create handlers for guilds it hears about before they are added
+        match ctx.data.read().await.get::<InternalSender>() {
+            Some(internal_sender) => {
+                if let Err(e) = internal_sender.send(DiscordEvent::Connected(Box::new(ctx.clone())))
+                {
+                    error!("Error sending connection to internal sender: {:?}", e);
+                }
+            }
+            None => error!("InternalSender not found in context"),
+        }
+
         // ready
         //     .user
         //     .edit(
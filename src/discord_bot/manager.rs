@@ -1,28 +1,39 @@
 //! The global manager for the bot, which manages all guilds as individual tasks
 //! and coordinates events between them.
 
-use std::{collections::HashMap, ops::DerefMut, time::Duration};
+use std::{future::Future, ops::DerefMut, time::Duration};
 
-use log::{error, warn};
+use log::{error, info, warn};
 use serenity::{
     all::Interaction,
-    futures::{stream::FuturesUnordered, StreamExt},
+    client::{ClientBuilder, Context},
+    http::HttpBuilder,
     model::prelude::Message,
     prelude::{GatewayIntents, TypeMapKey},
-    Client,
 };
 use tokio::{
     select,
-    sync::mpsc::{unbounded_channel, UnboundedSender},
+    sync::{
+        mpsc::{unbounded_channel, UnboundedSender},
+        oneshot,
+    },
+    time::Instant,
 };
 
-use super::{guilds::GuildHandler, handler::Handler};
+use super::{
+    guilds::GuildHandler, handler::Handler, reminders::DiscordDelivery, router::GuildRouter,
+};
+use crate::{reminders::Scheduler, state::AppState};
 
 /// An event that may occur between the various discord services
 #[derive(Debug)]
 pub enum DiscordEvent {
-    /// a guild has been added, and must be managed
-    NewGuild(GuildHandler),
+    /// a guild has been added, and must be managed. whether discord said it is new to the bot, rather
+    /// than sent again e.g. after reconnecting, is passed along with it
+    // the handler is boxed, as is quite large and so should be heap-allocated
+    NewGuild(Box<GuildHandler>, Option<bool>),
+    /// the bot has connected to discord, so handlers can be created for guilds as events arrive
+    Connected(Box<Context>),
     /// a guild was deleted and should no longer be managed
     DeletedGuild(u64),
     /// an interaction has been received from the user, and must be handled by a specific guild
@@ -30,8 +41,20 @@ pub enum DiscordEvent {
     Interaction(Box<Interaction>),
     /// a new message received from any guild
     Message(Box<Message>),
-    /// a shutdown command to be sent to a guild, when received the guild should cease all activity and shut down
-    Shutdown,
+    /// a shutdown command to be sent to a guild, when received the guild should cease all activity and shut down,
+    /// aborting anything still running at the deadline
+    Shutdown(Instant),
+}
+
+/// the default time given to guild handlers to finish what they are doing when the bot shuts down
+const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What was left unfinished when the bot shut down
+#[derive(Debug, Default)]
+pub struct ShutdownReport {
+    /// the tasks which were still running at the deadline, and so were aborted,
+    /// e.g. "/remind command in guild 10"
+    pub aborted: Vec<String>,
 }
 
 /// A channel that can be used to send messages between guild handlers and the master discord process
@@ -61,6 +84,12 @@ pub struct DiscordBotBuilder<T> {
     discord_token: Option<String>,
     /// the database to use for storing data
     app_state: Option<T>,
+    /// the scheduler which delivers reminders
+    scheduler: Option<Scheduler>,
+    /// the base url of the discord api, if not discord's own
+    api_url: Option<String>,
+    /// the time given to guild handlers to finish what they are doing when shutting down
+    shutdown_timeout: Duration,
 }
 
 impl<T> DiscordBotBuilder<T> {
@@ -77,6 +106,27 @@ impl<T> DiscordBotBuilder<T> {
         self
     }
 
+    /// The scheduler to deliver reminders with, it will run for as long as the bot does.
+    pub fn scheduler(mut self, scheduler: Scheduler) -> Self {
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    /// Send requests to the discord api to another server, e.g. `http://127.0.0.1:3000`.
+    /// The gateway is found through the api, so this can point the whole bot at a stand-in.
+    #[cfg(test)]
+    pub fn api_url(mut self, url: String) -> Self {
+        self.api_url = Some(url);
+        self
+    }
+
+    /// How long to wait for in-flight interactions and other tasks to finish when shutting down,
+    /// before they are aborted. Defaults to 10 seconds.
+    pub fn shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
+
     /// Build the bot, and create a [DiscordBot] instance.
     pub fn build(self) -> Result<DiscordBot<T>, String> {
         let discord_token = match self.discord_token {
@@ -92,6 +142,9 @@ impl<T> DiscordBotBuilder<T> {
         Ok(DiscordBot {
             discord_token,
             app_state,
+            scheduler: self.scheduler,
+            api_url: self.api_url,
+            shutdown_timeout: self.shutdown_timeout,
         })
     }
 }
@@ -101,6 +154,9 @@ impl<T> Default for DiscordBotBuilder<T> {
         DiscordBotBuilder {
             discord_token: None,
             app_state: None,
+            scheduler: None,
+            api_url: None,
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
         }
     }
 }
@@ -112,140 +168,164 @@ pub struct DiscordBot<T> {
     discord_token: String,
     /// the database to use for storing data
     app_state: T,
+    /// the scheduler which delivers reminders, if reminders are enabled
+    scheduler: Option<Scheduler>,
+    /// the base url of the discord api, if not discord's own
+    api_url: Option<String>,
+    /// the time given to guild handlers to finish what they are doing when shutting down
+    shutdown_timeout: Duration,
 }
 
-impl<T: Send + Sync + 'static + Clone + TypeMapKey<Value = T>> DiscordBot<T> {
+// the handlers read the state back out of the client's data as an [AppState]
+impl DiscordBot<AppState> {
     /// Get a builder to setup a new discord bot
-    pub fn builder() -> DiscordBotBuilder<T> {
+    pub fn builder() -> DiscordBotBuilder<AppState> {
         DiscordBotBuilder::default()
     }
 
     /// Start the discord bot, this will connect to discords api and create internal
     /// handlers as required.
+    /// Once `shutdown` completes, every guild handler is given until the shutdown timeout to finish
+    /// what it is doing, the state is saved and the gateway is closed.
     /// Will exit when the bot has fully disconnected from all services.
-    pub async fn run(self) -> Result<(), Box<dyn std::error::Error>> {
+    pub async fn run(
+        self,
+        shutdown: impl Future<Output = ()> + Send + 'static,
+    ) -> Result<ShutdownReport, Box<dyn std::error::Error>> {
         let intents = GatewayIntents::all();
 
-        let mut client = Client::builder(&self.discord_token, intents)
+        let mut http = HttpBuilder::new(&self.discord_token);
+        if let Some(url) = &self.api_url {
+            // a stand-in for discord won't send rate limit headers
+            http = http.proxy(url).ratelimiter_disabled(true);
+        }
+
+        let mut client = ClientBuilder::new_with_http(http.build(), intents)
             .event_handler(Handler)
             .await?;
 
         let (i_tx, mut i_rx) = unbounded_channel();
+        let shutdown_tx = i_tx.clone();
 
         // scoping this off means we'll drop the write guard properly
         {
             let mut data = client.data.write().await;
             data.insert::<InternalSender>(InternalSender(i_tx));
             // data.insert::<BotDiscordId>(BotDiscordId::new(client.user_id.0));
-            data.insert::<T>(self.app_state.clone());
+            data.insert::<AppState>(self.app_state.clone());
         }
 
+        let anomalies = self.app_state.routing_anomalies.clone();
+
         let handle = tokio::task::spawn(async move {
-            let mut thread_handles = FuturesUnordered::new();
-            let mut guild_handlers: HashMap<u64, GuildHandler> = HashMap::default();
+            let mut router = GuildRouter::new(anomalies);
 
             loop {
-                select! {
-                    Some(i_e) = i_rx.recv() => {
-                        match i_e {
-                            DiscordEvent::NewGuild(handler) => {
-                                // finish creating the handler
-                                let key: u64 = handler.guild_id.into();
-                                if guild_handlers.contains_key(&key) {
-                                    panic!("tried to double handle a guild");
-                                    // if let Err(e) = handler.close(Duration::from_secs(0)).await {
-                                    //     error!("failed to close a guild handler {}", e);
-                                    // }
-                                }
-                                guild_handlers.insert(key, handler);
-                            },
-                            DiscordEvent::DeletedGuild(guild) => {
-                                // remove guild handler
-                                let mut g_h = match guild_handlers.remove(&guild) {
-                                    Some(s) => s,
-                                    None => {
-                                        error!("tried to remove non-existant guild");
-                                        return;
-                                    }
-                                };
-
-                                let t_h = tokio::task::spawn(async move {
-                                    if let Err(e) = g_h.close(Duration::from_secs(5)).await {
-                                        error!("failed to close a guild handler {}", e);
-                                    }
-                                });
-
-                                thread_handles.push(t_h);
-                            },
-                            DiscordEvent::Interaction(interaction) => {
-                                let guild_id: Option<serenity::model::id::GuildId> = match *interaction {
-                                    Interaction::Ping(_) => {
-                                        error!("got ping application command, which was not handled");
-                                        continue;
-                                    },
-                                    Interaction::Command(ref c) => c.guild_id,
-                                    Interaction::Component(ref c) => c.guild_id,
-                                    Interaction::Autocomplete(ref c) => c.guild_id,
-                                    Interaction::Modal(ref c) => c.guild_id,
-                                };
-
-                                let guild_id: u64 = match guild_id {
-                                    Some(g_id) => g_id.0.into(),
-                                    None => {
-                                        error!("got interaction without guild id");
-                                        continue;
-                                    }
-                                };
-
-                                let g_h = match guild_handlers.get(&guild_id) {
-                                    Some(s) => s.internal_tx.clone(),
-                                    None => {
-                                        error!("tried to handle message for non-existant guild id {}", guild_id);
-                                        return;
-                                    }
-                                };
-                                if let Err(e) = g_h.send(DiscordEvent::Interaction (interaction)) {
-                                    error!("failed to send interaction to guild handler {}", e);
-                                }
-                            },
-                            DiscordEvent::Message(message) => {
-                                let guild_id: u64 = match message.guild_id {
-                                    Some(g_id) => g_id.into(),
-                                    None => {
-                                        warn!("got message without guild id");
-                                        continue;
-                                    }
-                                };
-
-                                let g_h = match guild_handlers.get(&guild_id) {
-                                    Some(s) => s.internal_tx.clone(),
-                                    None => {
-                                        error!("tried to handle message for non-existant guild id {}", guild_id);
-                                        return;
-                                    }
-                                };
-
-                                if let Err(e) = g_h.send(DiscordEvent::Message(message)) {
-                                    error!("failed to send message to guild handler {}", e);
-                                }
+                let Some(i_e) = i_rx.recv().await else {
+                    // every sender is gone, so nothing more can be routed
+                    warn!("the internal channel closed, shutting down the guild handlers");
+                    return router.shutdown(Instant::now()).await;
+                };
+                match i_e {
+                    DiscordEvent::Connected(context) => router.connected(*context).await,
+                    DiscordEvent::NewGuild(handler, is_new) => router.add(*handler, is_new),
+                    DiscordEvent::DeletedGuild(guild) => router.remove(guild),
+                    DiscordEvent::Interaction(interaction) => {
+                        let guild_id = match *interaction {
+                            Interaction::Ping(_) => {
+                                error!("got ping application command, which was not handled");
+                                continue;
+                            }
+                            Interaction::Command(ref c) => c.guild_id,
+                            Interaction::Component(ref c) => c.guild_id,
+                            Interaction::Autocomplete(ref c) => c.guild_id,
+                            Interaction::Modal(ref c) => c.guild_id,
+                        };
+
+                        match guild_id {
+                            Some(g_id) => {
+                                router
+                                    .route(g_id.into(), DiscordEvent::Interaction(interaction))
+                                    .await
+                            }
+                            // interactions outside of any guild are in direct messages
+                            None => {
+                                router
+                                    .route_direct(DiscordEvent::Interaction(interaction))
+                                    .await
                             }
-                            e => error!("unexpected discord event received {:?}", e),
                         }
-                    },
-                    _ = thread_handles.next(), if !thread_handles.is_empty() => {} //drain the handles as they complete
-                    else => {
-                        panic!("both receivers closed without breaking the loop, this indicates a failure")
                     }
+                    DiscordEvent::Message(message) => match message.guild_id {
+                        Some(g_id) => {
+                            router
+                                .route(g_id.into(), DiscordEvent::Message(message))
+                                .await
+                        }
+                        None => warn!("got message without guild id"),
+                    },
+                    DiscordEvent::Shutdown(deadline) => return router.shutdown(deadline).await,
                 }
             }
         });
 
-        client.start().await?;
+        // reminders are delivered over http, so do not need to wait for the gateway.
+        // the scheduler is stopped between deliveries, so no reminder is lost mid-delivery
+        let (scheduler_stop, scheduler_stopped) = oneshot::channel::<()>();
+        let scheduler_handle = self.scheduler.map(|scheduler| {
+            tokio::task::spawn(scheduler.run_until(
+                DiscordDelivery::new(client.http.clone()),
+                async move {
+                    let _ = scheduler_stopped.await;
+                },
+            ))
+        });
 
-        // once the discord bot has shutdown, we can expect to close the manager,
-        // so we need to clean up the task for it.
-        handle.abort();
+        // shut down when asked to, or when the gateway has stopped by itself
+        let (gateway_stopped, stopped) = oneshot::channel::<()>();
+        let shard_manager = client.shard_manager.clone();
+        let timeout = self.shutdown_timeout;
+        let stopping = tokio::task::spawn(async move {
+            select! {
+                _ = shutdown => info!("shutting down, waiting up to {:?} for guild handlers", timeout),
+                _ = stopped => {},
+            }
+            let report = match shutdown_tx.send(DiscordEvent::Shutdown(Instant::now() + timeout)) {
+                Ok(()) => handle.await.unwrap_or_else(|e| {
+                    error!("the guild manager failed: {}", e);
+                    ShutdownReport::default()
+                }),
+                // the manager has already stopped, and so has every handler
+                Err(_) => ShutdownReport::default(),
+            };
+            // the handlers are finished, so the gateway can be closed
+            shard_manager.shutdown_all().await;
+            report
+        });
+
+        let result = client.start().await;
+        let _ = gateway_stopped.send(());
+        let report = stopping.await?;
 
-        Ok(())
+        if let Some(scheduler_handle) = scheduler_handle {
+            let _ = scheduler_stop.send(());
+            if let Err(e) = scheduler_handle.await {
+                error!("the reminder scheduler failed: {}", e);
+            }
+        }
+        if let Err(e) = self.app_state.flush().await {
+            error!("failed to save the state while shutting down: {}", e);
+        }
+
+        match report.aborted.is_empty() {
+            true => info!("every guild handler finished before shutting down"),
+            false => warn!(
+                "aborted {} unfinished tasks while shutting down: {}",
+                report.aborted.len(),
+                report.aborted.join(", ")
+            ),
+        }
+        result?;
+        Ok(report)
     }
 }
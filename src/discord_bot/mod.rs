@@ -2,9 +2,21 @@
 //! The bot is built on top of the Serenity discord crate.
 
 mod commands;
+mod countdown;
+mod direct;
+mod dst;
 mod guilds;
 mod handler;
 mod manager;
+mod reminders;
+mod router;
+mod scanner;
 mod utils;
+mod worldclock;
+
+#[cfg(test)]
+mod fake;
+#[cfg(test)]
+mod tests;
 
 pub use manager::{DiscordBot, DiscordBotBuilder};
@@ -0,0 +1,73 @@
+//! Delivery of reminders through discord, used by the [crate::reminders::Scheduler]
+
+use std::sync::Arc;
+
+use log::warn;
+use serenity::{
+    async_trait,
+    builder::{CreateAllowedMentions, CreateMessage},
+    http::Http,
+    model::id::{ChannelId, UserId},
+};
+
+use crate::{
+    reminders::ReminderDelivery,
+    store::{Reminder, ReminderTarget},
+};
+
+/// delivers reminders as discord messages
+pub struct DiscordDelivery {
+    /// the http client to send messages with
+    http: Arc<Http>,
+}
+
+impl DiscordDelivery {
+    /// create a delivery using the provided http client
+    pub fn new(http: Arc<Http>) -> Self {
+        Self { http }
+    }
+
+    /// build the message which delivers a reminder, only ever pinging the user who set it
+    fn message(reminder: &Reminder) -> CreateMessage {
+        CreateMessage::new()
+            .content(format!(
+                "⏰ <@{}>, you asked me <t:{}:R> to remind you: {}",
+                reminder.user_id,
+                reminder.created.timestamp(),
+                reminder.message
+            ))
+            .allowed_mentions(CreateAllowedMentions::new().users([UserId::new(reminder.user_id)]))
+    }
+
+    /// send a reminder to its user as a direct message
+    async fn direct_message(&self, reminder: &Reminder) -> Result<(), String> {
+        UserId::new(reminder.user_id)
+            .direct_message(&*self.http, Self::message(reminder))
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[async_trait]
+impl ReminderDelivery for DiscordDelivery {
+    async fn deliver(&self, reminder: &Reminder) -> Result<(), String> {
+        match reminder.target {
+            ReminderTarget::Channel(channel_id) => {
+                let sent = ChannelId::new(channel_id)
+                    .send_message(&*self.http, Self::message(reminder))
+                    .await;
+                if let Err(e) = sent {
+                    // the channel may have been deleted, or we may have lost access to it
+                    warn!(
+                        "failed to deliver reminder {} in channel {}, sending as a direct message instead: {}",
+                        reminder.id, channel_id, e
+                    );
+                    return self.direct_message(reminder).await;
+                }
+                Ok(())
+            }
+            ReminderTarget::DirectMessage => self.direct_message(reminder).await,
+        }
+    }
+}
@@ -0,0 +1,339 @@
+//! Routing of events to the handler of the guild they belong to, or of direct messages.
+//! Unexpected events, such as a guild being added twice or an event for a guild the bot has no
+//! handler for, are recovered from rather than stopping the routing for every guild, and counted in
+//! [RoutingAnomalies] for the healthcheck.
+
+use std::{
+    collections::HashMap,
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
+
+use log::{error, info, warn};
+use serenity::{
+    all::Interaction,
+    client::Context,
+    futures::{stream::FuturesUnordered, StreamExt},
+    model::id::GuildId,
+};
+use tokio::{task::JoinHandle, time::Instant};
+
+use super::{
+    direct::DirectHandler,
+    guilds::GuildHandler,
+    manager::{DiscordEvent, ShutdownReport},
+};
+use crate::state::RoutingAnomalies;
+
+/// the time given to a replaced handler, or the handler of a deleted guild, to finish its tasks
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The handlers of every guild and of direct messages, and the events waiting for a handler
+pub struct GuildRouter {
+    /// the handler of each guild, by guild id
+    handlers: HashMap<u64, GuildHandler>,
+    /// the handler of direct messages, once the bot has connected
+    direct: Option<DirectHandler>,
+    /// events for guilds without a handler, held until the bot has connected and one can be created
+    queued: HashMap<u64, Vec<DiscordEvent>>,
+    /// events in direct messages which arrived before the bot had connected
+    queued_direct: Vec<DiscordEvent>,
+    /// the context handlers are created in, once the bot has connected
+    context: Option<Context>,
+    /// handlers which were replaced or whose guild was deleted, and are finishing their tasks
+    closing: Vec<JoinHandle<()>>,
+    /// counts of the unexpected events recovered from
+    anomalies: Arc<RoutingAnomalies>,
+}
+
+impl GuildRouter {
+    /// a router with no guilds, counting anomalies in the provided counters
+    pub fn new(anomalies: Arc<RoutingAnomalies>) -> Self {
+        Self {
+            handlers: HashMap::new(),
+            direct: None,
+            queued: HashMap::new(),
+            queued_direct: Vec::new(),
+            context: None,
+            closing: Vec::new(),
+            anomalies,
+        }
+    }
+
+    /// the bot has connected, so direct messages can be handled and handlers can be created for the
+    /// guilds with queued events
+    pub async fn connected(&mut self, context: Context) {
+        self.context = Some(context);
+        if !self.direct.as_ref().is_some_and(|d| d.is_running()) {
+            self.start_direct().await;
+        }
+        for event in std::mem::take(&mut self.queued_direct) {
+            self.route_direct(event).await;
+        }
+        for (guild, events) in std::mem::take(&mut self.queued) {
+            for event in events {
+                self.route(guild, event).await;
+            }
+        }
+    }
+
+    /// start managing a guild. discord sends every guild again after reconnecting, so a handler
+    /// which is still running is kept, and only one which has stopped is replaced
+    pub fn add(&mut self, handler: GuildHandler, is_new: Option<bool>) {
+        let guild: u64 = handler.guild_id.into();
+        match self.handlers.get(&guild) {
+            Some(running) if running.is_running() => match is_new {
+                Some(false) => info!("guild {} is available again, keeping its handler", guild),
+                _ => warn!(
+                    "guild {} was added again, keeping its running handler",
+                    guild
+                ),
+            },
+            _ => {
+                if self.insert(handler) {
+                    warn!("the handler for guild {} has stopped, replacing it", guild);
+                    self.anomalies
+                        .replaced_handlers
+                        .fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+        if let Some(events) = self.queued.remove(&guild) {
+            for event in events {
+                self.send(guild, event);
+            }
+        }
+    }
+
+    /// stop managing a guild
+    pub fn remove(&mut self, guild: u64) {
+        match self.handlers.remove(&guild) {
+            Some(handler) => self.close(guild, handler),
+            None => {
+                warn!("tried to remove guild {}, which has no handler", guild);
+                self.anomalies
+                    .unknown_deletions
+                    .fetch_add(1, Ordering::Relaxed);
+                if let Some(events) = self.queued.remove(&guild) {
+                    self.drop_events(events.len());
+                }
+            }
+        }
+    }
+
+    /// pass an event on to the handler of its guild, creating a handler if the guild doesn't have a
+    /// running one, or queueing the event if a handler can't be created yet. interactions from guilds
+    /// the bot isn't in are passed on to the direct message handler instead
+    pub async fn route(&mut self, guild: u64, event: DiscordEvent) {
+        if self.handlers.get(&guild).is_some_and(|h| h.is_running()) {
+            self.send(guild, event);
+            return;
+        }
+
+        let Some(context) = self.context.clone() else {
+            info!(
+                "queueing an event for guild {} until the bot connects",
+                guild
+            );
+            self.anomalies.queued_events.fetch_add(1, Ordering::Relaxed);
+            self.queued.entry(guild).or_default().push(event);
+            return;
+        };
+
+        // commands a user installed to their own account can be used in guilds the bot isn't in.
+        // discord will never send those guilds, or their removal, so they get no handler
+        let guild_id = GuildId::new(guild);
+        if !context.cache.guilds().contains(&guild_id) {
+            if let DiscordEvent::Interaction(mut interaction) = event {
+                info!(
+                    "answering an interaction from guild {}, which the bot isn't in, as a direct message",
+                    guild
+                );
+                outside_guild(&mut interaction);
+                return self
+                    .route_direct(DiscordEvent::Interaction(interaction))
+                    .await;
+            }
+        }
+
+        match self.handlers.contains_key(&guild) {
+            true => warn!("the handler for guild {} has stopped, replacing it", guild),
+            false => warn!(
+                "got an event for guild {}, which has no handler, creating one",
+                guild
+            ),
+        }
+        let name = context
+            .cache
+            .guild(guild_id)
+            .map(|g| g.name.clone())
+            .unwrap_or_default();
+        match GuildHandler::create(context, guild_id, name).await {
+            Ok(handler) => {
+                let counter = match self.insert(handler) {
+                    true => &self.anomalies.replaced_handlers,
+                    false => &self.anomalies.created_handlers,
+                };
+                counter.fetch_add(1, Ordering::Relaxed);
+                self.send(guild, event);
+            }
+            Err(e) => {
+                error!("failed to create a handler for guild {}: {}", guild, e);
+                self.drop_events(1);
+            }
+        }
+    }
+
+    /// pass an event from direct messages on to their handler, restarting it if it has stopped, or
+    /// queueing the event if the bot hasn't connected yet
+    pub async fn route_direct(&mut self, event: DiscordEvent) {
+        if !self.direct.as_ref().is_some_and(|d| d.is_running()) {
+            if self.context.is_none() {
+                info!("queueing a direct message event until the bot connects");
+                self.anomalies.queued_events.fetch_add(1, Ordering::Relaxed);
+                self.queued_direct.push(event);
+                return;
+            }
+            warn!("the direct message handler has stopped, replacing it");
+            self.anomalies
+                .replaced_handlers
+                .fetch_add(1, Ordering::Relaxed);
+            self.start_direct().await;
+        }
+
+        let Some(direct) = &self.direct else {
+            self.drop_events(1);
+            return;
+        };
+        if let Err(e) = direct.internal_tx.send(event) {
+            error!("failed to send event to direct message handler {}", e);
+            self.drop_events(1);
+        }
+    }
+
+    /// start handling direct messages, replacing the handler which was, if any
+    async fn start_direct(&mut self) {
+        let Some(context) = self.context.clone() else {
+            return;
+        };
+        let mut direct = match DirectHandler::create(context).await {
+            Ok(direct) => direct,
+            Err(e) => {
+                error!("failed to create the direct message handler: {}", e);
+                return;
+            }
+        };
+        direct.start();
+        if let Some(mut stale) = self.direct.replace(direct) {
+            self.closing.retain(|task| !task.is_finished());
+            self.closing.push(tokio::task::spawn(async move {
+                if let Err(e) = stale.close(Instant::now() + CLOSE_TIMEOUT).await {
+                    error!("failed to close the direct message handler {}", e);
+                }
+            }));
+        }
+    }
+
+    /// start a handler and route its guild's events to it, returning whether it replaced another
+    fn insert(&mut self, mut handler: GuildHandler) -> bool {
+        let guild: u64 = handler.guild_id.into();
+        handler.start();
+        match self.handlers.insert(guild, handler) {
+            Some(stale) => {
+                self.close(guild, stale);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// send an event to the handler of a guild
+    fn send(&mut self, guild: u64, event: DiscordEvent) {
+        let Some(handler) = self.handlers.get(&guild) else {
+            self.drop_events(1);
+            return;
+        };
+        if let Err(e) = handler.internal_tx.send(event) {
+            error!("failed to send event to guild handler {}", e);
+            self.drop_events(1);
+        }
+    }
+
+    /// count events which couldn't be passed on to a handler
+    fn drop_events(&self, count: usize) {
+        self.anomalies
+            .dropped_events
+            .fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    /// close a handler in the background, letting it finish its tasks
+    fn close(&mut self, guild: u64, mut handler: GuildHandler) {
+        self.closing.retain(|task| !task.is_finished());
+        self.closing.push(tokio::task::spawn(async move {
+            match handler.close(Instant::now() + CLOSE_TIMEOUT).await {
+                Ok(aborted) if !aborted.is_empty() => warn!(
+                    "aborted unfinished tasks for guild {}: {}",
+                    guild,
+                    aborted.join(", ")
+                ),
+                Ok(_) => {}
+                Err(e) => error!("failed to close a guild handler {}", e),
+            }
+        }));
+    }
+
+    /// close every handler, giving them until the deadline to finish their tasks
+    pub async fn shutdown(&mut self, deadline: Instant) -> ShutdownReport {
+        info!("closing {} guild handlers", self.handlers.len());
+        let closing = self
+            .handlers
+            .drain()
+            .map(|(guild, mut handler)| async move {
+                match handler.close(deadline).await {
+                    Ok(aborted) => aborted
+                        .into_iter()
+                        .map(|task| format!("{} in guild {}", task, guild))
+                        .collect(),
+                    Err(e) => {
+                        error!("failed to close a guild handler {}", e);
+                        Vec::new()
+                    }
+                }
+            })
+            .collect::<FuturesUnordered<_>>();
+        let mut aborted = closing.concat().await;
+        if let Some(mut direct) = self.direct.take() {
+            match direct.close(deadline).await {
+                Ok(tasks) => aborted.extend(
+                    tasks
+                        .into_iter()
+                        .map(|task| format!("{} in direct messages", task)),
+                ),
+                Err(e) => error!("failed to close the direct message handler {}", e),
+            }
+        }
+
+        // handlers which were already closing do so within their own timeouts
+        for task in self.closing.drain(..) {
+            if let Err(e) = task.await {
+                error!("failed to close a guild handler {}", e);
+            }
+        }
+
+        let queued = self.queued.drain().map(|(_, e)| e.len()).sum::<usize>()
+            + self.queued_direct.drain(..).len();
+        self.drop_events(queued);
+        ShutdownReport { aborted }
+    }
+}
+
+/// treat an interaction as if it were used outside of any guild, since the bot can't see or post in
+/// the guild it was used in
+fn outside_guild(interaction: &mut Interaction) {
+    match interaction {
+        Interaction::Command(c) | Interaction::Autocomplete(c) => c.guild_id = None,
+        Interaction::Component(c) => c.guild_id = None,
+        Interaction::Modal(c) => c.guild_id = None,
+        _ => {}
+    }
+}
@@ -0,0 +1,65 @@
+//! Scans messages sent in a guild for mentions of a time (e.g. "3pm", "15:30 CET" or "tomorrow at 9 EST"),
+//! and replies with discord timestamps so every reader sees the time in their own timezone.
+
+#[cfg(test)]
+mod tests;
+
+use chrono::Utc;
+use log::{error, trace};
+use serenity::{client::Context, model::prelude::Message};
+
+use crate::{
+    state::AppState,
+    store::GuildConfig,
+    time_parse::{find_all, FoundTime, ParseContext},
+};
+
+/// the most times converted in a single reply, so a long message can't make a reply too long to send
+const MAX_CONVERTED_TIMES: usize = 10;
+
+/// format a list of mentioned times as a reply, using discord timestamps to localise them for every reader
+pub fn format_reply(times: &[FoundTime]) -> String {
+    let mut lines = times
+        .iter()
+        .take(MAX_CONVERTED_TIMES)
+        .map(|m| {
+            let unix = m.parsed.time.timestamp();
+            format!("🕒 **{}** → <t:{}:t> (<t:{}:R>)", m.text, unix, unix)
+        })
+        .collect::<Vec<_>>();
+    if times.len() > MAX_CONVERTED_TIMES {
+        lines.push(format!("and {} more", times.len() - MAX_CONVERTED_TIMES));
+    }
+    lines.join("\n")
+}
+
+/// scan a message sent in a guild, replying with converted times if the guild and channel have opted in
+pub async fn scan_message(
+    message: Message,
+    context: Context,
+    app_state: AppState,
+    config: GuildConfig,
+) {
+    if message.author.bot {
+        return;
+    }
+
+    if !config.converts_in(message.channel_id.into()) {
+        return;
+    }
+
+    let author_tz = app_state
+        .effective_timezone(message.author.id.into(), message.guild_id.map(u64::from))
+        .await;
+    let parse_context =
+        ParseContext::new(Utc::now(), author_tz).month_first(config.locale.month_first());
+    let times = find_all(&message.content, &parse_context);
+    if times.is_empty() {
+        return;
+    }
+
+    trace!("converting {} times in message {}", times.len(), message.id);
+    if let Err(e) = message.reply(&context, format_reply(&times)).await {
+        error!("failed to reply with converted times: {}", e);
+    }
+}
@@ -0,0 +1,19 @@
+use chrono::Utc;
+
+use super::format_reply;
+use crate::time_parse::{find_all, ParseContext};
+
+#[test]
+fn long_lists_of_times_are_cut_short() {
+    let text = (1..=12)
+        .map(|hour| format!("{}pm UTC", hour))
+        .collect::<Vec<_>>()
+        .join(", then ");
+    let context = ParseContext::new(Utc::now(), None);
+    let times = find_all(&text, &context);
+    assert_eq!(times.len(), 12);
+
+    let reply = format_reply(&times);
+    assert_eq!(reply.lines().count(), 11, "{}", reply);
+    assert!(reply.ends_with("and 2 more"), "{}", reply);
+}
@@ -0,0 +1,455 @@
+use std::{
+    future::Future,
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
+
+use serde_json::{json, Value};
+use tokio::{sync::oneshot, task::JoinHandle};
+
+use super::{
+    fake::{FakeDiscord, APPLICATION_ID},
+    manager::ShutdownReport,
+    DiscordBot,
+};
+use crate::{
+    state::AppState,
+    store::{MemoryStore, ReminderTarget},
+};
+
+const GUILD: u64 = 10;
+const CHANNEL: u64 = 20;
+const ALICE: u64 = 30;
+const BOB: u64 = 31;
+
+/// run the bot against the stand-in, with its state kept in memory
+async fn start_bot(discord: &FakeDiscord) -> (AppState, JoinHandle<ShutdownReport>) {
+    spawn_bot(discord, Duration::from_secs(10), std::future::pending()).await
+}
+
+/// run the bot against the stand-in until it is told to stop, returning what it reported when it did
+async fn start_stoppable_bot(
+    discord: &FakeDiscord,
+    shutdown_timeout: Duration,
+) -> (oneshot::Sender<()>, JoinHandle<ShutdownReport>) {
+    let (stop, stopped) = oneshot::channel();
+    let (_, bot) = spawn_bot(discord, shutdown_timeout, async move {
+        let _ = stopped.await;
+    })
+    .await;
+    (stop, bot)
+}
+
+/// run the bot against the stand-in until `shutdown` completes
+async fn spawn_bot(
+    discord: &FakeDiscord,
+    shutdown_timeout: Duration,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> (AppState, JoinHandle<ShutdownReport>) {
+    let app_state = AppState::new(Arc::new(MemoryStore::default()))
+        .await
+        .unwrap();
+    let bot = DiscordBot::builder()
+        .discord_token(String::from("offline"))
+        .state(app_state.clone())
+        .api_url(discord.api_url())
+        .shutdown_timeout(shutdown_timeout)
+        .build()
+        .unwrap();
+    let handle = tokio::task::spawn(async move {
+        match bot.run(shutdown).await.map_err(|e| e.to_string()) {
+            Ok(report) => report,
+            Err(e) => panic!("the bot stopped: {}", e),
+        }
+    });
+    (app_state, handle)
+}
+
+/// add the bot to the test guild, and wait for it to register its commands there
+async fn join_guild(discord: &FakeDiscord) -> Value {
+    discord.guild_create(GUILD, "Test Guild", &[ALICE, BOB]);
+    let path = format!("/applications/{}/guilds/{}/commands", APPLICATION_ID, GUILD);
+    discord
+        .wait_for(|r| r.method == "PUT" && r.path == path)
+        .await
+        .body
+}
+
+/// wait for the bot to register its personal commands globally, once it has connected
+async fn wait_for_global_commands(discord: &FakeDiscord) -> Value {
+    let path = format!("/applications/{}/commands", APPLICATION_ID);
+    discord
+        .wait_for(|r| r.method == "PUT" && r.path == path)
+        .await
+        .body
+}
+
+/// wait for the bot to have recovered from a number of one kind of routing anomaly
+async fn wait_for_anomalies(app_state: &AppState, name: &str, count: u64) {
+    let wait = async {
+        while !app_state
+            .routing_anomalies
+            .counts()
+            .contains(&(name, count))
+        {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    };
+    if tokio::time::timeout(Duration::from_secs(5), wait)
+        .await
+        .is_err()
+    {
+        panic!(
+            "expected {} {}, the bot counted {:?}",
+            count,
+            name,
+            app_state.routing_anomalies.counts()
+        );
+    }
+}
+
+/// the text of a response to an interaction
+fn content(response: &Value) -> &str {
+    response["data"]["content"].as_str().unwrap_or_default()
+}
+
+#[tokio::test]
+async fn commands_are_registered_when_added_to_a_guild() {
+    let discord = FakeDiscord::start().await;
+    let (_, bot) = start_bot(&discord).await;
+
+    let names = |registered: Value| {
+        registered
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| c["name"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+    let global = wait_for_global_commands(&discord).await;
+    // personal commands can be installed to a user's account, and used wherever they are
+    for command in global.as_array().unwrap() {
+        assert_eq!(command["integration_types"], json!([0, 1]));
+        assert_eq!(command["contexts"], json!([0, 1, 2]));
+    }
+    let global = names(global);
+    assert!(global.contains(&String::from("timezone")));
+    assert!(global.contains(&String::from("Convert times in this message")));
+
+    // personal commands are available in every guild from their global registration
+    let guild = names(join_guild(&discord).await);
+    assert!(guild.contains(&String::from("config")));
+    assert!(!guild.contains(&String::from("timezone")));
+
+    bot.abort();
+}
+
+#[tokio::test]
+async fn registered_timezones_are_used_by_other_commands() {
+    let discord = FakeDiscord::start().await;
+    let (app_state, bot) = start_bot(&discord).await;
+    join_guild(&discord).await;
+
+    let set = discord.command(
+        GUILD,
+        CHANNEL,
+        ALICE,
+        "timezone",
+        json!([{
+            "name": "set",
+            "type": 1,
+            "options": [{ "name": "zone", "type": 3, "value": "Europe/London" }],
+        }]),
+    );
+    let response = discord.response_to(set).await;
+    assert!(content(&response).starts_with("Your timezone is now Europe/London"));
+    assert_eq!(
+        app_state.user_timezone(ALICE).await,
+        Some(chrono_tz::Europe::London)
+    );
+
+    let time = discord.command(
+        GUILD,
+        CHANNEL,
+        BOB,
+        "time",
+        json!([{ "name": "user", "type": 6, "value": ALICE.to_string() }]),
+    );
+    let response = discord.response_to(time).await;
+    assert!(content(&response).contains(&format!("for <@{}> (Europe/London", ALICE)));
+
+    bot.abort();
+}
+
+#[tokio::test]
+async fn invalid_options_are_explained() {
+    let discord = FakeDiscord::start().await;
+    let (_, bot) = start_bot(&discord).await;
+    join_guild(&discord).await;
+
+    let convert = discord.command(
+        GUILD,
+        CHANNEL,
+        ALICE,
+        "convert",
+        json!([{ "name": "time", "type": 3, "value": "3pm" }]),
+    );
+    let response = discord.response_to(convert).await;
+    assert_eq!(content(&response), "No to provided");
+    assert_eq!(response["data"]["flags"], 64, "failures are ephemeral");
+
+    bot.abort();
+}
+
+#[tokio::test]
+async fn times_are_converted_in_opted_in_channels() {
+    let discord = FakeDiscord::start().await;
+    let (app_state, bot) = start_bot(&discord).await;
+    app_state
+        .update_guild_config(GUILD, |c| {
+            c.auto_convert_channels.insert(CHANNEL);
+        })
+        .await
+        .unwrap();
+    join_guild(&discord).await;
+
+    let other_channel = CHANNEL + 1;
+    discord.message(GUILD, other_channel, ALICE, "lunch at 1pm UTC?");
+    discord.message(GUILD, CHANNEL, ALICE, "standup at 3pm UTC");
+
+    let path = format!("/channels/{}/messages", CHANNEL);
+    let reply = discord
+        .wait_for(|r| r.method == "POST" && r.path == path)
+        .await;
+    let reply = reply.body["content"].as_str().unwrap();
+    assert!(reply.contains("**3pm UTC**"), "{}", reply);
+    assert!(reply.contains("<t:"), "{}", reply);
+
+    // the other message was sent first and needs no requests to handle, so it has been by now
+    let other_path = format!("/channels/{}/messages", other_channel);
+    assert!(!discord.requests().iter().any(|r| r.path == other_path));
+
+    bot.abort();
+}
+
+#[tokio::test]
+async fn shutdown_waits_for_in_flight_interactions() {
+    let discord = FakeDiscord::start().await;
+    let (stop, bot) = start_stoppable_bot(&discord, Duration::from_secs(10)).await;
+    wait_for_global_commands(&discord).await;
+    join_guild(&discord).await;
+
+    // the response is sent, but the handler is still waiting for discord to accept it
+    discord.delay_responses(Duration::from_millis(300));
+    let ping = discord.command(GUILD, CHANNEL, ALICE, "ping", json!([]));
+    discord.response_to(ping).await;
+
+    stop.send(()).unwrap();
+    let report = bot.await.unwrap();
+    assert!(report.aborted.is_empty(), "{:?}", report.aborted);
+    discord.wait_for_disconnect().await;
+}
+
+#[tokio::test]
+async fn unfinished_tasks_are_aborted_at_the_deadline() {
+    let discord = FakeDiscord::start().await;
+    let (stop, bot) = start_stoppable_bot(&discord, Duration::from_millis(200)).await;
+    wait_for_global_commands(&discord).await;
+    join_guild(&discord).await;
+
+    discord.delay_responses(Duration::from_secs(60));
+    let ping = discord.command(GUILD, CHANNEL, ALICE, "ping", json!([]));
+    discord.response_to(ping).await;
+
+    stop.send(()).unwrap();
+    let report = tokio::time::timeout(Duration::from_secs(5), bot)
+        .await
+        .expect("the bot stops soon after the deadline")
+        .unwrap();
+    assert_eq!(
+        report.aborted,
+        [format!("/ping command in guild {}", GUILD)]
+    );
+    discord.wait_for_disconnect().await;
+}
+
+#[tokio::test]
+async fn guilds_added_twice_keep_their_running_handler() {
+    let discord = FakeDiscord::start().await;
+    let (app_state, bot) = start_bot(&discord).await;
+    join_guild(&discord).await;
+
+    discord.guild_create(GUILD, "Test Guild", &[ALICE, BOB]);
+    let ping = discord.command(GUILD, CHANNEL, ALICE, "ping", json!([]));
+    assert_eq!(content(&discord.response_to(ping).await), "Pong!");
+    assert_eq!(
+        app_state
+            .routing_anomalies
+            .replaced_handlers
+            .load(Ordering::Relaxed),
+        0
+    );
+
+    bot.abort();
+}
+
+#[tokio::test]
+async fn commands_in_unknown_guilds_create_a_handler() {
+    // the bot is in the guild, but discord hasn't sent it yet
+    let discord = FakeDiscord::start_in(&[GUILD]).await;
+    let (app_state, bot) = start_bot(&discord).await;
+
+    let ping = discord.command(GUILD, CHANNEL, ALICE, "ping", json!([]));
+    assert_eq!(content(&discord.response_to(ping).await), "Pong!");
+    wait_for_anomalies(&app_state, "created_handlers", 1).await;
+
+    // the guild arriving later keeps the handler created for it
+    join_guild(&discord).await;
+    let ping = discord.command(GUILD, CHANNEL, BOB, "ping", json!([]));
+    assert_eq!(content(&discord.response_to(ping).await), "Pong!");
+
+    bot.abort();
+}
+
+#[tokio::test]
+async fn commands_are_answered_in_guilds_refusing_their_registration() {
+    let discord = FakeDiscord::start().await;
+    discord.refuse_commands();
+    let (_, bot) = start_bot(&discord).await;
+
+    // a command installed to a user's account, used in a guild the bot isn't in
+    let ping = discord.command(GUILD, CHANNEL, ALICE, "ping", json!([]));
+    assert_eq!(content(&discord.response_to(ping).await), "Pong!");
+
+    bot.abort();
+}
+
+#[tokio::test]
+async fn deleting_unknown_guilds_does_not_stop_routing() {
+    let discord = FakeDiscord::start().await;
+    let (app_state, bot) = start_bot(&discord).await;
+    join_guild(&discord).await;
+
+    discord.guild_delete(GUILD + 1);
+    wait_for_anomalies(&app_state, "unknown_deletions", 1).await;
+
+    let ping = discord.command(GUILD, CHANNEL, ALICE, "ping", json!([]));
+    assert_eq!(content(&discord.response_to(ping).await), "Pong!");
+    assert_eq!(
+        app_state.routing_anomalies.counts().map(|(_, count)| count),
+        [0, 0, 0, 1, 0]
+    );
+
+    bot.abort();
+}
+
+#[tokio::test]
+async fn commands_in_guilds_the_bot_isnt_in_are_answered_as_direct_messages() {
+    let discord = FakeDiscord::start().await;
+    let (app_state, bot) = start_bot(&discord).await;
+
+    // a user's own install of the bot can be used in guilds the bot was never added to
+    let remind = discord.command(
+        GUILD,
+        CHANNEL,
+        ALICE,
+        "remind",
+        json!([{
+            "name": "in",
+            "type": 1,
+            "options": [
+                { "name": "duration", "type": 3, "value": "2h" },
+                { "name": "message", "type": 3, "value": "stretch" },
+            ],
+        }]),
+    );
+    assert!(content(&discord.response_to(remind).await).starts_with("I'll remind you"));
+
+    // the bot can't post in the guild, so the reminder is sent as a direct message instead
+    assert_eq!(
+        app_state.user_reminders(ALICE).await[0].target,
+        ReminderTarget::DirectMessage
+    );
+    assert_eq!(
+        app_state
+            .routing_anomalies
+            .created_handlers
+            .load(Ordering::Relaxed),
+        0
+    );
+    assert!(!discord
+        .requests()
+        .iter()
+        .any(|r| r.path.contains("/guilds/")));
+
+    bot.abort();
+}
+
+#[tokio::test]
+async fn personal_commands_can_be_used_in_direct_messages() {
+    let discord = FakeDiscord::start().await;
+    let (app_state, bot) = start_bot(&discord).await;
+
+    let set = discord.direct_command(
+        CHANNEL,
+        ALICE,
+        "timezone",
+        json!([{
+            "name": "set",
+            "type": 1,
+            "options": [{ "name": "zone", "type": 3, "value": "Europe/London" }],
+        }]),
+    );
+    let response = discord.response_to(set).await;
+    assert!(content(&response).starts_with("Your timezone is now Europe/London"));
+    assert_eq!(
+        app_state.user_timezone(ALICE).await,
+        Some(chrono_tz::Europe::London)
+    );
+
+    let config = discord.direct_command(CHANNEL, ALICE, "config", json!([]));
+    assert_eq!(
+        content(&discord.response_to(config).await),
+        "`/config` can only be used in a server"
+    );
+
+    // direct messages aren't routed through a guild, so no guild handler registered commands
+    assert!(!discord
+        .requests()
+        .iter()
+        .any(|r| r.path.contains("/guilds/")));
+
+    bot.abort();
+}
+
+#[tokio::test]
+async fn messages_by_authors_without_a_timezone_are_read_in_utc() {
+    let discord = FakeDiscord::start().await;
+    let (_, bot) = start_bot(&discord).await;
+    join_guild(&discord).await;
+
+    let convert = discord.message_command(
+        GUILD,
+        CHANNEL,
+        ALICE,
+        "Convert times in this message",
+        BOB,
+        "standup is at 2030-06-03 09:30",
+    );
+    let response = discord.response_to(convert).await;
+    let embed = &response["data"]["embeds"][0];
+    assert!(
+        embed["description"]
+            .as_str()
+            .unwrap_or_default()
+            .contains("<t:1906709400"),
+        "the time is read in UTC: {}",
+        embed
+    );
+    assert_eq!(
+        embed["footer"]["text"],
+        "The author hasn't registered a timezone, so times without one were read in UTC"
+    );
+
+    bot.abort();
+}
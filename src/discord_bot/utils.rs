@@ -1,4 +1,4 @@
-use serenity::prelude::TypeMapKey;
+use serenity::{http::StatusCode, prelude::TypeMapKey};
 
 /// represents the unique identifier that represents the user-id of this discord bot
 pub struct BotDiscordId(u64);
@@ -18,3 +18,14 @@ impl BotDiscordId {
 impl TypeMapKey for BotDiscordId {
     type Value = BotDiscordId;
 }
+
+/// whether discord refused a request because of the request itself, so sending it again won't help.
+/// rate limits aren't counted, as they pass with time
+pub fn is_refused(error: &serenity::Error) -> bool {
+    matches!(error, serenity::Error::Http(e) if e.status_code().is_some_and(|s| s.is_client_error() && s != StatusCode::TOO_MANY_REQUESTS))
+}
+
+/// whether an error from discord indicates that the message being acted on no longer exists
+pub fn is_missing_message(error: &serenity::Error) -> bool {
+    matches!(error, serenity::Error::Http(e) if e.status_code() == Some(StatusCode::NOT_FOUND))
+}
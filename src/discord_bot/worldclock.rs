@@ -0,0 +1,87 @@
+//! World clock boards, messages listing the current time in several timezones which are
+//! edited every minute by the guild handler to stay up to date.
+//! Boards are stored in the guild settings, so they are picked back up after a restart.
+
+use chrono::{DateTime, Utc};
+use log::{error, info, trace};
+use serenity::{
+    builder::{CreateEmbed, CreateEmbedFooter, EditMessage},
+    client::Context,
+    model::id::{ChannelId, GuildId, MessageId},
+};
+
+use super::utils::is_missing_message;
+use crate::{state::AppState, store::WorldClock, timezones};
+
+/// render the embed displaying a world clock board at the provided instant
+pub fn render_board(board: &WorldClock, now: DateTime<Utc>) -> CreateEmbed {
+    let format = if board.twenty_four_hour {
+        "%H:%M"
+    } else {
+        "%l:%M %P"
+    };
+    let lines = board
+        .zones
+        .iter()
+        .map(|zone| {
+            let local = now.with_timezone(&zone.tz);
+            format!(
+                "**{}** `{}` {} ({})",
+                zone.label(),
+                local.format(format),
+                local.format("%a"),
+                timezones::format_utc_offset(&local)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    CreateEmbed::new()
+        .title("🌍 World clock")
+        .description(lines)
+        .footer(CreateEmbedFooter::new("Updated every minute"))
+        .timestamp(now)
+}
+
+/// edit every world clock board in a guild to show the current time,
+/// forgetting any boards whose message has been deleted
+pub async fn update_world_clocks(guild_id: GuildId, context: Context, app_state: AppState) {
+    let guild: u64 = guild_id.into();
+    let boards = app_state.guild_settings(guild).await.world_clocks;
+    if boards.is_empty() {
+        return;
+    }
+
+    let now = Utc::now();
+    trace!("updating {} world clocks in guild {}", boards.len(), guild);
+
+    let mut missing = Vec::new();
+    for board in boards.iter() {
+        let edit = EditMessage::new().embed(render_board(board, now));
+        if let Err(e) = ChannelId::new(board.channel_id)
+            .edit_message(&context, MessageId::new(board.message_id), edit)
+            .await
+        {
+            if is_missing_message(&e) {
+                info!(
+                    "world clock {} in guild {} was deleted, no longer updating it",
+                    board.message_id, guild
+                );
+                missing.push(board.message_id);
+            } else {
+                error!("failed to update world clock {}: {}", board.message_id, e);
+            }
+        }
+    }
+
+    if !missing.is_empty() {
+        if let Err(e) = app_state
+            .update_guild_settings(guild, |s| {
+                s.world_clocks.retain(|b| !missing.contains(&b.message_id))
+            })
+            .await
+        {
+            error!("failed to remove deleted world clocks: {}", e);
+        }
+    }
+}
@@ -0,0 +1,149 @@
+//! Detection of daylight savings transitions, and of how they change the offsets between timezones.
+//! chrono_tz does not expose its transition table, so transitions are found by watching for changes
+//! in a timezone's utc offset.
+//!
+//! This module has no dependency on discord, so that it can be tested exhaustively.
+
+#[cfg(test)]
+mod tests;
+
+use chrono::{DateTime, Duration, DurationRound, NaiveDateTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// how far apart the offset of a timezone is sampled when searching for a transition,
+/// every timezone keeps each offset for far longer than this
+fn search_step() -> Duration {
+    Duration::hours(1)
+}
+
+/// A change in the utc offset of a timezone, e.g. the start or end of daylight savings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transition {
+    /// the timezone whose offset changes
+    pub tz: Tz,
+    /// the first instant the new offset applies
+    pub at: DateTime<Utc>,
+    /// the offset from utc before the transition, in seconds
+    pub before: i32,
+    /// the offset from utc after the transition, in seconds
+    pub after: i32,
+}
+
+/// the offset of a timezone from utc at an instant, in seconds
+fn offset_at(tz: Tz, at: DateTime<Utc>) -> i32 {
+    tz.offset_from_utc_datetime(&at.naive_utc())
+        .fix()
+        .local_minus_utc()
+}
+
+/// describe a length of time given in seconds, e.g. "one hour" or "30 minutes"
+fn describe_shift(seconds: i32) -> String {
+    match (seconds.abs() / 3600, seconds.abs() % 3600 / 60) {
+        (1, 0) => String::from("one hour"),
+        (h, 0) => format!("{} hours", h),
+        (0, m) => format!("{} minutes", m),
+        (h, m) => format!("{}h{:02}m", h, m),
+    }
+}
+
+/// describe the difference between two offsets, e.g. "13h ahead" or "5h30m behind"
+fn describe_difference(seconds: i32) -> String {
+    let (h, m) = (seconds.abs() / 3600, seconds.abs() % 3600 / 60);
+    let amount = match m {
+        0 => format!("{}h", h),
+        m => format!("{}h{:02}m", h, m),
+    };
+    match seconds {
+        0 => String::from("on the same time"),
+        s if s > 0 => format!("{} ahead", amount),
+        _ => format!("{} behind", amount),
+    }
+}
+
+impl Transition {
+    /// the local time on the clocks when they change, before they are moved
+    pub fn local_time(&self) -> NaiveDateTime {
+        self.at.naive_utc() + Duration::seconds(self.before.into())
+    }
+
+    /// describe the transition in plain english,
+    /// e.g. "Europe/London moves back one hour on Sunday 25 October at 02:00"
+    pub fn describe(&self) -> String {
+        let shift = self.after - self.before;
+        format!(
+            "{} moves {} {} on {} at {}",
+            self.tz.name(),
+            if shift < 0 { "back" } else { "forward" },
+            describe_shift(shift),
+            self.local_time().format("%A %-d %B"),
+            self.local_time().format("%H:%M"),
+        )
+    }
+
+    /// describe how the transition changes the offset between another timezone and this one,
+    /// e.g. "Pacific/Auckland is now 13h ahead instead of 12h ahead",
+    /// or nothing if the offset between them is unchanged
+    pub fn relative_to(&self, other: Tz) -> Option<String> {
+        let difference_before = offset_at(other, self.at - Duration::seconds(1)) - self.before;
+        let difference_after = offset_at(other, self.at) - self.after;
+        (difference_before != difference_after).then(|| {
+            format!(
+                "{} is now {} instead of {}",
+                other.name(),
+                describe_difference(difference_after),
+                describe_difference(difference_before)
+            )
+        })
+    }
+}
+
+/// find the first transition of a timezone after `from`, up to and including `until`
+pub fn next_transition(tz: Tz, from: DateTime<Utc>, until: DateTime<Utc>) -> Option<Transition> {
+    let before = offset_at(tz, from);
+    // offsets only change on whole seconds, so the search is kept to whole seconds too
+    let mut low = from.duration_trunc(Duration::seconds(1)).unwrap_or(from);
+    loop {
+        if low >= until {
+            return None;
+        }
+        let high = (low + search_step()).min(until);
+        if offset_at(tz, high) != before {
+            // the transition is somewhere in (low, high], narrow it down to the second
+            let mut high = high;
+            while high - low > Duration::seconds(1) {
+                let middle = low + Duration::seconds((high - low).num_seconds() / 2);
+                if offset_at(tz, middle) == before {
+                    low = middle;
+                } else {
+                    high = middle;
+                }
+            }
+            return Some(Transition {
+                tz,
+                at: high,
+                before,
+                after: offset_at(tz, high),
+            });
+        }
+        low = high;
+    }
+}
+
+/// find every transition of the provided timezones after `from`, up to and including `until`,
+/// ordered by when they happen
+pub fn transitions_between(
+    zones: &[Tz],
+    from: DateTime<Utc>,
+    until: DateTime<Utc>,
+) -> Vec<Transition> {
+    let mut transitions = Vec::new();
+    for tz in zones.iter() {
+        let mut after = from;
+        while let Some(transition) = next_transition(*tz, after, until) {
+            after = transition.at;
+            transitions.push(transition);
+        }
+    }
+    transitions.sort_by_key(|t| (t.at, t.tz.name()));
+    transitions
+}
@@ -0,0 +1,86 @@
+use chrono::{DateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use super::{next_transition, transitions_between};
+
+const LONDON: Tz = chrono_tz::Europe::London;
+const AUCKLAND: Tz = chrono_tz::Pacific::Auckland;
+
+fn utc(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(y, m, d, h, min, 0).unwrap()
+}
+
+#[test]
+fn finds_the_exact_instant() {
+    // london leaves bst at 01:00 utc on 2026-10-25
+    let transition = next_transition(LONDON, utc(2026, 10, 18, 0, 0), utc(2026, 11, 1, 0, 0))
+        .expect("london changes its clocks");
+    assert_eq!(transition.at, utc(2026, 10, 25, 1, 0));
+    assert_eq!(transition.before, 3600);
+    assert_eq!(transition.after, 0);
+    assert_eq!(
+        transition.describe(),
+        "Europe/London moves back one hour on Sunday 25 October at 02:00"
+    );
+}
+
+#[test]
+fn springing_forward() {
+    // auckland starts daylight savings at 02:00 local on 2026-09-27, which is 14:00 utc the day before
+    let transition = next_transition(AUCKLAND, utc(2026, 9, 20, 0, 0), utc(2026, 10, 1, 0, 0))
+        .expect("auckland changes its clocks");
+    assert_eq!(transition.at, utc(2026, 9, 26, 14, 0));
+    assert_eq!(
+        transition.describe(),
+        "Pacific/Auckland moves forward one hour on Sunday 27 September at 02:00"
+    );
+}
+
+#[test]
+fn nothing_outside_the_window() {
+    assert_eq!(
+        next_transition(LONDON, utc(2026, 10, 18, 0, 0), utc(2026, 10, 25, 0, 59)),
+        None
+    );
+    assert_eq!(
+        next_transition(
+            chrono_tz::Asia::Tokyo,
+            utc(2026, 1, 1, 0, 0),
+            utc(2027, 1, 1, 0, 0)
+        ),
+        None
+    );
+}
+
+#[test]
+fn offsets_between_timezones() {
+    let transition = next_transition(LONDON, utc(2026, 10, 18, 0, 0), utc(2026, 11, 1, 0, 0))
+        .expect("london changes its clocks");
+    assert_eq!(
+        transition.relative_to(AUCKLAND).as_deref(),
+        Some("Pacific/Auckland is now 13h ahead instead of 12h ahead")
+    );
+    assert_eq!(
+        transition.relative_to(chrono_tz::Asia::Kolkata).as_deref(),
+        Some("Asia/Kolkata is now 5h30m ahead instead of 4h30m ahead")
+    );
+    // paris changes at the same instant, so stays an hour ahead
+    assert_eq!(transition.relative_to(chrono_tz::Europe::Paris), None);
+}
+
+#[test]
+fn transitions_are_ordered() {
+    // new york leaves daylight savings a week after london
+    let found = transitions_between(
+        &[chrono_tz::America::New_York, LONDON, chrono_tz::Asia::Tokyo],
+        utc(2026, 10, 1, 0, 0),
+        utc(2026, 12, 1, 0, 0),
+    );
+    assert_eq!(
+        found.iter().map(|t| (t.tz, t.at)).collect::<Vec<_>>(),
+        vec![
+            (LONDON, utc(2026, 10, 25, 1, 0)),
+            (chrono_tz::America::New_York, utc(2026, 11, 1, 6, 0)),
+        ]
+    );
+}
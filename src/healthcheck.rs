@@ -38,21 +38,32 @@ impl Healthcheck {
 
         let start_time = self.state.start_time;
         let num_connected = self.state.num_connected.clone();
+        let routing_anomalies = self.state.routing_anomalies.clone();
 
         let healthcheck = warp::path!("healthcheck").and(warp::get()).map(move || {
+            // the anomalies recovered from are listed after the status, as "name count" lines
+            let reply = |status: &str, code| {
+                let anomalies = routing_anomalies
+                    .counts()
+                    .iter()
+                    .map(|(name, count)| format!("\n{} {}", name, count))
+                    .collect::<String>();
+                warp::reply::with_status(format!("{}{}", status, anomalies), code)
+            };
+
             // return if uptime less than 1 minute
             if start_time.elapsed() < Duration::from_secs(60) {
-                return warp::reply::with_status("OK", warp::http::StatusCode::OK);
+                return reply("OK", warp::http::StatusCode::OK);
             }
 
             if num_connected.load(Ordering::Relaxed) < 1 {
-                return warp::reply::with_status(
+                return reply(
                     "NOT OK - NOT ENOUGH SERVERS",
                     warp::http::StatusCode::INTERNAL_SERVER_ERROR,
                 );
             }
 
-            warp::reply::with_status("OK", warp::http::StatusCode::OK)
+            reply("OK", warp::http::StatusCode::OK)
         });
 
         let server = warp::serve(healthcheck);
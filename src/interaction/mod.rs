@@ -0,0 +1,105 @@
+//! The commands people use TimeBot through, described without reference to any chat platform.
+//!
+//! A [Transport] (e.g. discord) turns each use of a command into an [Invocation], saying who used it,
+//! where, and with which [Options]. Commands implementing [Respond] read their options and answer
+//! with a [Reply], which the transport then delivers in its own terms. This keeps the time logic of
+//! those commands free of any one library, so it can be tested directly.
+//!
+//! Some commands stay with the discord bot, since what they do has no meaning outside of it:
+//! - `/say`, `/countdown` and `/worldclock` post, pin and edit messages in a channel
+//! - `/dst upcoming` and `/meeting` look through the members of a server
+//! - `/hours set` asks for the hours in a form
+//! - `/timezone list`, `/remind list` and `/countdown list` are paged through with buttons
+//!
+//! Those still answer through the transport where they can, so e.g. `/meeting` shares its
+//! [Reply] with [Reply::public].
+
+mod options;
+mod reply;
+#[cfg(test)]
+mod tests;
+
+use async_trait::async_trait;
+
+use crate::state::AppState;
+
+pub use options::{FromValue, Options, Subcommand, UserRef, Value};
+pub use reply::{Embed, Failure, Reply};
+
+/// A single use of a command
+#[derive(Debug, Clone)]
+pub struct Invocation<'a> {
+    /// the user who used the command
+    pub user: u64,
+    /// the guild the command was used in, if it wasn't used in direct messages
+    pub guild: Option<u64>,
+    /// the channel the command was used in, if the transport has channels
+    pub channel: Option<u64>,
+    /// the options provided to the command
+    pub options: Options<'a>,
+}
+
+impl<'a> Invocation<'a> {
+    /// a command used by a user in direct messages, with no options
+    pub fn new(user: u64) -> Self {
+        Self {
+            user,
+            guild: None,
+            channel: None,
+            options: Options::default(),
+        }
+    }
+
+    /// set the guild the command was used in
+    pub fn guild(mut self, guild: u64) -> Self {
+        self.guild = Some(guild);
+        self
+    }
+
+    /// set the channel the command was used in
+    pub fn channel(mut self, channel: u64) -> Self {
+        self.channel = Some(channel);
+        self
+    }
+
+    /// set every option of the command at once, e.g. as read from the transport
+    pub fn options(mut self, options: Options<'a>) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// add an option to the command
+    pub fn option(mut self, name: &'a str, value: Value<'a>) -> Self {
+        self.options = self.options.with(name, value);
+        self
+    }
+}
+
+/// A command which can be used over any [Transport]
+#[async_trait]
+pub trait Respond<'a>: Sized {
+    /// read the command from the options it was used with
+    fn parse(options: Options<'a>) -> Result<Self, String>;
+
+    /// carry out the command, and build the reply to the user who used it
+    async fn respond(
+        self,
+        invocation: &Invocation<'_>,
+        app_state: &AppState,
+    ) -> Result<Reply, Failure>;
+}
+
+/// A way for people to reach the bot's commands, such as discord
+pub trait Transport {
+    /// what the transport receives when a command is used
+    type Incoming;
+
+    /// what the transport sends back to answer a command
+    type Outgoing;
+
+    /// read who used a command, where, and with which options
+    fn invocation(incoming: &Self::Incoming) -> Invocation<'_>;
+
+    /// turn the result of a command into the transport's answer
+    fn deliver(result: Result<Reply, Failure>) -> Self::Outgoing;
+}
@@ -0,0 +1,177 @@
+//! Typed access to the options a command was used with, and to the subcommand (and subcommand
+//! group) which was chosen for commands that are split into subcommands
+
+/// The value given for an option
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value<'a> {
+    /// some text
+    Text(&'a str),
+    /// a whole number
+    Integer(i64),
+    /// a number
+    Number(f64),
+    /// true or false
+    Boolean(bool),
+    /// a user, by id
+    User(u64),
+    /// a channel, by id
+    Channel(u64),
+    /// a role, by id
+    Role(u64),
+    /// a chosen subcommand, with its own options
+    Subcommand(Options<'a>),
+    /// a chosen subcommand group, holding the chosen subcommand
+    SubcommandGroup(Options<'a>),
+    /// a kind of value which no command reads
+    Other,
+}
+
+/// A type which can be read from the value of an option
+pub trait FromValue<'a>: Sized {
+    /// the kind of value, used when explaining that an option had the wrong type
+    const KIND: &'static str;
+
+    /// read the value, if it is of this type
+    fn from_value(value: &Value<'a>) -> Option<Self>;
+}
+
+impl<'a> FromValue<'a> for &'a str {
+    const KIND: &'static str = "text";
+    fn from_value(value: &Value<'a>) -> Option<Self> {
+        match value {
+            Value::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> FromValue<'a> for bool {
+    const KIND: &'static str = "true or false";
+    fn from_value(value: &Value<'a>) -> Option<Self> {
+        match value {
+            Value::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> FromValue<'a> for i64 {
+    const KIND: &'static str = "a whole number";
+    fn from_value(value: &Value<'a>) -> Option<Self> {
+        match value {
+            Value::Integer(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> FromValue<'a> for f64 {
+    const KIND: &'static str = "a number";
+    fn from_value(value: &Value<'a>) -> Option<Self> {
+        match value {
+            Value::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+/// A user given as an option, for commands which don't depend on a transport's own id types
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UserRef(pub u64);
+
+impl From<UserRef> for u64 {
+    fn from(user: UserRef) -> Self {
+        user.0
+    }
+}
+
+impl<'a> FromValue<'a> for UserRef {
+    const KIND: &'static str = "a user";
+    fn from_value(value: &Value<'a>) -> Option<Self> {
+        match value {
+            Value::User(id) => Some(Self(*id)),
+            _ => None,
+        }
+    }
+}
+
+/// The options provided to a command, or to one of its subcommands
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Options<'a>(Vec<(&'a str, Value<'a>)>);
+
+impl<'a> Options<'a> {
+    /// add an option
+    pub fn with(mut self, name: &'a str, value: Value<'a>) -> Self {
+        self.0.push((name, value));
+        self
+    }
+
+    /// read an option which may have been left out, failing if it was provided with the wrong type
+    pub fn optional<T: FromValue<'a>>(&self, name: &str) -> Result<Option<T>, String> {
+        match self.0.iter().find(|(n, _)| *n == name) {
+            Some((_, value)) => T::from_value(value)
+                .map(Some)
+                .ok_or_else(|| format!("The {} option should be {}", name, T::KIND)),
+            None => Ok(None),
+        }
+    }
+
+    /// read an option which must have been provided
+    pub fn required<T: FromValue<'a>>(&self, name: &str) -> Result<T, String> {
+        self.optional(name)?
+            .ok_or_else(|| format!("No {} provided", name.replace('_', " ")))
+    }
+}
+
+impl<'a> FromIterator<(&'a str, Value<'a>)> for Options<'a> {
+    fn from_iter<I: IntoIterator<Item = (&'a str, Value<'a>)>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+/// The subcommand chosen when using a command which is split into subcommands,
+/// e.g. `set` in `/timezone set`
+#[derive(Debug, Clone)]
+pub struct Subcommand<'a> {
+    /// the group the subcommand belongs to, if it is in one
+    pub group: Option<&'a str>,
+    /// the name of the subcommand
+    pub name: &'a str,
+    /// the options provided to the subcommand
+    pub options: Options<'a>,
+}
+
+impl<'a> Subcommand<'a> {
+    /// find the subcommand chosen in a command's options, looking inside a subcommand group if needed
+    pub fn find(options: Options<'a>) -> Result<Self, String> {
+        let missing = || String::from("No subcommand provided");
+        let (name, value) = options.0.into_iter().next().ok_or_else(missing)?;
+        match value {
+            Value::Subcommand(options) => Ok(Self {
+                group: None,
+                name,
+                options,
+            }),
+            Value::SubcommandGroup(subcommands) => {
+                let (subcommand, value) = subcommands.0.into_iter().next().ok_or_else(missing)?;
+                match value {
+                    Value::Subcommand(options) => Ok(Self {
+                        group: Some(name),
+                        name: subcommand,
+                        options,
+                    }),
+                    _ => Err(missing()),
+                }
+            }
+            _ => Err(missing()),
+        }
+    }
+
+    /// the error for a subcommand which the command doesn't know how to handle
+    pub fn unknown(&self) -> String {
+        match self.group {
+            Some(group) => format!("Unknown subcommand {} {}", group, self.name),
+            None => format!("Unknown subcommand {}", self.name),
+        }
+    }
+}
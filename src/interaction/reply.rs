@@ -0,0 +1,110 @@
+//! The answer to a command, built without reference to how it will be shown
+
+/// The reply to a command, which only the user who used it can see unless it is made public
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Reply {
+    /// the text of the reply
+    pub content: Option<String>,
+    /// the embeds shown below the text
+    pub embeds: Vec<Embed>,
+    /// whether everyone can see the reply, rather than just the user who used the command
+    pub public: bool,
+}
+
+impl Reply {
+    /// a reply with just some text
+    pub fn text(content: impl Into<String>) -> Self {
+        Self {
+            content: Some(content.into()),
+            ..Self::default()
+        }
+    }
+
+    /// a reply with just an embed
+    pub fn embed(embed: Embed) -> Self {
+        Self {
+            embeds: vec![embed],
+            ..Self::default()
+        }
+    }
+
+    /// show the reply to everyone
+    pub fn public(mut self) -> Self {
+        self.public = true;
+        self
+    }
+}
+
+/// A block of rich content in a [Reply], with a title, text and a set of fields
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Embed {
+    /// the title shown at the top
+    pub title: Option<String>,
+    /// the link the title leads to
+    pub url: Option<String>,
+    /// the main text
+    pub description: Option<String>,
+    /// the fields shown below the text, as (name, value, inline)
+    pub fields: Vec<(String, String, bool)>,
+    /// the small text shown at the bottom
+    pub footer: Option<String>,
+}
+
+impl Embed {
+    /// an embed with a title
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: Some(title.into()),
+            ..Self::default()
+        }
+    }
+
+    /// set the link the title leads to
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    /// set the main text
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// add a field, shown beside the others when inline
+    pub fn field(
+        mut self,
+        name: impl Into<String>,
+        value: impl Into<String>,
+        inline: bool,
+    ) -> Self {
+        self.fields.push((name.into(), value.into(), inline));
+        self
+    }
+
+    /// set the small text shown at the bottom
+    pub fn footer(mut self, footer: impl Into<String>) -> Self {
+        self.footer = Some(footer.into());
+        self
+    }
+}
+
+/// Why a command couldn't give the reply that was asked for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Failure {
+    /// the command couldn't be carried out as asked, the message explains why to the user
+    Refused(String),
+    /// something went wrong, the user is shown the response and the log message is recorded
+    Error {
+        /// the message shown to the user
+        response: String,
+        /// the message recorded in the log
+        log_message: String,
+    },
+}
+
+impl From<String> for Failure {
+    fn from(message: String) -> Self {
+        Self::Refused(message)
+    }
+}
@@ -0,0 +1,65 @@
+use super::{Embed, Failure, Invocation, Options, Reply, Subcommand, UserRef, Value};
+
+#[test]
+fn options_are_read_by_name_and_type() {
+    let options = Options::default()
+        .with("time", Value::Text("3pm"))
+        .with("days", Value::Integer(3))
+        .with("user", Value::User(30));
+
+    assert_eq!(options.required::<&str>("time"), Ok("3pm"));
+    assert_eq!(options.optional::<i64>("days"), Ok(Some(3)));
+    assert_eq!(options.optional::<UserRef>("user"), Ok(Some(UserRef(30))));
+    assert_eq!(options.optional::<&str>("timezone"), Ok(None));
+}
+
+#[test]
+fn missing_and_mistyped_options_are_explained() {
+    let options = Options::default().with("days", Value::Text("three"));
+
+    assert_eq!(
+        options.optional::<i64>("days"),
+        Err(String::from("The days option should be a whole number"))
+    );
+    assert_eq!(
+        options.required::<&str>("to_zone"),
+        Err(String::from("No to zone provided"))
+    );
+}
+
+#[test]
+fn subcommands_are_found_inside_groups() {
+    let options = Options::default().with(
+        "channel",
+        Value::SubcommandGroup(Options::default().with(
+            "add",
+            Value::Subcommand(Options::default().with("channel", Value::Channel(20))),
+        )),
+    );
+
+    let subcommand = Subcommand::find(options).unwrap();
+    assert_eq!(subcommand.group, Some("channel"));
+    assert_eq!(subcommand.name, "add");
+    assert!(subcommand.options.optional::<&str>("channel").is_err());
+    assert_eq!(subcommand.unknown(), "Unknown subcommand channel add");
+
+    let invocation = Invocation::new(30).option("time", Value::Text("3pm"));
+    assert_eq!(
+        Subcommand::find(invocation.options).unwrap_err(),
+        "No subcommand provided"
+    );
+}
+
+#[test]
+fn replies_are_private_unless_made_public() {
+    let reply = Reply::embed(Embed::new("Times").field("UTC", "3pm", true));
+    assert!(!reply.public);
+    assert_eq!(reply.content, None);
+    assert!(Reply::text("hello").public().public);
+
+    let embed = Embed::new("Times").url("https://discord.com");
+    assert_eq!(embed.url.as_deref(), Some("https://discord.com"));
+
+    let failure: Failure = String::from("No time provided").into();
+    assert_eq!(failure, Failure::Refused(String::from("No time provided")));
+}
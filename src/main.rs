@@ -1,14 +1,28 @@
 mod discord_bot;
+mod dst;
 
 mod healthcheck;
+mod interaction;
 
 mod logging;
+mod meeting;
+mod reminders;
 mod state;
+mod store;
+mod time_parse;
+mod timezones;
 
 use log::{error, info};
-use std::process::exit;
+use std::{process::exit, time::Duration};
+use tokio::sync::oneshot;
 
-use crate::{discord_bot::DiscordBot, logging::configure_logger, state::AppState};
+use crate::{
+    discord_bot::DiscordBot,
+    logging::configure_logger,
+    reminders::{Scheduler, SystemClock},
+    state::AppState,
+    store::JsonFileStore,
+};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -16,17 +30,31 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let discord_token = std::env::var("DISCORD_TOKEN").expect("DISCORD_TOKEN must be set");
 
-    let state = AppState::new().await?;
+    let database_path =
+        std::env::var("DATABASE_PATH").unwrap_or_else(|_| String::from("./data/timebot.json"));
+
+    info!("loading database from {}", database_path);
+    let state = AppState::new(JsonFileStore::new(database_path)).await?;
+
+    // the bot's own default is used unless a timeout is configured
+    let shutdown_timeout = std::env::var("SHUTDOWN_TIMEOUT_SECS")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .map(Duration::from_secs);
 
     info!("spawning discord handler");
     let discord_state = state.clone();
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
     let discord_handle = tokio::task::spawn(async move {
-        let builder = DiscordBot::builder()
+        let mut builder = DiscordBot::builder()
             .discord_token(discord_token)
-            .state(discord_state)
-            .build();
+            .state(discord_state.clone())
+            .scheduler(Scheduler::new(discord_state, SystemClock));
+        if let Some(timeout) = shutdown_timeout {
+            builder = builder.shutdown_timeout(timeout);
+        }
 
-        let bot = match builder {
+        let bot = match builder.build() {
             Ok(bot) => bot,
             Err(e) => {
                 error!("failed to build discord bot: {}", e);
@@ -34,7 +62,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         };
 
-        if let Err(e) = bot.run().await {
+        let shutdown = async move {
+            let _ = shutdown_rx.await;
+        };
+        if let Err(e) = bot.run(shutdown).await {
             error!("failed to run discord bot: {}", e);
             exit(1);
         }
@@ -65,23 +96,29 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tokio::pin!(discord_handle);
     tokio::pin!(healthcheck_handle);
 
-    loop {
-        tokio::select! {
-            biased;
-            _ = tokio::signal::ctrl_c() => {
-                info!("received ctrl-c, shutting down");
-                break;
-            }
+    let bot_running = tokio::select! {
+        biased;
+        _ = tokio::signal::ctrl_c() => {
+            info!("received ctrl-c, shutting down");
+            true
+        }
 
-            _ = &mut discord_handle => {
-                info!("discord handler shut down");
-                break;
-            }
+        _ = &mut discord_handle => {
+            info!("discord handler shut down");
+            false
+        }
 
-            _ = healthcheck_handle => {
-                info!("healthcheck server shut down");
-                break;
-            }
+        _ = healthcheck_handle => {
+            info!("healthcheck server shut down");
+            true
+        }
+    };
+
+    if bot_running {
+        // the bot finishes what it is doing, giving up on anything unfinished at its deadline
+        let _ = shutdown_tx.send(());
+        if let Err(e) = discord_handle.await {
+            error!("discord handler failed while shutting down: {}", e);
         }
     }
 
@@ -0,0 +1,124 @@
+//! Planning of meetings across timezones, finding the times when the most participants are
+//! within their working hours.
+//!
+//! This module has no dependency on discord, so that it can be tested exhaustively.
+
+#[cfg(test)]
+mod tests;
+
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use chrono_tz::Tz;
+
+use crate::store::WorkingHours;
+
+/// the granularity of the start times which are considered for a meeting
+const SLOT_STEP_MINUTES: i64 = 30;
+
+/// how often a participant's availability is checked within a meeting,
+/// so that a meeting running over a lunch break or the end of the day is not proposed
+const CHECK_STEP_MINUTES: i64 = 15;
+
+/// Someone who should attend a meeting
+#[derive(Debug, Clone)]
+pub struct Participant {
+    /// the discord id of the participant
+    pub user_id: u64,
+    /// the timezone the participant's working hours are in
+    pub tz: Tz,
+    /// the hours the participant is usually working
+    pub working_hours: WorkingHours,
+}
+
+impl Participant {
+    /// whether the participant is working at the provided instant
+    pub fn is_working(&self, at: DateTime<Utc>) -> bool {
+        self.working_hours.contains(&at.with_timezone(&self.tz))
+    }
+
+    /// whether the participant is working for the entirety of a meeting
+    pub fn is_available(&self, start: DateTime<Utc>, duration: Duration) -> bool {
+        let checks = (duration.num_minutes() / CHECK_STEP_MINUTES).max(1);
+        (0..checks).all(|i| self.is_working(start + Duration::minutes(i * CHECK_STEP_MINUTES)))
+            // the final moment of the meeting is checked too, the meeting may end exactly as work does
+            && self.is_working(start + duration - Duration::minutes(1))
+    }
+}
+
+/// A possible time for a meeting
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Slot {
+    /// when the meeting would start
+    pub start: DateTime<Utc>,
+    /// when the meeting would end
+    pub end: DateTime<Utc>,
+    /// the participants who are within their working hours for the whole meeting
+    pub available: Vec<u64>,
+}
+
+/// find the best times for a meeting of the provided duration, starting between `from` and `until`.
+/// slots are ordered by the number of participants available, then by how soon they are, and never
+/// overlap one another. Slots which nobody is available for are never returned.
+pub fn find_slots(
+    participants: &[Participant],
+    from: DateTime<Utc>,
+    until: DateTime<Utc>,
+    duration: Duration,
+    limit: usize,
+) -> Vec<Slot> {
+    let step = Duration::minutes(SLOT_STEP_MINUTES);
+    // start on the first whole step after `from`, meetings at 10:00 rather than 09:47
+    let mut start = from.duration_trunc(step).unwrap_or(from);
+    if start < from {
+        start += step;
+    }
+
+    let mut candidates = Vec::new();
+    while start < until {
+        let available = participants
+            .iter()
+            .filter(|p| p.is_available(start, duration))
+            .map(|p| p.user_id)
+            .collect::<Vec<_>>();
+        if !available.is_empty() {
+            candidates.push(Slot {
+                start,
+                end: start + duration,
+                available,
+            });
+        }
+        start += step;
+    }
+
+    // a stable sort keeps the earliest slots first within each level of availability
+    candidates.sort_by_key(|s| std::cmp::Reverse(s.available.len()));
+
+    let mut chosen: Vec<Slot> = Vec::new();
+    for candidate in candidates {
+        if chosen.len() >= limit {
+            break;
+        }
+        if chosen
+            .iter()
+            .all(|c| candidate.end <= c.start || candidate.start >= c.end)
+        {
+            chosen.push(candidate);
+        }
+    }
+    chosen
+}
+
+/// the share of participants working at the start of each hour from `from`,
+/// between 0 and 1, for `hours` hours
+pub fn hourly_availability(
+    participants: &[Participant],
+    from: DateTime<Utc>,
+    hours: i64,
+) -> Vec<f64> {
+    (0..hours)
+        .map(|h| {
+            let at = from + Duration::hours(h);
+            let working = participants.iter().filter(|p| p.is_working(at)).count();
+            working as f64 / participants.len().max(1) as f64
+        })
+        .collect()
+}
@@ -0,0 +1,179 @@
+use chrono::{DateTime, Duration, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+
+use super::{find_slots, hourly_availability, Participant};
+use crate::store::{DayHours, TimeRange, WorkStatus, WorkingHours};
+
+fn time(h: u32, m: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(h, m, 0).unwrap()
+}
+
+fn utc(d: u32, h: u32, m: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 10, d, h, m, 0).unwrap()
+}
+
+/// someone working 09:00-17:00 monday to friday in the provided timezone
+fn participant(user_id: u64, tz: Tz) -> Participant {
+    Participant {
+        user_id,
+        tz,
+        working_hours: WorkingHours::default(),
+    }
+}
+
+#[test]
+fn finds_the_overlap_between_timezones() {
+    // london is utc+1 and new york is utc-4, so both work 13:00-16:00 utc
+    let participants = [
+        participant(1, chrono_tz::Europe::London),
+        participant(2, chrono_tz::America::New_York),
+    ];
+    // wednesday 2026-10-14
+    let slots = find_slots(
+        &participants,
+        utc(14, 0, 0),
+        utc(15, 0, 0),
+        Duration::hours(1),
+        3,
+    );
+
+    assert_eq!(slots[0].start, utc(14, 13, 0));
+    assert_eq!(slots[0].end, utc(14, 14, 0));
+    assert_eq!(slots[0].available, vec![1, 2]);
+    // later slots do not overlap the earlier ones
+    assert_eq!(slots[1].start, utc(14, 14, 0));
+    assert_eq!(slots[2].start, utc(14, 15, 0));
+}
+
+#[test]
+fn meetings_must_fit_within_working_hours() {
+    let participants = [participant(1, chrono_tz::Europe::London)];
+    // london work ends at 16:00 utc, so a 2 hour meeting must start by 14:00
+    let slots = find_slots(
+        &participants,
+        utc(14, 14, 0),
+        utc(14, 20, 0),
+        Duration::hours(2),
+        10,
+    );
+    assert_eq!(
+        slots.iter().map(|s| s.start).collect::<Vec<_>>(),
+        vec![utc(14, 14, 0)]
+    );
+}
+
+#[test]
+fn prefers_more_participants_over_sooner_slots() {
+    let participants = [
+        participant(1, chrono_tz::Europe::London),
+        participant(2, chrono_tz::Europe::London),
+        participant(3, chrono_tz::Asia::Tokyo),
+    ];
+    // tokyo (utc+9) works 00:00-08:00 utc, london 08:00-16:00 utc, so no slot has everyone
+    let slots = find_slots(
+        &participants,
+        utc(14, 0, 0),
+        utc(15, 0, 0),
+        Duration::minutes(30),
+        1,
+    );
+    assert_eq!(slots[0].start, utc(14, 8, 0));
+    assert_eq!(slots[0].available, vec![1, 2]);
+}
+
+#[test]
+fn starts_on_a_whole_step() {
+    let participants = [participant(1, chrono_tz::Europe::London)];
+    let slots = find_slots(
+        &participants,
+        utc(14, 9, 47),
+        utc(14, 20, 0),
+        Duration::minutes(30),
+        1,
+    );
+    assert_eq!(slots[0].start, utc(14, 10, 0));
+}
+
+#[test]
+fn nobody_available_finds_nothing() {
+    let mut weekend_only = participant(1, chrono_tz::Europe::London);
+    weekend_only.working_hours.days = vec![Weekday::Sat];
+    weekend_only.working_hours.start = NaiveTime::from_hms_opt(10, 0, 0).unwrap();
+    // wednesday to friday
+    let slots = find_slots(
+        &[weekend_only],
+        utc(14, 0, 0),
+        utc(17, 0, 0),
+        Duration::hours(1),
+        5,
+    );
+    assert!(slots.is_empty());
+}
+
+#[test]
+fn hourly_availability_is_a_share_of_participants() {
+    let participants = [
+        participant(1, chrono_tz::Europe::London),
+        participant(2, chrono_tz::America::New_York),
+    ];
+    let hours = hourly_availability(&participants, utc(14, 0, 0), 24);
+    assert_eq!(hours[7], 0.0);
+    assert_eq!(hours[8], 0.5);
+    assert_eq!(hours[13], 1.0);
+    assert_eq!(hours[16], 0.5);
+    assert_eq!(hours[20], 0.5);
+    assert_eq!(hours[21], 0.0);
+}
+
+#[test]
+fn meetings_avoid_lunch_breaks_and_days_off() {
+    let mut person = participant(1, chrono_tz::UTC);
+    person.working_hours.lunch = Some(TimeRange {
+        start: time(12, 0),
+        end: time(13, 0),
+    });
+    // thursday 2026-10-15 is taken off
+    person.working_hours.days_off = vec![utc(15, 0, 0).date_naive()];
+
+    let hours = &person.working_hours;
+    assert_eq!(hours.status(&utc(14, 11, 59)), WorkStatus::Working);
+    assert_eq!(hours.status(&utc(14, 12, 30)), WorkStatus::OnBreak);
+    assert_eq!(hours.status(&utc(15, 10, 0)), WorkStatus::DayOff);
+    assert_eq!(hours.status(&utc(14, 18, 0)), WorkStatus::OutsideHours);
+
+    // an hour long meeting must end before lunch, or start after it
+    let slots = find_slots(
+        &[person],
+        utc(14, 11, 0),
+        utc(16, 0, 0),
+        Duration::hours(1),
+        10,
+    );
+    let starts = slots.iter().map(|s| s.start).collect::<Vec<_>>();
+    assert!(starts.contains(&utc(14, 11, 0)));
+    assert!(starts.contains(&utc(14, 13, 0)));
+    assert!(!starts.contains(&utc(14, 11, 30)));
+    assert!(starts.iter().all(|s| *s < utc(15, 0, 0)));
+}
+
+#[test]
+fn overnight_shifts_belong_to_the_day_they_start() {
+    let mut night = participant(1, chrono_tz::UTC);
+    night.working_hours.days = vec![Weekday::Fri];
+    night.working_hours.day_hours = vec![DayHours {
+        day: Weekday::Fri,
+        hours: TimeRange {
+            start: time(22, 0),
+            end: time(6, 0),
+        },
+    }];
+
+    let hours = &night.working_hours;
+    // friday 2026-10-16 night into saturday morning
+    assert_eq!(hours.status(&utc(16, 21, 0)), WorkStatus::OutsideHours);
+    assert_eq!(hours.status(&utc(16, 23, 0)), WorkStatus::Working);
+    assert_eq!(hours.status(&utc(17, 5, 0)), WorkStatus::Working);
+    assert_eq!(hours.status(&utc(17, 23, 0)), WorkStatus::OutsideHours);
+    // and thursday night is not worked
+    assert_eq!(hours.status(&utc(16, 3, 0)), WorkStatus::OutsideHours);
+}
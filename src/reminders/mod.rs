@@ -0,0 +1,170 @@
+//! The reminder scheduler, a long-lived task which delivers each stored [Reminder] once it is due.
+//!
+//! Reminders are removed from the store before they are delivered, so a restart can never cause
+//! one to be delivered twice. Recurring reminders are rescheduled for their next occurrence in the
+//! same change. Reminders which came due while the bot was offline are delivered as soon as the
+//! scheduler starts. A reminder which fails to be delivered is put back in the store to be tried
+//! again a minute later, up to [MAX_DELIVERY_ATTEMPTS] times.
+//!
+//! Time is measured through a [Clock], so that tests can fast-forward it. Delivery is through a
+//! [ReminderDelivery], so that this module has no dependency on discord.
+
+mod recurrence;
+#[cfg(test)]
+mod tests;
+
+use std::{future::Future, sync::Arc};
+
+use chrono::{DateTime, Duration, Utc};
+use log::{error, info, trace, warn};
+use serenity::async_trait;
+use tokio::select;
+
+use crate::{
+    state::AppState,
+    store::{Reminder, StoreError},
+};
+
+pub use recurrence::Recurrence;
+
+/// the longest the system clock will sleep before checking the time again,
+/// so that changes to the wall clock (e.g. after a suspend) are noticed
+const MAX_SLEEP: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// the most times delivering a reminder is attempted before it is given up on
+const MAX_DELIVERY_ATTEMPTS: u32 = 5;
+
+/// how long to wait before trying again to deliver a reminder, or to take due reminders from the
+/// store, after a failure
+fn retry_delay() -> Duration {
+    Duration::minutes(1)
+}
+
+/// A source of the current time, which can be waited on
+#[async_trait]
+pub trait Clock: Send + Sync {
+    /// the current instant
+    fn now(&self) -> DateTime<Utc>;
+
+    /// wait until the provided instant has been reached
+    async fn sleep_until(&self, instant: DateTime<Utc>);
+}
+
+/// The real wall clock
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+#[async_trait]
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    async fn sleep_until(&self, instant: DateTime<Utc>) {
+        while let Ok(remaining) = (instant - self.now()).to_std() {
+            if remaining.is_zero() {
+                break;
+            }
+            tokio::time::sleep(remaining.min(MAX_SLEEP)).await;
+        }
+    }
+}
+
+/// A way of delivering a reminder to its user
+#[async_trait]
+pub trait ReminderDelivery: Send + Sync {
+    /// deliver a reminder, which is already due
+    async fn deliver(&self, reminder: &Reminder) -> Result<(), String>;
+}
+
+/// Delivers reminders from the [AppState] as they become due
+pub struct Scheduler {
+    /// the state the reminders are stored in
+    app_state: AppState,
+    /// the clock used to decide when reminders are due
+    clock: Arc<dyn Clock>,
+}
+
+impl Scheduler {
+    /// create a scheduler for the reminders stored in the provided state
+    pub fn new(app_state: AppState, clock: impl Clock + 'static) -> Self {
+        Self {
+            app_state,
+            clock: Arc::new(clock),
+        }
+    }
+
+    /// deliver every reminder which is currently due, returning how many were delivered.
+    /// reminders which fail to be delivered are put back to be tried again later
+    pub async fn deliver_due(&self, delivery: &impl ReminderDelivery) -> Result<usize, StoreError> {
+        let now = self.clock.now();
+        let due = self.app_state.take_due_reminders(now).await?;
+
+        let mut delivered = 0;
+        for reminder in due {
+            trace!("delivering reminder {}", reminder.id);
+            let Err(e) = delivery.deliver(&reminder).await else {
+                delivered += 1;
+                continue;
+            };
+            if reminder.failed_attempts + 1 >= MAX_DELIVERY_ATTEMPTS {
+                error!(
+                    "failed to deliver reminder {}, giving up after {} attempts: {}",
+                    reminder.id, MAX_DELIVERY_ATTEMPTS, e
+                );
+                continue;
+            }
+
+            warn!(
+                "failed to deliver reminder {}, will try again: {}",
+                reminder.id, e
+            );
+            let id = reminder.id;
+            if let Err(e) = self
+                .app_state
+                .retry_reminder(reminder, now + retry_delay())
+                .await
+            {
+                error!("failed to put back reminder {} to try again: {}", id, e);
+            }
+        }
+        Ok(delivered)
+    }
+
+    /// run the scheduler forever, delivering reminders as they become due
+    #[cfg(test)]
+    pub async fn run(self, delivery: impl ReminderDelivery) {
+        self.run_until(delivery, std::future::pending()).await
+    }
+
+    /// run the scheduler until `stop` completes. reminders being delivered at the time are
+    /// finished first, so that none are taken from the store without being delivered
+    pub async fn run_until(self, delivery: impl ReminderDelivery, stop: impl Future<Output = ()>) {
+        info!("starting reminder scheduler");
+        tokio::pin!(stop);
+        loop {
+            let next = match self.deliver_due(&delivery).await {
+                Ok(_) => self.app_state.next_reminder_due().await,
+                Err(e) => {
+                    // nothing was taken from the store, so the due reminders are taken again later
+                    error!("failed to take due reminders from the store: {}", e);
+                    Some(self.clock.now() + retry_delay())
+                }
+            };
+            select! {
+                _ = &mut stop => {
+                    info!("stopping reminder scheduler");
+                    return;
+                },
+                // a reminder was added or removed, so the next due reminder may have changed
+                _ = self.app_state.reminders_changed() => {},
+                _ = async {
+                    match next {
+                        Some(next) => self.clock.sleep_until(next).await,
+                        None => std::future::pending().await,
+                    }
+                } => {},
+            }
+        }
+    }
+}
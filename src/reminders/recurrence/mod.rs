@@ -0,0 +1,237 @@
+//! Recurrence rules for repeating reminders, such as "every weekday at 09:30 Europe/London" or
+//! "first monday of each month at 10am".
+//!
+//! Rules are pinned to the local wall-clock time of their timezone, so a rule firing at 09:30
+//! keeps firing at 09:30 local time as the clocks change for daylight savings. A time which
+//! happens twice as the clocks go back fires once, at the first occurrence, and a time which
+//! is skipped as the clocks go forward fires at the equivalent time after the gap.
+
+#[cfg(test)]
+mod tests;
+
+use std::fmt::Display;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    time_parse::{parse_day_of_month, parse_time_of_day, parse_weekday, resolve_local},
+    timezones,
+};
+
+/// the furthest ahead to search for the next occurrence of a rule, every valid rule
+/// occurs at least once in this many days
+const MAX_SEARCH_DAYS: usize = 400;
+
+/// words which carry no meaning in a rule
+const FILLER_WORDS: &[&str] = &[
+    "every", "each", "of", "the", "on", "and", "at", "week", "month",
+];
+
+/// Which days a [Recurrence] occurs on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Frequency {
+    /// every day
+    Daily,
+    /// on the provided days of each week
+    Weekly(Vec<Weekday>),
+    /// on the provided day of each month, skipping months which are too short
+    MonthlyDay(u32),
+    /// on the nth weekday of each month, e.g. the first monday
+    MonthlyWeekday {
+        /// which occurrence of the weekday, from 1 to 4, or -1 for the last
+        nth: i8,
+        /// the day of the week
+        weekday: Weekday,
+    },
+}
+
+impl Frequency {
+    /// whether the rule occurs on the provided local date
+    fn matches(&self, date: NaiveDate) -> bool {
+        match self {
+            Self::Daily => true,
+            Self::Weekly(days) => days.contains(&date.weekday()),
+            Self::MonthlyDay(day) => date.day() == *day,
+            Self::MonthlyWeekday { nth, weekday } => {
+                date.weekday() == *weekday
+                    && match nth {
+                        -1 => (date + Duration::days(7)).month() != date.month(),
+                        nth => (date.day() - 1) / 7 + 1 == *nth as u32,
+                    }
+            }
+        }
+    }
+}
+
+/// A rule describing when a reminder repeats
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Recurrence {
+    /// the days the rule occurs on
+    pub frequency: Frequency,
+    /// the local time of day the rule occurs at
+    pub time: NaiveTime,
+    /// the timezone the time of day is in
+    pub tz: Tz,
+}
+
+/// parse an ordinal such as "first", "2nd" or "last", as used in "first monday of each month"
+fn parse_ordinal(word: &str) -> Option<i8> {
+    match word {
+        "first" | "1st" => Some(1),
+        "second" | "2nd" => Some(2),
+        "third" | "3rd" => Some(3),
+        "fourth" | "4th" => Some(4),
+        "last" => Some(-1),
+        _ => None,
+    }
+}
+
+impl Recurrence {
+    /// parse a rule such as "every weekday at 09:30 Europe/London", "mon and thu at 5pm",
+    /// "first monday of each month" or "15th of each month at noon".
+    /// if the rule does not name a timezone, `default_tz` is used, and if it does not include a
+    /// time of day, 09:00 is used.
+    pub fn parse(input: &str, default_tz: Option<Tz>) -> Result<Self, String> {
+        let input = input.trim().to_ascii_lowercase();
+        let words = input
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|w| !w.is_empty())
+            .collect::<Vec<_>>();
+
+        // the rule is "<days> [at <time>] [<timezone>]", so find where the time starts
+        let mut time = None;
+        let mut pattern_end = words.len();
+        let mut tz_start = words.len();
+        for (i, word) in words.iter().enumerate() {
+            // times may be written as two words, e.g. "9 am"
+            if let Some(t) = words
+                .get(i + 1)
+                .and_then(|m| parse_time_of_day(&format!("{} {}", word, m)))
+            {
+                (time, pattern_end, tz_start) = (Some(t), i, i + 2);
+                break;
+            }
+            if let Some(t) = parse_time_of_day(word) {
+                (time, pattern_end, tz_start) = (Some(t), i, i + 1);
+                break;
+            }
+        }
+
+        let tz = match words[tz_start..].join(" ") {
+            name if name.is_empty() => default_tz.ok_or_else(|| {
+                String::from("I don't know which timezone that rule is in, include one such as \"Europe/London\"")
+            })?,
+            name => timezones::resolve(&name)
+                .ok_or_else(|| format!("I don't know the timezone \"{}\"", name))?,
+        };
+
+        let pattern = words[..pattern_end]
+            .iter()
+            .copied()
+            .filter(|w| !FILLER_WORDS.contains(w))
+            .collect::<Vec<_>>();
+        let frequency = Self::parse_frequency(&pattern)?;
+
+        Ok(Self {
+            frequency,
+            time: time.unwrap_or_else(|| NaiveTime::from_hms_opt(9, 0, 0).expect("valid time")),
+            tz,
+        })
+    }
+
+    /// parse the days a rule occurs on, with filler words already removed
+    fn parse_frequency(words: &[&str]) -> Result<Frequency, String> {
+        let unknown = || {
+            format!(
+                "I don't understand \"{}\", try something like \"weekday\", \"monday and thursday\" or \"first friday of the month\"",
+                words.join(" ")
+            )
+        };
+
+        match words {
+            ["day"] | ["daily"] | ["days"] | [] => return Ok(Frequency::Daily),
+            ["weekday"] | ["weekdays"] => {
+                return Ok(Frequency::Weekly(vec![
+                    Weekday::Mon,
+                    Weekday::Tue,
+                    Weekday::Wed,
+                    Weekday::Thu,
+                    Weekday::Fri,
+                ]))
+            }
+            ["weekend"] | ["weekends"] => {
+                return Ok(Frequency::Weekly(vec![Weekday::Sat, Weekday::Sun]))
+            }
+            [ordinal, weekday] if parse_ordinal(ordinal).is_some() => {
+                let weekday = parse_weekday(weekday.trim_end_matches('s')).ok_or_else(unknown)?;
+                return Ok(Frequency::MonthlyWeekday {
+                    nth: parse_ordinal(ordinal).ok_or_else(unknown)?,
+                    weekday,
+                });
+            }
+            [day] if parse_day_of_month(day).is_some() => {
+                return Ok(Frequency::MonthlyDay(
+                    parse_day_of_month(day).ok_or_else(unknown)?,
+                ))
+            }
+            _ => {}
+        }
+
+        // otherwise, a list of weekdays
+        let mut days = words
+            .iter()
+            .map(|w| parse_weekday(w.trim_end_matches('s')).or_else(|| parse_weekday(w)))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(unknown)?;
+        days.sort_by_key(|d| d.num_days_from_monday());
+        days.dedup();
+        Ok(Frequency::Weekly(days))
+    }
+
+    /// find the first instant the rule occurs strictly after the provided instant
+    pub fn next_occurrence(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let start = after.with_timezone(&self.tz).date_naive();
+        start
+            .iter_days()
+            .take(MAX_SEARCH_DAYS)
+            .filter(|date| self.frequency.matches(*date))
+            .filter_map(|date| {
+                resolve_local(self.tz, date.and_time(self.time), &mut Vec::new()).ok()
+            })
+            .map(|time| time.with_timezone(&Utc))
+            .find(|time| *time > after)
+    }
+}
+
+impl Display for Recurrence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.frequency {
+            Frequency::Daily => write!(f, "every day")?,
+            Frequency::Weekly(days)
+                if days.len() == 5
+                    && !days.contains(&Weekday::Sat)
+                    && !days.contains(&Weekday::Sun) =>
+            {
+                write!(f, "every weekday")?
+            }
+            Frequency::Weekly(days) => {
+                let days = days.iter().map(|d| d.to_string()).collect::<Vec<_>>();
+                write!(f, "every {}", days.join(", "))?
+            }
+            Frequency::MonthlyDay(day) => write!(f, "on day {} of every month", day)?,
+            Frequency::MonthlyWeekday { nth, weekday } => {
+                let nth = match nth {
+                    1 => "first",
+                    2 => "second",
+                    3 => "third",
+                    4 => "fourth",
+                    _ => "last",
+                };
+                write!(f, "on the {} {} of every month", nth, weekday)?
+            }
+        }
+        write!(f, " at {} {}", self.time.format("%H:%M"), self.tz.name())
+    }
+}
@@ -0,0 +1,197 @@
+use chrono::{DateTime, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+
+use super::{Frequency, Recurrence};
+
+const LONDON: Tz = chrono_tz::Europe::London;
+
+fn utc(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(y, m, d, h, min, 0).unwrap()
+}
+
+fn rule(input: &str) -> Recurrence {
+    Recurrence::parse(input, Some(LONDON)).unwrap_or_else(|e| panic!("{}: {}", input, e))
+}
+
+/// the first `n` occurrences of a rule after an instant
+fn occurrences(rule: &Recurrence, after: DateTime<Utc>, n: usize) -> Vec<DateTime<Utc>> {
+    let mut found = Vec::new();
+    let mut after = after;
+    for _ in 0..n {
+        after = rule.next_occurrence(after).expect("rule occurs again");
+        found.push(after);
+    }
+    found
+}
+
+#[test]
+fn parsing() {
+    let weekdays = Frequency::Weekly(vec![
+        Weekday::Mon,
+        Weekday::Tue,
+        Weekday::Wed,
+        Weekday::Thu,
+        Weekday::Fri,
+    ]);
+    let cases = [
+        (
+            "every weekday at 09:30 Europe/London",
+            weekdays.clone(),
+            (9, 30),
+            LONDON,
+        ),
+        ("weekdays 9:30am", weekdays, (9, 30), LONDON),
+        ("every day at noon", Frequency::Daily, (12, 0), LONDON),
+        (
+            "mondays and thursdays at 5 pm Tokyo",
+            Frequency::Weekly(vec![Weekday::Mon, Weekday::Thu]),
+            (17, 0),
+            chrono_tz::Asia::Tokyo,
+        ),
+        (
+            "first Monday of each month",
+            Frequency::MonthlyWeekday {
+                nth: 1,
+                weekday: Weekday::Mon,
+            },
+            (9, 0),
+            LONDON,
+        ),
+        (
+            "last friday of the month at 16:00 EST",
+            Frequency::MonthlyWeekday {
+                nth: -1,
+                weekday: Weekday::Fri,
+            },
+            (16, 0),
+            chrono_tz::America::New_York,
+        ),
+        (
+            "15th of every month at 8am",
+            Frequency::MonthlyDay(15),
+            (8, 0),
+            LONDON,
+        ),
+    ];
+
+    for (input, frequency, (hour, minute), tz) in cases {
+        assert_eq!(
+            rule(input),
+            Recurrence {
+                frequency,
+                time: NaiveTime::from_hms_opt(hour, minute, 0).unwrap(),
+                tz,
+            },
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn parsing_errors() {
+    assert!(Recurrence::parse("every weekday at 9", None).is_err());
+    assert!(Recurrence::parse("every blue moon at 9", Some(LONDON)).is_err());
+    assert!(Recurrence::parse("every day at 9 Atlantis", Some(LONDON)).is_err());
+}
+
+#[test]
+fn weekdays_skip_the_weekend() {
+    // friday 2026-10-16 at 10:00 utc, which is 11:00 in london
+    let found = occurrences(&rule("every weekday at 09:30"), utc(2026, 10, 16, 10, 0), 2);
+    assert_eq!(
+        found,
+        vec![utc(2026, 10, 19, 8, 30), utc(2026, 10, 20, 8, 30)]
+    );
+}
+
+#[test]
+fn next_occurrence_is_strictly_after() {
+    let rule = rule("every day at 09:30");
+    let at = utc(2026, 10, 14, 8, 30);
+    assert_eq!(rule.next_occurrence(at), Some(utc(2026, 10, 15, 8, 30)));
+    assert_eq!(
+        rule.next_occurrence(at - chrono::Duration::seconds(1)),
+        Some(at)
+    );
+}
+
+#[test]
+fn stays_on_local_time_across_daylight_savings() {
+    // london moves from bst (utc+1) to gmt on 2026-10-25
+    let found = occurrences(&rule("every day at 09:30"), utc(2026, 10, 23, 12, 0), 3);
+    assert_eq!(
+        found,
+        vec![
+            utc(2026, 10, 24, 8, 30),
+            utc(2026, 10, 25, 9, 30),
+            utc(2026, 10, 26, 9, 30),
+        ]
+    );
+}
+
+#[test]
+fn repeated_local_time_fires_once() {
+    // 01:30 happens twice in london on 2026-10-25, only the first should fire
+    let found = occurrences(&rule("every day at 01:30"), utc(2026, 10, 24, 12, 0), 2);
+    assert_eq!(
+        found,
+        vec![utc(2026, 10, 25, 0, 30), utc(2026, 10, 26, 1, 30)]
+    );
+}
+
+#[test]
+fn skipped_local_time_fires_after_the_gap() {
+    // 01:30 does not exist in london on 2026-03-29, as the clocks go from 01:00 to 02:00
+    let found = occurrences(&rule("every day at 01:30"), utc(2026, 3, 28, 12, 0), 2);
+    assert_eq!(
+        found,
+        vec![utc(2026, 3, 29, 1, 30), utc(2026, 3, 30, 0, 30)]
+    );
+}
+
+#[test]
+fn monthly_rules() {
+    let first_monday = occurrences(
+        &rule("first monday of each month"),
+        utc(2026, 10, 14, 0, 0),
+        3,
+    );
+    assert_eq!(
+        first_monday,
+        vec![
+            utc(2026, 11, 2, 9, 0),
+            utc(2026, 12, 7, 9, 0),
+            utc(2027, 1, 4, 9, 0),
+        ]
+    );
+
+    let last_friday = occurrences(
+        &rule("last friday of the month"),
+        utc(2026, 10, 14, 0, 0),
+        2,
+    );
+    assert_eq!(
+        last_friday,
+        vec![utc(2026, 10, 30, 9, 0), utc(2026, 11, 27, 9, 0)]
+    );
+
+    // months without a 31st are skipped
+    let thirty_first = occurrences(&rule("31st of each month"), utc(2026, 10, 14, 0, 0), 2);
+    assert_eq!(
+        thirty_first,
+        vec![utc(2026, 10, 31, 9, 0), utc(2026, 12, 31, 9, 0)]
+    );
+}
+
+#[test]
+fn description() {
+    assert_eq!(
+        rule("every weekday at 09:30").to_string(),
+        "every weekday at 09:30 Europe/London"
+    );
+    assert_eq!(
+        rule("first monday of each month at 10am").to_string(),
+        "on the first Mon of every month at 10:00 Europe/London"
+    );
+}
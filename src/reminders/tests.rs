@@ -0,0 +1,348 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::{DateTime, TimeZone, Utc};
+use serenity::async_trait;
+use tokio::sync::{mpsc, watch};
+
+use super::{Clock, Recurrence, ReminderDelivery, Scheduler};
+use crate::{
+    state::AppState,
+    store::{Database, MemoryStore, Reminder, ReminderTarget},
+};
+
+/// a clock which only moves when told to
+struct ManualClock(watch::Sender<DateTime<Utc>>);
+
+impl ManualClock {
+    fn new(now: DateTime<Utc>) -> Arc<Self> {
+        Arc::new(Self(watch::channel(now).0))
+    }
+
+    fn advance(&self, by: chrono::Duration) {
+        self.0.send_modify(|now| *now += by);
+    }
+}
+
+#[async_trait]
+impl Clock for Arc<ManualClock> {
+    fn now(&self) -> DateTime<Utc> {
+        *self.0.borrow()
+    }
+
+    async fn sleep_until(&self, instant: DateTime<Utc>) {
+        let mut rx = self.0.subscribe();
+        let _ = rx.wait_for(|now| *now >= instant).await;
+    }
+}
+
+/// records every delivered reminder
+struct Recorder(mpsc::UnboundedSender<Reminder>);
+
+#[async_trait]
+impl ReminderDelivery for Recorder {
+    async fn deliver(&self, reminder: &Reminder) -> Result<(), String> {
+        self.0.send(reminder.clone()).map_err(|e| e.to_string())
+    }
+}
+
+/// fails to deliver the first `failures` reminders, then records the rest
+struct Flaky {
+    failures: std::sync::Mutex<u32>,
+    recorder: Recorder,
+}
+
+#[async_trait]
+impl ReminderDelivery for Flaky {
+    async fn deliver(&self, reminder: &Reminder) -> Result<(), String> {
+        {
+            let mut failures = self.failures.lock().unwrap();
+            if *failures > 0 {
+                *failures -= 1;
+                return Err(String::from("discord is down"));
+            }
+        }
+        self.recorder.deliver(reminder).await
+    }
+}
+
+fn start() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 10, 14, 12, 0, 0).unwrap()
+}
+
+async fn state(store: &Arc<MemoryStore>) -> AppState {
+    AppState::new(store.clone()).await.unwrap()
+}
+
+/// wait briefly for a delivery, returning none if nothing is delivered
+async fn delivered(rx: &mut mpsc::UnboundedReceiver<Reminder>) -> Option<Reminder> {
+    tokio::time::timeout(Duration::from_millis(200), rx.recv())
+        .await
+        .ok()
+        .flatten()
+}
+
+#[tokio::test]
+async fn delivers_when_due_and_not_before() {
+    let store = Arc::new(MemoryStore::default());
+    let app_state = state(&store).await;
+    let clock = ManualClock::new(start());
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    tokio::spawn(Scheduler::new(app_state.clone(), clock.clone()).run(Recorder(tx)));
+    app_state
+        .add_reminder(
+            1,
+            ReminderTarget::DirectMessage,
+            start() + chrono::Duration::hours(2),
+            start(),
+            String::from("deploy"),
+            None,
+        )
+        .await
+        .unwrap();
+
+    clock.advance(chrono::Duration::minutes(119));
+    assert_eq!(delivered(&mut rx).await, None);
+
+    clock.advance(chrono::Duration::minutes(1));
+    let reminder = delivered(&mut rx).await.expect("reminder delivered");
+    assert_eq!(reminder.message, "deploy");
+    assert!(app_state.user_reminders(1).await.is_empty());
+    assert!(store.saved().await.reminders.is_empty());
+}
+
+#[tokio::test]
+async fn earlier_reminder_added_later_is_delivered_first() {
+    let store = Arc::new(MemoryStore::default());
+    let app_state = state(&store).await;
+    let clock = ManualClock::new(start());
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    tokio::spawn(Scheduler::new(app_state.clone(), clock.clone()).run(Recorder(tx)));
+    for (hours, message) in [(5, "later"), (1, "sooner")] {
+        app_state
+            .add_reminder(
+                1,
+                ReminderTarget::Channel(10),
+                start() + chrono::Duration::hours(hours),
+                start(),
+                String::from(message),
+                None,
+            )
+            .await
+            .unwrap();
+    }
+
+    clock.advance(chrono::Duration::hours(1));
+    assert_eq!(delivered(&mut rx).await.unwrap().message, "sooner");
+    assert_eq!(delivered(&mut rx).await, None);
+
+    clock.advance(chrono::Duration::hours(4));
+    assert_eq!(delivered(&mut rx).await.unwrap().message, "later");
+}
+
+#[tokio::test]
+async fn cancelled_reminders_are_not_delivered() {
+    let store = Arc::new(MemoryStore::default());
+    let app_state = state(&store).await;
+    let clock = ManualClock::new(start());
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    tokio::spawn(Scheduler::new(app_state.clone(), clock.clone()).run(Recorder(tx)));
+    let reminder = app_state
+        .add_reminder(
+            1,
+            ReminderTarget::DirectMessage,
+            start() + chrono::Duration::hours(1),
+            start(),
+            String::from("cancelled"),
+            None,
+        )
+        .await
+        .unwrap();
+
+    // only the owner can cancel a reminder
+    assert_eq!(
+        app_state.cancel_reminder(2, reminder.id).await.unwrap(),
+        None
+    );
+    assert!(app_state
+        .cancel_reminder(1, reminder.id)
+        .await
+        .unwrap()
+        .is_some());
+
+    clock.advance(chrono::Duration::hours(2));
+    assert_eq!(delivered(&mut rx).await, None);
+}
+
+#[tokio::test]
+async fn restart_delivers_missed_reminders_exactly_once() {
+    let mut database = Database::default();
+    database.reminders.push(Reminder {
+        id: 0,
+        user_id: 1,
+        target: ReminderTarget::DirectMessage,
+        due: start() - chrono::Duration::minutes(30),
+        created: start() - chrono::Duration::days(1),
+        message: String::from("missed while offline"),
+        recurrence: None,
+        failed_attempts: 0,
+    });
+    database.next_reminder_id = 1;
+    let store = Arc::new(MemoryStore::new(database));
+
+    // the first run delivers the reminder which came due while offline
+    let clock = ManualClock::new(start());
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let scheduler = Scheduler::new(state(&store).await, clock.clone());
+    assert_eq!(scheduler.deliver_due(&Recorder(tx)).await.unwrap(), 1);
+    assert_eq!(
+        delivered(&mut rx).await.unwrap().message,
+        "missed while offline"
+    );
+
+    // after a restart, the same store must not deliver it again
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    tokio::spawn(Scheduler::new(state(&store).await, clock.clone()).run(Recorder(tx)));
+    clock.advance(chrono::Duration::days(1));
+    assert_eq!(delivered(&mut rx).await, None);
+}
+
+#[tokio::test]
+async fn reminder_ids_are_not_reused() {
+    let store = Arc::new(MemoryStore::default());
+    let app_state = state(&store).await;
+    let first = app_state
+        .add_reminder(
+            1,
+            ReminderTarget::DirectMessage,
+            start(),
+            start(),
+            String::new(),
+            None,
+        )
+        .await
+        .unwrap();
+    app_state.cancel_reminder(1, first.id).await.unwrap();
+
+    let second = state(&store)
+        .await
+        .add_reminder(
+            1,
+            ReminderTarget::DirectMessage,
+            start(),
+            start(),
+            String::new(),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_ne!(first.id, second.id);
+}
+
+#[tokio::test]
+async fn recurring_reminders_are_rescheduled() {
+    let store = Arc::new(MemoryStore::default());
+    let app_state = state(&store).await;
+    let clock = ManualClock::new(start());
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    // start() is 13:00 in london, so the first occurrence is tomorrow
+    let rule = Recurrence::parse("every day at 09:30", Some(chrono_tz::Europe::London)).unwrap();
+    let first = rule.next_occurrence(start()).unwrap();
+    tokio::spawn(Scheduler::new(app_state.clone(), clock.clone()).run(Recorder(tx)));
+    app_state
+        .add_reminder(
+            1,
+            ReminderTarget::DirectMessage,
+            first,
+            start(),
+            String::from("standup"),
+            Some(rule.clone()),
+        )
+        .await
+        .unwrap();
+
+    clock.advance(first - start());
+    assert_eq!(delivered(&mut rx).await.unwrap().due, first);
+    assert_eq!(delivered(&mut rx).await, None);
+
+    // the next occurrence is persisted, so it survives a restart
+    let second = first + chrono::Duration::days(1);
+    assert_eq!(store.saved().await.reminders[0].due, second);
+
+    clock.advance(chrono::Duration::days(1));
+    assert_eq!(delivered(&mut rx).await.unwrap().due, second);
+}
+
+#[tokio::test]
+async fn failed_deliveries_are_tried_again() {
+    let store = Arc::new(MemoryStore::default());
+    let app_state = state(&store).await;
+    let clock = ManualClock::new(start());
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let delivery = Flaky {
+        failures: std::sync::Mutex::new(2),
+        recorder: Recorder(tx),
+    };
+
+    tokio::spawn(Scheduler::new(app_state.clone(), clock.clone()).run(delivery));
+    let reminder = app_state
+        .add_reminder(
+            1,
+            ReminderTarget::Channel(10),
+            start() + chrono::Duration::hours(1),
+            start(),
+            String::from("deploy"),
+            None,
+        )
+        .await
+        .unwrap();
+
+    clock.advance(chrono::Duration::hours(1));
+    assert_eq!(delivered(&mut rx).await, None);
+    // the failed reminder is kept in the store, so it survives a restart
+    assert_eq!(store.saved().await.reminders[0].id, reminder.id);
+
+    clock.advance(chrono::Duration::minutes(1));
+    assert_eq!(delivered(&mut rx).await, None);
+
+    clock.advance(chrono::Duration::minutes(1));
+    let retried = delivered(&mut rx).await.expect("reminder delivered");
+    assert_eq!(retried.id, reminder.id);
+    assert_eq!(retried.failed_attempts, 2);
+    assert!(store.saved().await.reminders.is_empty());
+}
+
+#[tokio::test]
+async fn undeliverable_reminders_are_given_up_on() {
+    let store = Arc::new(MemoryStore::default());
+    let app_state = state(&store).await;
+    let clock = ManualClock::new(start());
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let delivery = Flaky {
+        failures: std::sync::Mutex::new(u32::MAX),
+        recorder: Recorder(tx),
+    };
+
+    let scheduler = Scheduler::new(app_state.clone(), clock.clone());
+    app_state
+        .add_reminder(
+            1,
+            ReminderTarget::Channel(10),
+            start(),
+            start(),
+            String::from("deploy"),
+            None,
+        )
+        .await
+        .unwrap();
+
+    for _ in 0..10 {
+        assert_eq!(scheduler.deliver_due(&delivery).await.unwrap(), 0);
+        clock.advance(chrono::Duration::minutes(1));
+    }
+    assert!(app_state.user_reminders(1).await.is_empty());
+    assert_eq!(delivered(&mut rx).await, None);
+}
@@ -1,22 +1,374 @@
 use std::{
+    collections::HashMap,
     error::Error,
-    sync::{atomic::AtomicU64, Arc},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
 };
 
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
 use serenity::prelude::TypeMapKey;
+use tokio::sync::{watch, Mutex as AsyncMutex, Notify, RwLock};
+
+use crate::{
+    reminders::Recurrence,
+    store::{
+        Countdown, Database, GuildConfig, GuildSettings, Reminder, ReminderTarget, Store,
+        StoreError, UserProfile, WorkingHours,
+    },
+};
+
+/// Counts of the unexpected events the discord manager recovered from while routing events to
+/// guild handlers, reported by the healthcheck
+#[derive(Debug, Default)]
+pub struct RoutingAnomalies {
+    /// a guild's handler had stopped, so it was replaced when the guild was added again or an event
+    /// arrived for it
+    pub replaced_handlers: AtomicU64,
+    /// an event arrived for a guild without a handler, so one was created for it
+    pub created_handlers: AtomicU64,
+    /// an event arrived before the bot had connected, so was held until a handler could be created
+    pub queued_events: AtomicU64,
+    /// a guild which had no handler was deleted
+    pub unknown_deletions: AtomicU64,
+    /// an event couldn't be passed on to any handler, so was dropped
+    pub dropped_events: AtomicU64,
+}
+
+impl RoutingAnomalies {
+    /// every counter with its name, e.g. ("replaced_handlers", 1)
+    pub fn counts(&self) -> [(&'static str, u64); 5] {
+        [
+            ("replaced_handlers", &self.replaced_handlers),
+            ("created_handlers", &self.created_handlers),
+            ("queued_events", &self.queued_events),
+            ("unknown_deletions", &self.unknown_deletions),
+            ("dropped_events", &self.dropped_events),
+        ]
+        .map(|(name, counter)| (name, counter.load(Ordering::Relaxed)))
+    }
+}
 
 /// A connection to the database, representing the stored "state" of the app
 pub struct AppState {
     pub start_time: std::time::Instant,
     pub num_connected: Arc<AtomicU64>,
+    /// counts of the unexpected events the discord manager has recovered from
+    pub routing_anomalies: Arc<RoutingAnomalies>,
+    /// the backend the database is persisted to
+    store: Arc<dyn Store>,
+    /// the in-memory copy of the database, every change is written through to the store
+    database: Arc<RwLock<Database>>,
+    /// held while a change is being made and saved, so changes are saved one at a time and in order
+    saving: Arc<AsyncMutex<()>>,
+    /// notified whenever the set of pending reminders changes, so the scheduler can re-plan
+    reminders_changed: Arc<Notify>,
+    /// the configuration of each guild which is being watched, so changes reach running guild handlers
+    guild_configs: Arc<Mutex<HashMap<u64, watch::Sender<GuildConfig>>>>,
 }
 
 impl AppState {
-    pub async fn new() -> Result<Self, Box<dyn Error>> {
+    /// create the app state, loading any previously stored data from the provided store
+    pub async fn new(store: impl Store + 'static) -> Result<Self, Box<dyn Error>> {
+        let mut database = store.load().await.map_err(|e| e.to_string())?;
+        database.migrate();
         Ok(Self {
             start_time: std::time::Instant::now(),
             num_connected: Arc::new(AtomicU64::new(0)),
+            routing_anomalies: Arc::new(RoutingAnomalies::default()),
+            store: Arc::new(store),
+            database: Arc::new(RwLock::new(database)),
+            saving: Arc::new(AsyncMutex::new(())),
+            reminders_changed: Arc::new(Notify::new()),
+            guild_configs: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    /// read from the database
+    pub async fn read<R>(&self, f: impl FnOnce(&Database) -> R) -> R {
+        f(&*self.database.read().await)
+    }
+
+    /// modify the database, persisting the changes to the store before returning.
+    /// the change is made to a copy, which only replaces the database once it has been saved,
+    /// so a failed save leaves the database untouched. the database can still be read while
+    /// the copy is being saved
+    pub async fn update<R>(&self, f: impl FnOnce(&mut Database) -> R) -> Result<R, StoreError> {
+        let _saving = self.saving.lock().await;
+        let mut updated = self.database.read().await.clone();
+        let result = f(&mut updated);
+        self.store.save(&updated).await?;
+        *self.database.write().await = updated;
+        Ok(result)
+    }
+
+    /// save the database to the store once more, after any change in progress has been saved.
+    /// used when shutting down, so the store is known to hold the final state
+    pub async fn flush(&self) -> Result<(), StoreError> {
+        let _saving = self.saving.lock().await;
+        let database = self.database.read().await.clone();
+        self.store.save(&database).await
+    }
+
+    /// get the timezone a user has registered, if any
+    pub async fn user_timezone(&self, user_id: u64) -> Option<Tz> {
+        self.read(|db| db.users.get(&user_id).and_then(|u| u.timezone))
+            .await
+    }
+
+    /// register the timezone for a user, replacing any existing timezone
+    pub async fn set_user_timezone(&self, user_id: u64, timezone: Tz) -> Result<(), StoreError> {
+        self.update(|db| db.users.entry(user_id).or_default().timezone = Some(timezone))
+            .await
+    }
+
+    /// get the stored profile of a user, if they have one
+    pub async fn user_profile(&self, user_id: u64) -> Option<UserProfile> {
+        self.read(|db| db.users.get(&user_id).cloned()).await
+    }
+
+    /// set the working hours of a user
+    pub async fn set_user_working_hours(
+        &self,
+        user_id: u64,
+        working_hours: WorkingHours,
+    ) -> Result<(), StoreError> {
+        self.update(|db| db.users.entry(user_id).or_default().working_hours = Some(working_hours))
+            .await
+    }
+
+    /// get the settings of a guild, or the default settings if none have been stored
+    pub async fn guild_settings(&self, guild_id: u64) -> GuildSettings {
+        self.read(|db| db.guilds.get(&guild_id).cloned().unwrap_or_default())
+            .await
+    }
+
+    /// modify the settings of a guild, returning the updated settings
+    pub async fn update_guild_settings(
+        &self,
+        guild_id: u64,
+        f: impl FnOnce(&mut GuildSettings),
+    ) -> Result<GuildSettings, StoreError> {
+        self.update(|db| {
+            let settings = db.guilds.entry(guild_id).or_default();
+            f(settings);
+            settings.clone()
+        })
+        .await
+    }
+
+    /// get the configuration of a guild, or the default configuration if none has been stored
+    pub async fn guild_config(&self, guild_id: u64) -> GuildConfig {
+        self.read(|db| {
+            db.guilds
+                .get(&guild_id)
+                .map(|g| g.config.clone())
+                .unwrap_or_default()
         })
+        .await
+    }
+
+    /// watch the configuration of a guild, the receiver sees every change made with
+    /// [AppState::update_guild_config] as soon as it is saved
+    pub async fn watch_guild_config(&self, guild_id: u64) -> watch::Receiver<GuildConfig> {
+        let config = self.guild_config(guild_id).await;
+        self.guild_configs
+            .lock()
+            .expect("guild config lock poisoned")
+            .entry(guild_id)
+            .or_insert_with(|| watch::channel(config).0)
+            .subscribe()
+    }
+
+    /// modify the configuration of a guild, returning the updated configuration.
+    /// the change is published to anything watching the guild's configuration
+    pub async fn update_guild_config(
+        &self,
+        guild_id: u64,
+        f: impl FnOnce(&mut GuildConfig),
+    ) -> Result<GuildConfig, StoreError> {
+        let config = self
+            .update_guild_settings(guild_id, |s| f(&mut s.config))
+            .await?
+            .config;
+        if let Some(sender) = self
+            .guild_configs
+            .lock()
+            .expect("guild config lock poisoned")
+            .get(&guild_id)
+        {
+            sender.send_replace(config.clone());
+        }
+        Ok(config)
+    }
+
+    /// the timezone to use for a user, their registered timezone or else the default timezone of
+    /// the guild they are in
+    pub async fn effective_timezone(&self, user_id: u64, guild_id: Option<u64>) -> Option<Tz> {
+        self.read(|db| {
+            db.users.get(&user_id).and_then(|u| u.timezone).or_else(|| {
+                guild_id
+                    .and_then(|g| db.guilds.get(&g))
+                    .and_then(|g| g.config.default_timezone)
+            })
+        })
+        .await
+    }
+
+    /// remove the countdowns in a guild which have been reached by `now`, returning them.
+    /// countdowns are removed before being announced, so they are never announced twice
+    pub async fn take_finished_countdowns(
+        &self,
+        guild_id: u64,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<Countdown>, StoreError> {
+        self.update(|db| match db.guilds.get_mut(&guild_id) {
+            Some(settings) => {
+                let (finished, running) = std::mem::take(&mut settings.countdowns)
+                    .into_iter()
+                    .partition(|c| c.target <= now);
+                settings.countdowns = running;
+                finished
+            }
+            None => Vec::new(),
+        })
+        .await
+    }
+
+    /// remove the timezone registered for a user, returning the removed timezone if there was one
+    pub async fn clear_user_timezone(&self, user_id: u64) -> Result<Option<Tz>, StoreError> {
+        self.update(|db| db.users.get_mut(&user_id).and_then(|u| u.timezone.take()))
+            .await
+    }
+
+    /// create a reminder for a user at the instant `created`, returning the stored reminder
+    pub async fn add_reminder(
+        &self,
+        user_id: u64,
+        target: ReminderTarget,
+        due: DateTime<Utc>,
+        created: DateTime<Utc>,
+        message: String,
+        recurrence: Option<Recurrence>,
+    ) -> Result<Reminder, StoreError> {
+        let reminder = self
+            .update(|db| {
+                let reminder = Reminder {
+                    id: db.next_reminder_id,
+                    user_id,
+                    target,
+                    due,
+                    created,
+                    message,
+                    recurrence,
+                    failed_attempts: 0,
+                };
+                db.next_reminder_id += 1;
+                db.reminders.push(reminder.clone());
+                reminder
+            })
+            .await?;
+        self.reminders_changed.notify_one();
+        Ok(reminder)
+    }
+
+    /// get the pending reminders of a user, soonest first
+    pub async fn user_reminders(&self, user_id: u64) -> Vec<Reminder> {
+        let mut reminders = self
+            .read(|db| {
+                db.reminders
+                    .iter()
+                    .filter(|r| r.user_id == user_id)
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .await;
+        reminders.sort_by_key(|r| r.due);
+        reminders
+    }
+
+    /// cancel a pending reminder belonging to a user, returning the reminder if it existed
+    pub async fn cancel_reminder(
+        &self,
+        user_id: u64,
+        id: u64,
+    ) -> Result<Option<Reminder>, StoreError> {
+        let removed = self
+            .update(|db| {
+                let index = db
+                    .reminders
+                    .iter()
+                    .position(|r| r.id == id && r.user_id == user_id)?;
+                Some(db.reminders.remove(index))
+            })
+            .await?;
+        self.reminders_changed.notify_one();
+        Ok(removed)
+    }
+
+    /// the instant the next pending reminder is due, if there are any
+    pub async fn next_reminder_due(&self) -> Option<DateTime<Utc>> {
+        self.read(|db| db.reminders.iter().map(|r| r.due).min())
+            .await
+    }
+
+    /// remove every reminder due at or before the provided instant, returning them to be delivered.
+    /// they are removed from the store before being returned, so a reminder is never delivered twice,
+    /// and recurring reminders are rescheduled for their next occurrence after `now` in the same change
+    pub async fn take_due_reminders(
+        &self,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<Reminder>, StoreError> {
+        self.update(|db| {
+            let (due, mut pending): (Vec<_>, Vec<_>) = std::mem::take(&mut db.reminders)
+                .into_iter()
+                .partition(|r| r.due <= now);
+            pending.extend(due.iter().filter_map(|r| {
+                let next = r.recurrence.as_ref()?.next_occurrence(now)?;
+                Some(Reminder {
+                    due: next,
+                    failed_attempts: 0,
+                    ..r.clone()
+                })
+            }));
+            db.reminders = pending;
+            due
+        })
+        .await
+    }
+
+    /// put back a reminder which failed to be delivered, to be tried again at `retry_at`.
+    /// a recurring reminder has already been rescheduled, so it is put back as a one-off copy
+    /// with its own id
+    pub async fn retry_reminder(
+        &self,
+        reminder: Reminder,
+        retry_at: DateTime<Utc>,
+    ) -> Result<Reminder, StoreError> {
+        let retry = self
+            .update(|db| {
+                let mut retry = Reminder {
+                    due: retry_at,
+                    failed_attempts: reminder.failed_attempts + 1,
+                    ..reminder
+                };
+                if retry.recurrence.take().is_some() {
+                    retry.id = db.next_reminder_id;
+                    db.next_reminder_id += 1;
+                }
+                db.reminders.push(retry.clone());
+                retry
+            })
+            .await?;
+        self.reminders_changed.notify_one();
+        Ok(retry)
+    }
+
+    /// wait until the set of pending reminders changes
+    pub async fn reminders_changed(&self) {
+        self.reminders_changed.notified().await
     }
 }
 
@@ -31,6 +383,12 @@ impl Clone for AppState {
         Self {
             start_time: self.start_time,
             num_connected: self.num_connected.clone(),
+            routing_anomalies: self.routing_anomalies.clone(),
+            store: self.store.clone(),
+            database: self.database.clone(),
+            saving: self.saving.clone(),
+            reminders_changed: self.reminders_changed.clone(),
+            guild_configs: self.guild_configs.clone(),
         }
     }
 }
@@ -0,0 +1,52 @@
+//! A store which keeps the database as a single json file on disk
+
+use std::path::PathBuf;
+
+use log::info;
+use serenity::async_trait;
+
+use super::{Database, Store, StoreError};
+
+/// Persists the database as pretty-printed json at the provided path. Writes go to a temporary
+/// file first and are then renamed into place, so a crash mid-write never corrupts the save.
+#[derive(Debug, Clone)]
+pub struct JsonFileStore {
+    /// the location of the database file
+    path: PathBuf,
+}
+
+impl JsonFileStore {
+    /// create a new store backed by the file at `path`, the file is created on first save
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[async_trait]
+impl Store for JsonFileStore {
+    async fn load(&self) -> Result<Database, StoreError> {
+        match tokio::fs::read(&self.path).await {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                info!(
+                    "no database found at {}, starting with an empty database",
+                    self.path.display()
+                );
+                Ok(Database::default())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn save(&self, database: &Database) -> Result<(), StoreError> {
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let tmp_path = self.path.with_extension("json.tmp");
+        tokio::fs::write(&tmp_path, serde_json::to_vec_pretty(database)?).await?;
+        tokio::fs::rename(&tmp_path, &self.path).await?;
+
+        Ok(())
+    }
+}
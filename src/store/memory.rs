@@ -0,0 +1,39 @@
+//! A store which only keeps the database in memory, used for testing
+
+use serenity::async_trait;
+use tokio::sync::Mutex;
+
+use super::{Database, Store, StoreError};
+
+/// Keeps the last saved database in memory, nothing survives a restart
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    /// the most recently saved database
+    saved: Mutex<Database>,
+}
+
+impl MemoryStore {
+    /// create a new store, which will load the provided database
+    pub fn new(database: Database) -> Self {
+        Self {
+            saved: Mutex::new(database),
+        }
+    }
+
+    /// get a copy of the most recently saved database
+    pub async fn saved(&self) -> Database {
+        self.saved.lock().await.clone()
+    }
+}
+
+#[async_trait]
+impl Store for MemoryStore {
+    async fn load(&self) -> Result<Database, StoreError> {
+        Ok(self.saved.lock().await.clone())
+    }
+
+    async fn save(&self, database: &Database) -> Result<(), StoreError> {
+        *self.saved.lock().await = database.clone();
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+//! Persistent storage for the bot. All stored data lives in a single [Database] document, which is
+//! held in memory by [crate::state::AppState] and written back through a [Store] whenever it changes.
+//! This keeps the backing store trivially swappable, e.g. for the [MemoryStore] in tests.
+
+mod json;
+#[cfg(test)]
+mod memory;
+mod models;
+
+#[cfg(test)]
+mod tests;
+
+use std::sync::Arc;
+
+use serenity::async_trait;
+
+pub use json::JsonFileStore;
+#[cfg(test)]
+pub use memory::MemoryStore;
+#[cfg(test)]
+pub use models::WorkStatus;
+pub use models::{
+    ClockFormat, ClockZone, Countdown, Database, DayHours, Feature, GuildConfig, GuildSettings,
+    Locale, Reminder, ReminderTarget, TimeRange, UserProfile, WorkingHours, WorldClock,
+};
+
+/// an error generated by a store, must be sendable between tasks
+pub type StoreError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A backend able to persist and later restore the [Database]
+#[async_trait]
+pub trait Store: Send + Sync {
+    /// load the most recently saved database, or an empty database if nothing has been saved yet
+    async fn load(&self) -> Result<Database, StoreError>;
+
+    /// persist the provided database, replacing anything saved previously
+    async fn save(&self, database: &Database) -> Result<(), StoreError>;
+}
+
+#[async_trait]
+impl<T: Store + ?Sized> Store for Arc<T> {
+    async fn load(&self) -> Result<Database, StoreError> {
+        (**self).load().await
+    }
+
+    async fn save(&self, database: &Database) -> Result<(), StoreError> {
+        (**self).save(database).await
+    }
+}
@@ -0,0 +1,527 @@
+//! The data models which are persisted by a [super::Store]
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use crate::reminders::Recurrence;
+
+/// Everything the bot stores, new fields must be `#[serde(default)]` so that older
+/// saves can still be loaded.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Database {
+    /// preferences for each user, keyed by discord user id
+    #[serde(default)]
+    pub users: HashMap<u64, UserProfile>,
+    /// settings for each guild, keyed by discord guild id
+    #[serde(default)]
+    pub guilds: HashMap<u64, GuildSettings>,
+    /// reminders which have not been delivered yet
+    #[serde(default)]
+    pub reminders: Vec<Reminder>,
+    /// the id to give to the next reminder created, so ids are never reused
+    #[serde(default)]
+    pub next_reminder_id: u64,
+}
+
+/// The stored settings of a single discord guild
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct GuildSettings {
+    /// the configuration chosen by the guild's administrators
+    #[serde(default)]
+    pub config: GuildConfig,
+    /// the world clock boards posted in the guild, which are kept up to date by the guild handler
+    #[serde(default)]
+    pub world_clocks: Vec<WorldClock>,
+    /// the countdowns running in the guild, which are announced by the guild handler once reached
+    #[serde(default)]
+    pub countdowns: Vec<Countdown>,
+    /// every daylight savings change up to this instant has already been announced
+    #[serde(default)]
+    pub dst_announced_until: Option<DateTime<Utc>>,
+    /// settings from before guilds had a [GuildConfig], which are only read to be migrated
+    #[serde(default, flatten, skip_serializing)]
+    legacy: LegacyGuildSettings,
+}
+
+/// The settings a guild could have before they were moved into its [GuildConfig]
+#[derive(Debug, Default, Clone, Deserialize)]
+struct LegacyGuildSettings {
+    #[serde(default)]
+    auto_convert: bool,
+    #[serde(default)]
+    auto_convert_channels: BTreeSet<u64>,
+    #[serde(default)]
+    dst_channel: Option<u64>,
+}
+
+impl Database {
+    /// move settings saved by older versions to where they are kept now
+    pub fn migrate(&mut self) {
+        for settings in self.guilds.values_mut() {
+            let legacy = std::mem::take(&mut settings.legacy);
+            if legacy.auto_convert {
+                settings
+                    .config
+                    .auto_convert_channels
+                    .extend(legacy.auto_convert_channels);
+            }
+            if settings.config.announcement_channel.is_none() {
+                settings.config.announcement_channel = legacy.dst_channel;
+            }
+        }
+    }
+}
+
+/// The configuration of a guild, chosen by its administrators with `/config`
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GuildConfig {
+    /// the timezone used for members who have not registered one
+    #[serde(default)]
+    pub default_timezone: Option<Tz>,
+    /// how times are written out
+    #[serde(default)]
+    pub clock: ClockFormat,
+    /// how dates are read and written
+    #[serde(default)]
+    pub locale: Locale,
+    /// the features which have been turned off, every feature is on unless listed here
+    #[serde(default)]
+    pub disabled_features: BTreeSet<Feature>,
+    /// the channel announcements such as daylight savings changes are posted in
+    #[serde(default)]
+    pub announcement_channel: Option<u64>,
+    /// the channels in which times mentioned in messages are converted automatically
+    #[serde(default)]
+    pub auto_convert_channels: BTreeSet<u64>,
+    /// commands which only members with one of the listed roles may use, keyed by command name.
+    /// commands which aren't listed can be used by anyone allowed to by discord
+    #[serde(default)]
+    pub command_roles: BTreeMap<String, BTreeSet<u64>>,
+}
+
+impl GuildConfig {
+    /// check if a feature is turned on
+    pub fn enabled(&self, feature: Feature) -> bool {
+        !self.disabled_features.contains(&feature)
+    }
+
+    /// check if a member with the provided roles may use a command,
+    /// administrators can use every command so that they can't lock themselves out
+    pub fn allows(&self, command: &str, roles: &[u64], administrator: bool) -> bool {
+        match self.command_roles.get(command) {
+            Some(allowed) if !administrator => roles.iter().any(|r| allowed.contains(r)),
+            _ => true,
+        }
+    }
+
+    /// check if times mentioned in the provided channel should be converted automatically
+    pub fn converts_in(&self, channel_id: u64) -> bool {
+        self.enabled(Feature::AutoConvert) && self.auto_convert_channels.contains(&channel_id)
+    }
+}
+
+/// Whether times are written with a 24 hour clock, or a 12 hour clock with am/pm
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClockFormat {
+    TwentyFourHour,
+    #[default]
+    TwelveHour,
+}
+
+impl ClockFormat {
+    /// the chrono format string for a time of day in this format
+    pub fn time_format(&self) -> &'static str {
+        match self {
+            Self::TwentyFourHour => "%H:%M",
+            Self::TwelveHour => "%-I:%M%P",
+        }
+    }
+}
+
+impl std::fmt::Display for ClockFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TwentyFourHour => write!(f, "24 hour"),
+            Self::TwelveHour => write!(f, "12 hour"),
+        }
+    }
+}
+
+/// The conventions used to read and write dates
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Locale {
+    /// british english, numeric dates are day/month
+    #[default]
+    #[serde(rename = "en-GB")]
+    EnGb,
+    /// american english, numeric dates are month/day
+    #[serde(rename = "en-US")]
+    EnUs,
+}
+
+impl Locale {
+    /// every supported locale
+    pub const ALL: [Locale; 2] = [Locale::EnGb, Locale::EnUs];
+
+    /// the language tag of the locale, e.g. "en-GB"
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::EnGb => "en-GB",
+            Self::EnUs => "en-US",
+        }
+    }
+
+    /// find a locale by its language tag
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|l| l.code().eq_ignore_ascii_case(code))
+    }
+
+    /// whether numeric dates such as 3/11 are written month first
+    pub fn month_first(&self) -> bool {
+        matches!(self, Self::EnUs)
+    }
+}
+
+/// A part of the bot which a guild can turn off
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Feature {
+    AutoConvert,
+    WorldClocks,
+    Countdowns,
+    DstAnnouncements,
+    Reminders,
+    Meetings,
+}
+
+impl Feature {
+    /// every feature which can be turned off
+    pub const ALL: [Feature; 6] = [
+        Feature::AutoConvert,
+        Feature::WorldClocks,
+        Feature::Countdowns,
+        Feature::DstAnnouncements,
+        Feature::Reminders,
+        Feature::Meetings,
+    ];
+
+    /// the identifier of the feature, as used in commands, e.g. "world_clocks"
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::AutoConvert => "auto_convert",
+            Self::WorldClocks => "world_clocks",
+            Self::Countdowns => "countdowns",
+            Self::DstAnnouncements => "dst_announcements",
+            Self::Reminders => "reminders",
+            Self::Meetings => "meetings",
+        }
+    }
+
+    /// find a feature by its identifier
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.id() == id)
+    }
+}
+
+impl std::fmt::Display for Feature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::AutoConvert => write!(f, "Automatic time conversion"),
+            Self::WorldClocks => write!(f, "World clocks"),
+            Self::Countdowns => write!(f, "Countdowns"),
+            Self::DstAnnouncements => write!(f, "Daylight savings announcements"),
+            Self::Reminders => write!(f, "Reminders"),
+            Self::Meetings => write!(f, "Meeting planning"),
+        }
+    }
+}
+
+/// A message counting down to an instant, announced in its channel once the instant is reached
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Countdown {
+    /// the channel the countdown was posted in, and will be announced in
+    pub channel_id: u64,
+    /// the message displaying the countdown
+    pub message_id: u64,
+    /// the user who started the countdown
+    pub user_id: u64,
+    /// what is being counted down to
+    pub label: String,
+    /// the instant being counted down to
+    pub target: DateTime<Utc>,
+    /// whether the message is edited as time passes to show the time remaining
+    #[serde(default)]
+    pub live: bool,
+}
+
+/// A message listing the current time in several timezones, which is edited every minute
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorldClock {
+    /// the channel the board was posted in
+    pub channel_id: u64,
+    /// the message displaying the board
+    pub message_id: u64,
+    /// the timezones shown on the board, in order
+    pub zones: Vec<ClockZone>,
+    /// whether times are shown in 24 hour format rather than with am/pm
+    #[serde(default)]
+    pub twenty_four_hour: bool,
+}
+
+/// A single timezone displayed on a [WorldClock]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClockZone {
+    /// the timezone to display
+    pub tz: Tz,
+    /// the name to display the timezone as, instead of the timezone name
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+impl ClockZone {
+    /// the name to display for this timezone
+    pub fn label(&self) -> &str {
+        self.label.as_deref().unwrap_or(self.tz.name())
+    }
+}
+
+/// The stored preferences of a single discord user
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct UserProfile {
+    /// the timezone the user has registered, if any
+    #[serde(default)]
+    pub timezone: Option<Tz>,
+    /// the hours the user is usually working, if they have set them
+    #[serde(default)]
+    pub working_hours: Option<WorkingHours>,
+}
+
+/// A span of local time within a day, which may cross midnight if `end` is before `start`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeRange {
+    /// the local time the span starts
+    pub start: NaiveTime,
+    /// the local time the span ends
+    pub end: NaiveTime,
+}
+
+impl TimeRange {
+    /// check if the span contains the provided local time, ignoring any part crossing midnight
+    fn contains_before_midnight(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            time >= self.start && time < self.end
+        } else {
+            time >= self.start
+        }
+    }
+
+    /// check if the part of the span after midnight contains the provided local time
+    fn contains_after_midnight(&self, time: NaiveTime) -> bool {
+        self.start > self.end && time < self.end
+    }
+}
+
+impl std::fmt::Display for TimeRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )
+    }
+}
+
+/// Working hours for a specific day of the week, replacing the usual hours on that day
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DayHours {
+    /// the day of the week
+    pub day: Weekday,
+    /// the hours worked on that day
+    pub hours: TimeRange,
+}
+
+/// Whether a user is working at a specific time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkStatus {
+    /// within their working hours
+    Working,
+    /// within their working hours, but on their lunch break
+    OnBreak,
+    /// the day is one of their days off
+    DayOff,
+    /// outside of their working hours
+    OutsideHours,
+}
+
+impl std::fmt::Display for WorkStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Working => write!(f, "within working hours"),
+            Self::OnBreak => write!(f, "on their lunch break"),
+            Self::DayOff => write!(f, "on a day off"),
+            Self::OutsideHours => write!(f, "outside working hours"),
+        }
+    }
+}
+
+/// The hours of the day, and days of the week, a user is usually working
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkingHours {
+    /// the local time the working day starts
+    pub start: NaiveTime,
+    /// the local time the working day ends, may be before `start` for overnight shifts
+    pub end: NaiveTime,
+    /// the days of the week which are worked
+    pub days: Vec<Weekday>,
+    /// different hours for specific days of the week, which replace `start` and `end` on those days
+    #[serde(default)]
+    pub day_hours: Vec<DayHours>,
+    /// a break taken every working day, e.g. for lunch
+    #[serde(default)]
+    pub lunch: Option<TimeRange>,
+    /// specific dates which are not worked, e.g. holidays
+    #[serde(default)]
+    pub days_off: Vec<NaiveDate>,
+}
+
+impl Default for WorkingHours {
+    fn default() -> Self {
+        Self {
+            start: NaiveTime::from_hms_opt(9, 0, 0).expect("valid time"),
+            end: NaiveTime::from_hms_opt(17, 0, 0).expect("valid time"),
+            days: vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+            ],
+            day_hours: Vec::new(),
+            lunch: None,
+            days_off: Vec::new(),
+        }
+    }
+}
+
+impl WorkingHours {
+    /// the hours usually worked on a day of the week, if it is worked
+    pub fn hours_on(&self, day: Weekday) -> Option<TimeRange> {
+        if !self.days.contains(&day) {
+            return None;
+        }
+        Some(
+            self.day_hours
+                .iter()
+                .find(|d| d.day == day)
+                .map(|d| d.hours)
+                .unwrap_or(TimeRange {
+                    start: self.start,
+                    end: self.end,
+                }),
+        )
+    }
+
+    /// whether the user is working at the provided local time
+    pub fn status<T: TimeZone>(&self, local: &DateTime<T>) -> WorkStatus {
+        let date = local.date_naive();
+        let time = local.time();
+
+        // an overnight shift belongs to the day it started on
+        let shift_start = match self.hours_on(date.weekday()) {
+            Some(hours) if hours.contains_before_midnight(time) => Some(date),
+            _ => match date
+                .pred_opt()
+                .and_then(|d| Some((d, self.hours_on(d.weekday())?)))
+            {
+                Some((yesterday, hours)) if hours.contains_after_midnight(time) => Some(yesterday),
+                _ => None,
+            },
+        };
+
+        match shift_start {
+            Some(day) if self.days_off.contains(&day) => WorkStatus::DayOff,
+            None if self.days_off.contains(&date) => WorkStatus::DayOff,
+            None => WorkStatus::OutsideHours,
+            Some(_)
+                if self.lunch.is_some_and(|l| {
+                    l.contains_before_midnight(time) || l.contains_after_midnight(time)
+                }) =>
+            {
+                WorkStatus::OnBreak
+            }
+            Some(_) => WorkStatus::Working,
+        }
+    }
+
+    /// check if the provided local time falls within these working hours
+    pub fn contains<T: TimeZone>(&self, local: &DateTime<T>) -> bool {
+        self.status(local) == WorkStatus::Working
+    }
+
+    /// describe these working hours in plain english, e.g. "09:00-17:00 on Mon, Tue, Wed"
+    pub fn describe(&self) -> String {
+        let days = self
+            .days
+            .iter()
+            .map(|d| match self.day_hours.iter().find(|h| h.day == *d) {
+                Some(h) => format!("{} ({})", d, h.hours),
+                None => d.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut description = format!(
+            "{}-{} on {}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M"),
+            days
+        );
+        if let Some(lunch) = self.lunch {
+            description.push_str(&format!(", lunch {}", lunch));
+        }
+        match self.days_off.len() {
+            0 => {}
+            1 => description.push_str(", 1 day off"),
+            n => description.push_str(&format!(", {} days off", n)),
+        }
+        description
+    }
+}
+
+/// A message to deliver to a user at a specific instant
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reminder {
+    /// a unique id for the reminder, used to cancel it
+    pub id: u64,
+    /// the user who created the reminder
+    pub user_id: u64,
+    /// where the reminder should be delivered
+    pub target: ReminderTarget,
+    /// the instant the reminder should be delivered
+    pub due: DateTime<Utc>,
+    /// the instant the reminder was created
+    pub created: DateTime<Utc>,
+    /// what the user asked to be reminded of
+    pub message: String,
+    /// the rule the reminder repeats on, if it is recurring
+    #[serde(default)]
+    pub recurrence: Option<Recurrence>,
+    /// how many times delivering the reminder has failed
+    #[serde(default)]
+    pub failed_attempts: u32,
+}
+
+/// Where a [Reminder] is delivered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReminderTarget {
+    /// posted in a guild channel, mentioning the user
+    Channel(u64),
+    /// sent to the user as a direct message
+    DirectMessage,
+}
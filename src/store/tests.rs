@@ -0,0 +1,123 @@
+use std::{sync::Arc, time::Duration};
+
+use serenity::async_trait;
+use tokio::sync::Notify;
+
+use super::{Database, Feature, GuildConfig, Store, StoreError};
+use crate::state::AppState;
+
+/// a store which can be loaded from, but refuses every save
+struct ReadOnlyStore;
+
+#[async_trait]
+impl Store for ReadOnlyStore {
+    async fn load(&self) -> Result<Database, StoreError> {
+        Ok(Database::default())
+    }
+
+    async fn save(&self, _: &Database) -> Result<(), StoreError> {
+        Err("the store is read only".into())
+    }
+}
+
+/// a store which holds every save until it is released
+#[derive(Default)]
+struct SlowStore {
+    /// notified to let a save finish
+    release: Notify,
+}
+
+#[async_trait]
+impl Store for SlowStore {
+    async fn load(&self) -> Result<Database, StoreError> {
+        Ok(Database::default())
+    }
+
+    async fn save(&self, _: &Database) -> Result<(), StoreError> {
+        self.release.notified().await;
+        Ok(())
+    }
+}
+
+#[test]
+fn legacy_guild_settings_are_migrated() {
+    let mut database: Database = serde_json::from_str(
+        r#"{
+            "guilds": {
+                "1": { "auto_convert": true, "auto_convert_channels": [10, 11], "dst_channel": 12 },
+                "2": { "auto_convert": false, "auto_convert_channels": [20] }
+            }
+        }"#,
+    )
+    .expect("legacy settings can be loaded");
+    database.migrate();
+
+    let config = &database.guilds[&1].config;
+    assert!(config.converts_in(10) && config.converts_in(11));
+    assert_eq!(config.announcement_channel, Some(12));
+
+    // conversion was off for the whole guild, so its channels are not carried over
+    let config = &database.guilds[&2].config;
+    assert!(!config.converts_in(20));
+    assert!(config.enabled(Feature::AutoConvert));
+
+    // the old fields are not written back
+    let saved = serde_json::to_string(&database).expect("database can be saved");
+    assert!(!saved.contains("dst_channel"));
+}
+
+#[test]
+fn times_are_written_with_a_12_hour_clock_by_default() {
+    let time = chrono::NaiveTime::from_hms_opt(15, 34, 0).unwrap();
+    let format = GuildConfig::default().clock.time_format();
+    assert_eq!(time.format(format).to_string(), "3:34pm");
+}
+
+#[test]
+fn commands_can_be_restricted_to_roles() {
+    let mut config = GuildConfig::default();
+    assert!(config.allows("meeting", &[], false));
+
+    config
+        .command_roles
+        .insert(String::from("meeting"), [1, 2].into_iter().collect());
+    assert!(config.allows("meeting", &[3, 2], false));
+    assert!(!config.allows("meeting", &[3], false));
+    // administrators can't lock themselves out
+    assert!(config.allows("meeting", &[], true));
+    // other commands are unaffected
+    assert!(config.allows("time", &[], false));
+}
+
+#[tokio::test]
+async fn failed_saves_leave_the_database_untouched() {
+    let app_state = AppState::new(ReadOnlyStore).await.unwrap();
+
+    let result = app_state
+        .set_user_timezone(1, chrono_tz::Europe::London)
+        .await;
+    assert!(result.is_err());
+    assert_eq!(app_state.user_timezone(1).await, None);
+}
+
+#[tokio::test]
+async fn the_database_can_be_read_while_a_change_is_saved() {
+    let store = Arc::new(SlowStore::default());
+    let app_state = AppState::new(store.clone()).await.unwrap();
+
+    let saving = app_state.clone();
+    let update =
+        tokio::spawn(async move { saving.set_user_timezone(1, chrono_tz::Europe::London).await });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    // the change isn't seen until it has been saved, but reading doesn't wait for the save
+    let read = tokio::time::timeout(Duration::from_secs(1), app_state.user_timezone(1)).await;
+    assert_eq!(read, Ok(None));
+
+    store.release.notify_one();
+    update.await.unwrap().unwrap();
+    assert_eq!(
+        app_state.user_timezone(1).await,
+        Some(chrono_tz::Europe::London)
+    );
+}
@@ -0,0 +1,1138 @@
+//! Parsing of natural language time expressions, such as "next friday 4:30pm PST", "in 2h15m",
+//! "noon Auckland time" or "2026-11-03T09:00+13:00", into a point in time in a specific timezone.
+//!
+//! Anything that could reasonably have been meant more than one way (e.g. a time which happens twice
+//! as the clocks go back, or "9" without an am/pm) is resolved to a single answer, with the decision
+//! explicitly reported as an [Ambiguity] so callers can surface it to the user.
+//!
+//! This module has no dependency on discord, so that it can be tested exhaustively.
+
+#[cfg(test)]
+mod tests;
+
+use std::fmt::Display;
+
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, LocalResult, NaiveDate, NaiveDateTime, NaiveTime,
+    Offset, TimeZone, Timelike, Utc, Weekday,
+};
+use chrono_tz::Tz;
+
+use crate::timezones;
+
+/// words which carry no meaning of their own, and are skipped while parsing
+const FILLER_WORDS: &[&str] = &["at", "on", "the", "of", "time", "by", "@", "and", "this"];
+
+/// The information required to interpret a time expression
+#[derive(Debug, Clone, Copy)]
+pub struct ParseContext {
+    /// the current instant, relative expressions ("tomorrow", "in 2h") are relative to this
+    pub now: DateTime<Utc>,
+    /// the timezone to interpret the expression in, if it does not name a timezone itself
+    pub default_tz: Option<Tz>,
+    /// if an expression without a full date ("3pm", "friday") would be in the past, move it
+    /// forward to the next occurrence instead
+    pub prefer_future: bool,
+    /// read numeric dates such as 3/11 as month/day, rather than day/month
+    pub month_first: bool,
+}
+
+impl ParseContext {
+    /// create a new context at the provided instant, in the provided default timezone
+    pub fn new(now: DateTime<Utc>, default_tz: Option<Tz>) -> Self {
+        Self {
+            now,
+            default_tz,
+            prefer_future: false,
+            month_first: false,
+        }
+    }
+
+    /// move expressions without a full date forward to their next occurrence, see [ParseContext::prefer_future]
+    pub fn prefer_future(mut self) -> Self {
+        self.prefer_future = true;
+        self
+    }
+
+    /// set whether numeric dates are read as month/day, see [ParseContext::month_first]
+    pub fn month_first(mut self, month_first: bool) -> Self {
+        self.month_first = month_first;
+        self
+    }
+}
+
+/// A successfully parsed time expression
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedTime {
+    /// the point in time which was described
+    pub time: DateTime<Tz>,
+    /// whether the expression named its own timezone or offset, rather than using the default
+    pub explicit_timezone: bool,
+    /// whether the expression was relative to now, e.g. "in 2 hours"
+    pub relative: bool,
+    /// every decision that was made while interpreting the expression
+    pub ambiguities: Vec<Ambiguity>,
+}
+
+impl ParsedTime {
+    /// whether any part of the expression could have been interpreted differently
+    pub fn is_ambiguous(&self) -> bool {
+        !self.ambiguities.is_empty()
+    }
+}
+
+/// A part of a time expression which could have meant more than one thing
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ambiguity {
+    /// the local time occurs twice as the clocks go back, the earlier of the two was chosen
+    DstOverlap {
+        /// the first occurrence, which was chosen
+        earlier: DateTime<Tz>,
+        /// the second occurrence
+        later: DateTime<Tz>,
+    },
+    /// the local time does not exist as the clocks go forward, it was moved forward by the length of the gap
+    DstGap {
+        /// the local time which was asked for
+        requested: NaiveDateTime,
+        /// the time which was used instead
+        shifted_to: DateTime<Tz>,
+    },
+    /// an hour was given without am or pm, and was read as a 24 hour time
+    NoMeridiem {
+        /// the time which was chosen
+        chosen: NaiveTime,
+        /// the time if it had been read as pm
+        alternative: NaiveTime,
+    },
+    /// "next <weekday>" could mean the coming day, or the same day a week later
+    NextWeekday {
+        /// the date which was chosen, the first matching day after today
+        chosen: NaiveDate,
+        /// the date a week later
+        alternative: NaiveDate,
+    },
+    /// a numeric date such as 3/11 could be read as day/month or month/day, day/month was chosen
+    DayMonthOrder {
+        /// the date read as day/month
+        chosen: NaiveDate,
+        /// the date read as month/day
+        alternative: NaiveDate,
+    },
+    /// a numeric date such as 3/11 could be read as month/day or day/month, month/day was chosen
+    /// as the context asked for it
+    MonthDayOrder {
+        /// the date read as month/day
+        chosen: NaiveDate,
+        /// the date read as day/month
+        alternative: NaiveDate,
+    },
+}
+
+impl Display for Ambiguity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DstOverlap { earlier, later } => write!(
+                f,
+                "{} happens twice as the clocks go back, I used the first ({}) rather than the second ({})",
+                earlier.format("%H:%M"),
+                earlier.format("%Z"),
+                later.format("%Z")
+            ),
+            Self::DstGap {
+                requested,
+                shifted_to,
+            } => write!(
+                f,
+                "{} doesn't exist as the clocks go forward, I used {} instead",
+                requested.format("%H:%M"),
+                shifted_to.format("%H:%M %Z")
+            ),
+            Self::NoMeridiem {
+                chosen,
+                alternative,
+            } => write!(
+                f,
+                "no am or pm was given, I used {} rather than {}",
+                chosen.format("%H:%M"),
+                alternative.format("%H:%M")
+            ),
+            Self::NextWeekday {
+                chosen,
+                alternative,
+            } => write!(
+                f,
+                "I read \"next {}\" as {} rather than {}",
+                chosen.format("%A"),
+                chosen.format("%e %b"),
+                alternative.format("%e %b")
+            ),
+            Self::DayMonthOrder {
+                chosen,
+                alternative,
+            } => write!(
+                f,
+                "I read the date as day/month ({}) rather than month/day ({})",
+                chosen.format("%e %b"),
+                alternative.format("%e %b")
+            ),
+            Self::MonthDayOrder {
+                chosen,
+                alternative,
+            } => write!(
+                f,
+                "I read the date as month/day ({}) rather than day/month ({})",
+                chosen.format("%e %b"),
+                alternative.format("%e %b")
+            ),
+        }
+    }
+}
+
+/// The reasons a time expression could not be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// nothing was provided
+    Empty,
+    /// a word was not understood
+    Unrecognised(String),
+    /// the expression did not name a timezone, and there was no default
+    MissingTimezone,
+    /// the expression contained a date which does not exist, e.g. the 31st of February
+    InvalidDate(String),
+    /// the expression contained a time which does not exist, e.g. 25:00
+    InvalidTime(String),
+    /// two parts of the expression contradict each other, e.g. two different timezones
+    Conflicting(String),
+    /// the expression named a day or timezone, but no time
+    NoTime,
+    /// the expression described a time too far away to be represented, e.g. "in 999999999 weeks"
+    OutOfRange,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "no time was provided"),
+            Self::Unrecognised(word) => write!(f, "I don't understand \"{}\"", word),
+            Self::MissingTimezone => write!(
+                f,
+                "no timezone was given, add one (e.g. \"3pm PST\") or register yours with /timezone set"
+            ),
+            Self::InvalidDate(date) => write!(f, "\"{}\" is not a valid date", date),
+            Self::InvalidTime(time) => write!(f, "\"{}\" is not a valid time", time),
+            Self::Conflicting(reason) => write!(f, "{}", reason),
+            Self::NoTime => write!(f, "no time or date was given"),
+            Self::OutOfRange => write!(f, "that time is too far away"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// The day part of an expression
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DateSpec {
+    /// a specific date, `explicit_year` is false if the year was assumed
+    Absolute {
+        /// the date
+        date: NaiveDate,
+        /// whether the year was written out
+        explicit_year: bool,
+    },
+    /// a number of days from today
+    Relative(i64),
+    /// a day of the week, `next` if written as "next friday"
+    Weekday {
+        /// the day of the week
+        day: Weekday,
+        /// whether the day was written as "next <weekday>"
+        next: bool,
+    },
+}
+
+/// The individual parts found in an expression, before they are combined into a single time
+#[derive(Debug, Default)]
+struct Components {
+    /// the day
+    date: Option<DateSpec>,
+    /// the time of day
+    time: Option<NaiveTime>,
+    /// whether the time of day was written unambiguously, e.g. with am/pm, minutes or "noon"
+    explicit_clock: bool,
+    /// an offset from now, e.g. "in 2 hours"
+    duration: Option<Duration>,
+    /// a complete instant, e.g. an ISO 8601 timestamp with an offset
+    instant: Option<DateTime<FixedOffset>>,
+    /// a fixed UTC offset, e.g. "+13:00" or "UTC-5"
+    offset: Option<FixedOffset>,
+    /// a named timezone
+    tz: Option<Tz>,
+    /// decisions made while parsing
+    ambiguities: Vec<Ambiguity>,
+}
+
+impl Components {
+    /// set the date, failing if one has already been set
+    fn set_date(&mut self, date: DateSpec) -> Result<(), ParseError> {
+        if self.date.replace(date).is_some() {
+            return Err(ParseError::Conflicting(String::from(
+                "more than one date was given",
+            )));
+        }
+        Ok(())
+    }
+
+    /// set the time of day, failing if one has already been set
+    fn set_time(&mut self, time: NaiveTime, explicit: bool) -> Result<(), ParseError> {
+        if self.time.replace(time).is_some() {
+            return Err(ParseError::Conflicting(String::from(
+                "more than one time was given",
+            )));
+        }
+        self.explicit_clock = explicit;
+        Ok(())
+    }
+
+    /// set the timezone, failing if a different timezone has already been set
+    fn set_tz(&mut self, tz: Tz) -> Result<(), ParseError> {
+        match self.tz.replace(tz) {
+            Some(existing) if existing != tz => Err(ParseError::Conflicting(format!(
+                "both {} and {} were given as the timezone",
+                existing.name(),
+                tz.name()
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Parse a time expression, see the module documentation for what is supported
+pub fn parse(input: &str, context: &ParseContext) -> Result<ParsedTime, ParseError> {
+    let components = parse_components(input, context.month_first)?;
+    resolve(components, context)
+}
+
+/// A time expression found within a larger piece of text
+#[derive(Debug, Clone, PartialEq)]
+pub struct FoundTime {
+    /// the words which made up the expression
+    pub text: String,
+    /// the parsed expression
+    pub parsed: ParsedTime,
+}
+
+/// the longest run of words which will be considered as a single expression by [find_all]
+const MAX_EXPRESSION_WORDS: usize = 6;
+
+/// Find every absolute time of day mentioned in free text, e.g. a chat message. Only expressions which
+/// clearly name a time ("3pm", "15:30", "noon", or "at 9 EST") are returned, so that ordinary numbers
+/// in a sentence are not mistaken for times.
+pub fn find_all(text: &str, context: &ParseContext) -> Vec<FoundTime> {
+    let words: Vec<&str> = text
+        .split_whitespace()
+        .filter(|w| !w.contains("://") && !w.starts_with("<t:"))
+        .map(|w| {
+            w.trim_matches(|c: char| {
+                matches!(
+                    c,
+                    ',' | '.' | '!' | '?' | ';' | '(' | ')' | '"' | '\'' | '*' | '_' | '`'
+                )
+            })
+        })
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    let mut found: Vec<FoundTime> = Vec::new();
+    let mut i = 0;
+    'outer: while i < words.len() {
+        // most words can't begin an expression, so aren't worth parsing
+        let first = words[i..]
+            .iter()
+            .take(MAX_EXPRESSION_WORDS)
+            .flat_map(|w| w.split(','))
+            .map(|w| w.to_ascii_lowercase())
+            .find(|w| !w.is_empty() && !FILLER_WORDS.contains(&w.as_str()));
+        if !first.is_some_and(|w| can_start_expression(&w)) {
+            i += 1;
+            continue;
+        }
+
+        let longest = MAX_EXPRESSION_WORDS.min(words.len() - i);
+        for len in (1..=longest).rev() {
+            let text = words[i..i + len].join(" ");
+            let components = match parse_components(&text, context.month_first) {
+                Ok(c) => c,
+                Err(_) => continue,
+            };
+
+            let names_time = components.time.is_some()
+                && (components.explicit_clock
+                    || components.tz.is_some()
+                    || components.offset.is_some())
+                && components.duration.is_none();
+            if !names_time {
+                continue;
+            }
+
+            if let Ok(parsed) = resolve(components, context) {
+                // leave out any leading filler, so "at 3pm" is reported as "3pm"
+                let text = words[i..i + len]
+                    .iter()
+                    .skip_while(|w| FILLER_WORDS.contains(&w.to_ascii_lowercase().as_str()))
+                    .copied()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                if !found.iter().any(|f| f.parsed.time == parsed.time) {
+                    found.push(FoundTime { text, parsed });
+                }
+                i += len;
+                continue 'outer;
+            }
+        }
+
+        i += 1;
+    }
+
+    found
+}
+
+/// whether a lowercase word could be the first word of an expression naming a time, besides
+/// any filler, e.g. "3pm", "tomorrow", "next", "nov" or "london"
+fn can_start_expression(word: &str) -> bool {
+    matches!(
+        word,
+        "today"
+            | "tonight"
+            | "tomorrow"
+            | "tmrw"
+            | "tmr"
+            | "yesterday"
+            | "noon"
+            | "midday"
+            | "midnight"
+            | "next"
+    ) || word.starts_with(|c: char| c.is_ascii_digit())
+        || parse_weekday(word).is_some()
+        || parse_month(word).is_some()
+        || parse_offset(word).is_some()
+        || timezones::starts_name(word)
+}
+
+/// split an expression into lowercase words, keeping the original text of each
+fn tokenize(input: &str) -> Vec<(String, &str)> {
+    input
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|w| !w.is_empty())
+        .map(|w| (w.to_ascii_lowercase(), w))
+        .collect()
+}
+
+/// parse a weekday name, full or abbreviated
+pub fn parse_weekday(word: &str) -> Option<Weekday> {
+    match word {
+        "mon" | "monday" => Some(Weekday::Mon),
+        "tue" | "tues" | "tuesday" => Some(Weekday::Tue),
+        "wed" | "weds" | "wednesday" => Some(Weekday::Wed),
+        "thu" | "thur" | "thurs" | "thursday" => Some(Weekday::Thu),
+        "fri" | "friday" => Some(Weekday::Fri),
+        "sat" | "saturday" => Some(Weekday::Sat),
+        "sun" | "sunday" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+/// parse a month name, full or abbreviated, returning the month number
+fn parse_month(word: &str) -> Option<u32> {
+    let months = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    let full = [
+        "january",
+        "february",
+        "march",
+        "april",
+        "may",
+        "june",
+        "july",
+        "august",
+        "september",
+        "october",
+        "november",
+        "december",
+    ];
+    let word = word.trim_end_matches('.');
+    months
+        .iter()
+        .position(|m| *m == word)
+        .or_else(|| full.iter().position(|m| *m == word))
+        .or_else(|| (word == "sept").then_some(8))
+        .map(|i| i as u32 + 1)
+}
+
+/// parse a day of the month, optionally with an ordinal suffix ("3", "3rd", "21st")
+pub fn parse_day_of_month(word: &str) -> Option<u32> {
+    let digits = ["st", "nd", "rd", "th"]
+        .iter()
+        .find_map(|s| word.strip_suffix(s))
+        .unwrap_or(word);
+    match digits.parse::<u32>() {
+        Ok(d) if (1..=31).contains(&d) && digits.len() <= 2 => Some(d),
+        _ => None,
+    }
+}
+
+/// parse a four digit year
+fn parse_year(word: &str) -> Option<i32> {
+    match word.parse::<i32>() {
+        Ok(y) if word.len() == 4 && (1970..=9999).contains(&y) => Some(y),
+        _ => None,
+    }
+}
+
+/// A clock time written in an expression, e.g. "4:30pm" or "16:30"
+#[derive(Debug, Clone, Copy)]
+struct Clock {
+    /// the time of day
+    time: NaiveTime,
+    /// whether am or pm was written
+    has_meridiem: bool,
+    /// whether minutes were written
+    has_minutes: bool,
+    /// whether the hour was written with a leading zero, e.g. "09:00"
+    zero_padded: bool,
+}
+
+/// parse a clock time such as "4:30pm", "4pm", "16:30" or "4:30", with `meridiem` being the am/pm
+/// written as a separate word if there was one.
+fn parse_clock(word: &str, meridiem: Option<&str>) -> Result<Option<Clock>, ParseError> {
+    let (digits, pm) = if let Some(d) = word
+        .strip_suffix("am")
+        .or_else(|| word.strip_suffix("a.m."))
+    {
+        (d, Some(false))
+    } else if let Some(d) = word
+        .strip_suffix("pm")
+        .or_else(|| word.strip_suffix("p.m."))
+    {
+        (d, Some(true))
+    } else {
+        (word, meridiem.map(|m| m.starts_with('p')))
+    };
+
+    if digits.is_empty()
+        || !digits
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == ':' || c == '.')
+    {
+        return Ok(None);
+    }
+
+    let invalid = || ParseError::InvalidTime(word.to_string());
+    // "4.30" is only read as a time with an am/pm, as otherwise it is far more likely to be a number
+    let separator = |c: char| c == ':' || (c == '.' && pm.is_some());
+    let (hour, minute, has_minutes) = match digits.split_once(separator) {
+        Some((h, m)) if m.len() == 2 && !h.is_empty() && h.len() <= 2 => (
+            h.parse::<u32>().map_err(|_| invalid())?,
+            m.parse::<u32>().map_err(|_| invalid())?,
+            true,
+        ),
+        Some(_) => return Ok(None),
+        None if digits.len() <= 2 && digits.chars().all(|c| c.is_ascii_digit()) => {
+            (digits.parse::<u32>().map_err(|_| invalid())?, 0, false)
+        }
+        None => return Ok(None),
+    };
+
+    let hour = match pm {
+        Some(_) if hour == 0 || hour > 12 => return Err(invalid()),
+        Some(pm) => hour % 12 + if pm { 12 } else { 0 },
+        None => hour,
+    };
+
+    let time = NaiveTime::from_hms_opt(hour, minute, 0).ok_or_else(invalid)?;
+    Ok(Some(Clock {
+        time,
+        has_meridiem: pm.is_some(),
+        has_minutes,
+        zero_padded: digits.starts_with('0'),
+    }))
+}
+
+/// parse a time of day on its own, such as "9:30", "9am", "4:30 pm" or "noon"
+pub fn parse_time_of_day(input: &str) -> Option<NaiveTime> {
+    let words = tokenize(input);
+    let clock = match words.as_slice() {
+        [(word, _)] if word == "noon" || word == "midday" => {
+            return NaiveTime::from_hms_opt(12, 0, 0)
+        }
+        [(word, _)] if word == "midnight" => return NaiveTime::from_hms_opt(0, 0, 0),
+        [(word, _)] => parse_clock(word, None),
+        [(word, _), (meridiem, _)] if meridiem == "am" || meridiem == "pm" => {
+            parse_clock(word, Some(meridiem))
+        }
+        _ => return None,
+    };
+    clock.ok().flatten().map(|c| c.time)
+}
+
+/// parse a utc offset such as "+13:00", "-0500", "utc+5" or "gmt-3:30"
+fn parse_offset(word: &str) -> Option<FixedOffset> {
+    let rest = word
+        .strip_prefix("utc")
+        .or_else(|| word.strip_prefix("gmt"))
+        .unwrap_or(word);
+    let (sign, rest) = match rest.chars().next()? {
+        '+' => (1, &rest[1..]),
+        '-' => (-1, &rest[1..]),
+        _ => return None,
+    };
+
+    let (hours, minutes) = match rest.split_once(':') {
+        Some((h, m)) => (h.parse::<i32>().ok()?, m.parse::<i32>().ok()?),
+        None if rest.len() == 4 => (
+            rest[..2].parse::<i32>().ok()?,
+            rest[2..].parse::<i32>().ok()?,
+        ),
+        None if rest.len() <= 2 => (rest.parse::<i32>().ok()?, 0),
+        None => return None,
+    };
+
+    if hours > 14 || minutes >= 60 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+/// parse a compact or single unit duration such as "2h15m", "90min", "3d" or "1w",
+/// returning `None` if the word isn't a duration
+fn parse_compact_duration(word: &str) -> Result<Option<Duration>, ParseError> {
+    let mut total = Duration::zero();
+    let mut rest = word;
+    let mut any = false;
+    while !rest.is_empty() {
+        let digits_end = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        if digits_end == 0 {
+            return Ok(None);
+        }
+        let value = &rest[..digits_end];
+        rest = &rest[digits_end..];
+
+        let unit_end = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let Some(unit) = duration_unit(&rest[..unit_end]) else {
+            return Ok(None);
+        };
+        rest = &rest[unit_end..];
+
+        // the value is all digits, so can only fail to parse by being too large
+        let scaled = value.parse().ok().and_then(|v| scale_duration(unit, v));
+        total = scaled
+            .and_then(|d| total.checked_add(&d))
+            .ok_or(ParseError::OutOfRange)?;
+        any = true;
+    }
+    Ok(any.then_some(total))
+}
+
+/// whether a word is a whole number, e.g. "90" or "-3"
+fn is_integer(word: &str) -> bool {
+    let digits = word.strip_prefix('-').unwrap_or(word);
+    !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// multiply the unit of a duration by a count, or `None` if the result is too large to be represented
+fn scale_duration(unit: Duration, count: i64) -> Option<Duration> {
+    let count = i32::try_from(count).ok()?;
+    let scaled = unit.to_std().ok()?.checked_mul(count.unsigned_abs())?;
+    let scaled = Duration::from_std(scaled).ok()?;
+    Some(if count < 0 { -scaled } else { scaled })
+}
+
+/// parse the unit of a duration, e.g. "h", "hours" or "mins"
+fn duration_unit(word: &str) -> Option<Duration> {
+    match word {
+        "s" | "sec" | "secs" | "second" | "seconds" => Some(Duration::seconds(1)),
+        "m" | "min" | "mins" | "minute" | "minutes" => Some(Duration::minutes(1)),
+        "h" | "hr" | "hrs" | "hour" | "hours" => Some(Duration::hours(1)),
+        "d" | "day" | "days" => Some(Duration::days(1)),
+        "w" | "wk" | "wks" | "week" | "weeks" => Some(Duration::weeks(1)),
+        _ => None,
+    }
+}
+
+/// parse an ISO 8601 date or date-time, returning the local date and time, and the offset if there was one
+fn parse_iso(word: &str) -> Option<(NaiveDate, Option<NaiveTime>, Option<FixedOffset>)> {
+    if let Ok(date) = NaiveDate::parse_from_str(word, "%Y-%m-%d") {
+        return Some((date, None, None));
+    }
+
+    let upper = word.to_ascii_uppercase();
+    let (date, rest) = upper.split_once('T')?;
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+
+    // split the offset from the time, which may be "Z", "+13:00" or "-0500"
+    let (time, offset) = if let Some(time) = rest.strip_suffix('Z') {
+        (time, Some(FixedOffset::east_opt(0)?))
+    } else if let Some(idx) = rest.find(['+', '-']) {
+        (&rest[..idx], Some(parse_offset(&rest[idx..])?))
+    } else {
+        (rest, None)
+    };
+
+    let time = NaiveTime::parse_from_str(time, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M"))
+        .ok()?;
+    Some((date, Some(time), offset))
+}
+
+/// parse a numeric date such as 3/11 or 3/11/2026, as day/month unless `month_first` is set
+fn parse_numeric_date(
+    word: &str,
+    month_first: bool,
+    components: &mut Components,
+) -> Result<Option<DateSpec>, ParseError> {
+    let parts: Vec<&str> = word.split('/').collect();
+    if parts.len() < 2
+        || parts.len() > 3
+        || parts
+            .iter()
+            .any(|p| p.is_empty() || !p.chars().all(|c| c.is_ascii_digit()))
+    {
+        return Ok(None);
+    }
+
+    let first: u32 = parts[0]
+        .parse()
+        .map_err(|_| ParseError::InvalidDate(word.to_string()))?;
+    let second: u32 = parts[1]
+        .parse()
+        .map_err(|_| ParseError::InvalidDate(word.to_string()))?;
+    let year = match parts.get(2) {
+        Some(y) => Some(parse_year(y).ok_or_else(|| ParseError::InvalidDate(word.to_string()))?),
+        None => None,
+    };
+
+    // the year is filled in when resolving, use a leap year so the 29th of february is accepted
+    let date_in = |day: u32, month: u32| NaiveDate::from_ymd_opt(year.unwrap_or(2000), month, day);
+    let (day, month) = if month_first {
+        (second, first)
+    } else {
+        (first, second)
+    };
+    let chosen = date_in(day, month).ok_or_else(|| ParseError::InvalidDate(word.to_string()))?;
+    if day != month {
+        if let Some(alternative) = date_in(month, day) {
+            components.ambiguities.push(if month_first {
+                Ambiguity::MonthDayOrder {
+                    chosen,
+                    alternative,
+                }
+            } else {
+                Ambiguity::DayMonthOrder {
+                    chosen,
+                    alternative,
+                }
+            });
+        }
+    }
+
+    Ok(Some(DateSpec::Absolute {
+        date: chosen,
+        explicit_year: year.is_some(),
+    }))
+}
+
+/// split an expression into its individual parts, reading numeric dates as month/day if `month_first` is set
+fn parse_components(input: &str, month_first: bool) -> Result<Components, ParseError> {
+    let tokens = tokenize(input);
+    if tokens.is_empty() {
+        return Err(ParseError::Empty);
+    }
+
+    let mut c = Components::default();
+    let mut i = 0;
+    while i < tokens.len() {
+        let word = tokens[i].0.as_str();
+        let next = tokens.get(i + 1).map(|t| t.0.as_str());
+        let previous = i.checked_sub(1).map(|j| tokens[j].0.as_str());
+
+        // a duration, "in 2 hours and 15 minutes"
+        if word == "in" {
+            let mut total = Duration::zero();
+            let mut j = i + 1;
+            let mut any = false;
+            while j < tokens.len() {
+                let w = tokens[j].0.as_str();
+                let next_unit = tokens.get(j + 1).and_then(|t| duration_unit(&t.0));
+                let d = if let Some(d) = parse_compact_duration(w)? {
+                    d
+                } else if let (true, Some(unit)) = (is_integer(w), next_unit) {
+                    // the word is an integer, so can only fail to parse by being too large
+                    j += 1;
+                    w.parse()
+                        .ok()
+                        .and_then(|n| scale_duration(unit, n))
+                        .ok_or(ParseError::OutOfRange)?
+                } else if let (true, Some(unit)) = (w == "a" || w == "an", next_unit) {
+                    j += 1;
+                    unit
+                } else if w == "and" && any {
+                    // "2 hours and 15 minutes"
+                    Duration::zero()
+                } else {
+                    break;
+                };
+                total = total.checked_add(&d).ok_or(ParseError::OutOfRange)?;
+                any = true;
+                j += 1;
+            }
+
+            if !any {
+                return Err(ParseError::Unrecognised(tokens[i].1.to_string()));
+            }
+            if c.duration.replace(total).is_some() {
+                return Err(ParseError::Conflicting(String::from(
+                    "more than one duration was given",
+                )));
+            }
+            i = j;
+            continue;
+        }
+
+        if FILLER_WORDS.contains(&word) {
+            i += 1;
+            continue;
+        }
+
+        match word {
+            "now" => {
+                c.duration = Some(Duration::zero());
+                i += 1;
+                continue;
+            }
+            "today" | "tonight" => {
+                c.set_date(DateSpec::Relative(0))?;
+                i += 1;
+                continue;
+            }
+            "tomorrow" | "tmrw" | "tmr" => {
+                c.set_date(DateSpec::Relative(1))?;
+                i += 1;
+                continue;
+            }
+            "yesterday" => {
+                c.set_date(DateSpec::Relative(-1))?;
+                i += 1;
+                continue;
+            }
+            "noon" | "midday" => {
+                c.set_time(NaiveTime::from_hms_opt(12, 0, 0).expect("valid time"), true)?;
+                i += 1;
+                continue;
+            }
+            "midnight" => {
+                c.set_time(NaiveTime::from_hms_opt(0, 0, 0).expect("valid time"), true)?;
+                i += 1;
+                continue;
+            }
+            "next" => {
+                match next.and_then(parse_weekday) {
+                    Some(day) => c.set_date(DateSpec::Weekday { day, next: true })?,
+                    None => return Err(ParseError::Unrecognised(tokens[i].1.to_string())),
+                }
+                i += 2;
+                continue;
+            }
+            _ => {}
+        }
+
+        if let Some(day) = parse_weekday(word) {
+            c.set_date(DateSpec::Weekday { day, next: false })?;
+            i += 1;
+            continue;
+        }
+
+        // "november 3rd 2026" or "nov 3"
+        if let Some(month) = parse_month(word) {
+            if let Some(day) = next.and_then(parse_day_of_month) {
+                let year = tokens.get(i + 2).and_then(|t| parse_year(&t.0));
+                let date =
+                    NaiveDate::from_ymd_opt(year.unwrap_or(2000), month, day).ok_or_else(|| {
+                        ParseError::InvalidDate(format!("{} {}", tokens[i].1, tokens[i + 1].1))
+                    })?;
+                c.set_date(DateSpec::Absolute {
+                    date,
+                    explicit_year: year.is_some(),
+                })?;
+                i += if year.is_some() { 3 } else { 2 };
+                continue;
+            }
+        }
+
+        // "3rd november 2026" or "3 nov"
+        if let (Some(day), Some(month)) = (parse_day_of_month(word), next.and_then(parse_month)) {
+            let year = tokens.get(i + 2).and_then(|t| parse_year(&t.0));
+            let date =
+                NaiveDate::from_ymd_opt(year.unwrap_or(2000), month, day).ok_or_else(|| {
+                    ParseError::InvalidDate(format!("{} {}", tokens[i].1, tokens[i + 1].1))
+                })?;
+            c.set_date(DateSpec::Absolute {
+                date,
+                explicit_year: year.is_some(),
+            })?;
+            i += if year.is_some() { 3 } else { 2 };
+            continue;
+        }
+
+        if let Some((date, time, offset)) = parse_iso(word) {
+            c.set_date(DateSpec::Absolute {
+                date,
+                explicit_year: true,
+            })?;
+            if let Some(time) = time {
+                c.set_time(time, true)?;
+            }
+            if let Some(offset) = offset {
+                let naive = date.and_time(time.unwrap_or_default());
+                c.instant = offset.from_local_datetime(&naive).single();
+            }
+            i += 1;
+            continue;
+        }
+
+        if let Some(date) = parse_numeric_date(word, month_first, &mut c)? {
+            c.set_date(date)?;
+            i += 1;
+            continue;
+        }
+
+        // a clock time, with am/pm possibly written as a separate word
+        let meridiem = next.filter(|n| matches!(*n, "am" | "pm" | "a.m." | "p.m."));
+        if let Some(clock) = parse_clock(word, meridiem)? {
+            // a bare number is only an hour if it follows "at", or is all that was written
+            let is_bare = !clock.has_meridiem && !clock.has_minutes;
+            if is_bare && previous != Some("at") && tokens.len() > 1 {
+                return Err(ParseError::Unrecognised(tokens[i].1.to_string()));
+            }
+
+            let hour = clock.time.hour();
+            if !clock.has_meridiem && !clock.zero_padded && (1..12).contains(&hour) {
+                c.ambiguities.push(Ambiguity::NoMeridiem {
+                    chosen: clock.time,
+                    alternative: clock.time + Duration::hours(12),
+                });
+            }
+
+            c.set_time(clock.time, !is_bare)?;
+            i += if meridiem.is_some() { 2 } else { 1 };
+            continue;
+        }
+
+        if let Some(offset) = parse_offset(word) {
+            if c.offset.replace(offset).is_some() {
+                return Err(ParseError::Conflicting(String::from(
+                    "more than one utc offset was given",
+                )));
+            }
+            i += 1;
+            continue;
+        }
+
+        // finally try a timezone, which may be several words long ("new york")
+        let mut matched = false;
+        for len in (1..=3.min(tokens.len() - i)).rev() {
+            let name = tokens[i..i + len]
+                .iter()
+                .map(|t| t.1)
+                .collect::<Vec<_>>()
+                .join(" ");
+            if let Some(tz) = timezones::resolve(&name) {
+                c.set_tz(tz)?;
+                i += len;
+                matched = true;
+                break;
+            }
+        }
+        if matched {
+            continue;
+        }
+
+        return Err(ParseError::Unrecognised(tokens[i].1.to_string()));
+    }
+
+    Ok(c)
+}
+
+/// find the instant a local time refers to in a timezone, reporting any daylight savings ambiguity
+pub fn resolve_local(
+    tz: Tz,
+    naive: NaiveDateTime,
+    ambiguities: &mut Vec<Ambiguity>,
+) -> Result<DateTime<Tz>, ParseError> {
+    match tz.from_local_datetime(&naive) {
+        LocalResult::Single(time) => Ok(time),
+        LocalResult::Ambiguous(earlier, later) => {
+            ambiguities.push(Ambiguity::DstOverlap { earlier, later });
+            Ok(earlier)
+        }
+        LocalResult::None => {
+            // interpret the time using the offset from before the gap, which moves it forward by the gap's length
+            let before = tz
+                .from_local_datetime(&(naive - Duration::hours(3)))
+                .earliest()
+                .ok_or_else(|| ParseError::InvalidTime(naive.to_string()))?;
+            let offset = before.offset().fix();
+            let shifted_to = offset
+                .from_local_datetime(&naive)
+                .single()
+                .ok_or_else(|| ParseError::InvalidTime(naive.to_string()))?
+                .with_timezone(&tz);
+            ambiguities.push(Ambiguity::DstGap {
+                requested: naive,
+                shifted_to,
+            });
+            Ok(shifted_to)
+        }
+    }
+}
+
+/// combine the parts of an expression into a single point in time
+fn resolve(c: Components, context: &ParseContext) -> Result<ParsedTime, ParseError> {
+    let Components {
+        date,
+        time,
+        duration,
+        instant,
+        offset,
+        tz,
+        mut ambiguities,
+        ..
+    } = c;
+
+    let explicit_timezone = tz.is_some() || offset.is_some() || instant.is_some();
+
+    if let Some(instant) = instant {
+        if offset.is_some() || duration.is_some() {
+            return Err(ParseError::Conflicting(String::from(
+                "a complete timestamp can't be combined with another offset or duration",
+            )));
+        }
+        let zone = tz.or(context.default_tz).unwrap_or(Tz::UTC);
+        return Ok(ParsedTime {
+            time: instant.with_timezone(&zone),
+            explicit_timezone,
+            relative: false,
+            ambiguities,
+        });
+    }
+
+    if let Some(duration) = duration {
+        if date.is_some() || time.is_some() {
+            return Err(ParseError::Conflicting(String::from(
+                "a relative time like \"in 2 hours\" can't be combined with a date or time",
+            )));
+        }
+        let zone = tz.or(context.default_tz).unwrap_or(Tz::UTC);
+        let time = context
+            .now
+            .checked_add_signed(duration)
+            .ok_or(ParseError::OutOfRange)?;
+        return Ok(ParsedTime {
+            time: time.with_timezone(&zone),
+            explicit_timezone,
+            relative: true,
+            ambiguities,
+        });
+    }
+
+    if date.is_none() && time.is_none() {
+        return Err(ParseError::NoTime);
+    }
+
+    // a fixed offset without a named zone, the result is shown in the default timezone (or utc)
+    let zone = match (tz, offset) {
+        (Some(tz), None) => tz,
+        (None, Some(_)) => context.default_tz.unwrap_or(Tz::UTC),
+        (Some(_), Some(_)) => {
+            return Err(ParseError::Conflicting(String::from(
+                "both a timezone and a utc offset were given",
+            )))
+        }
+        (None, None) => context.default_tz.ok_or(ParseError::MissingTimezone)?,
+    };
+
+    let local_now = match offset {
+        Some(offset) => context.now.with_timezone(&offset).naive_local(),
+        None => context.now.with_timezone(&zone).naive_local(),
+    };
+    let today = local_now.date();
+    let time_of_day = time.unwrap_or_default();
+
+    let mut day = match date {
+        Some(DateSpec::Absolute {
+            date,
+            explicit_year: true,
+        }) => date,
+        Some(DateSpec::Absolute {
+            date,
+            explicit_year: false,
+        }) => {
+            let in_year = |year: i32| {
+                NaiveDate::from_ymd_opt(year, date.month(), date.day())
+                    .ok_or_else(|| ParseError::InvalidDate(date.format("%e %B").to_string()))
+            };
+            let this_year = in_year(today.year())?;
+            if context.prefer_future && this_year.and_time(time_of_day) < local_now {
+                in_year(today.year() + 1)?
+            } else {
+                this_year
+            }
+        }
+        Some(DateSpec::Relative(days)) => today + Duration::days(days),
+        Some(DateSpec::Weekday { day, next }) => {
+            let ahead = (7 + day.num_days_from_monday() as i64
+                - today.weekday().num_days_from_monday() as i64)
+                % 7;
+            let mut date = today + Duration::days(ahead);
+            let passed = date.and_time(time_of_day) < local_now;
+            if next || (ahead == 0 && context.prefer_future && passed) {
+                if ahead == 0 {
+                    date += Duration::days(7);
+                }
+                if next {
+                    ambiguities.push(Ambiguity::NextWeekday {
+                        chosen: date,
+                        alternative: date + Duration::days(7),
+                    });
+                }
+            }
+            date
+        }
+        None => today,
+    };
+
+    if date.is_none() && context.prefer_future && day.and_time(time_of_day) <= local_now {
+        day += Duration::days(1);
+    }
+
+    let naive = day.and_time(time_of_day);
+    let time = match offset {
+        Some(offset) => offset
+            .from_local_datetime(&naive)
+            .single()
+            .ok_or_else(|| ParseError::InvalidTime(naive.to_string()))?
+            .with_timezone(&zone),
+        None => resolve_local(zone, naive, &mut ambiguities)?,
+    };
+
+    Ok(ParsedTime {
+        time,
+        explicit_timezone,
+        relative: false,
+        ambiguities,
+    })
+}
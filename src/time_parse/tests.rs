@@ -0,0 +1,412 @@
+//! A corpus of time expressions, all parsed relative to Wednesday the 14th of October 2026 at 12:00 UTC
+//! (13:00 in London, 08:00 in New York and 01:00 on Thursday in Auckland).
+
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use super::{find_all, parse, Ambiguity, ParseContext, ParseError};
+
+/// the instant all expressions are parsed relative to
+fn now() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 10, 14, 12, 0, 0).unwrap()
+}
+
+/// a context in london, where the clocks go back on the 25th of October 2026
+fn london() -> ParseContext {
+    ParseContext::new(now(), Some(Tz::Europe__London))
+}
+
+/// parse an expression which must succeed, returning the instant it describes in utc
+fn utc(input: &str, context: &ParseContext) -> DateTime<Utc> {
+    match parse(input, context) {
+        Ok(parsed) => parsed.time.with_timezone(&Utc),
+        Err(e) => panic!("failed to parse \"{}\": {}", input, e),
+    }
+}
+
+/// construct an instant in utc
+fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(year, month, day, hour, minute, 0)
+        .unwrap()
+}
+
+#[test]
+fn clock_times_in_the_default_timezone() {
+    let cases = [
+        ("3pm", at(2026, 10, 14, 14, 0)),
+        ("3 pm", at(2026, 10, 14, 14, 0)),
+        ("3:45pm", at(2026, 10, 14, 14, 45)),
+        ("3.45pm", at(2026, 10, 14, 14, 45)),
+        ("15:30", at(2026, 10, 14, 14, 30)),
+        ("12am", at(2026, 10, 13, 23, 0)),
+        ("12pm", at(2026, 10, 14, 11, 0)),
+        ("noon", at(2026, 10, 14, 11, 0)),
+        ("midnight", at(2026, 10, 13, 23, 0)),
+        ("at 17:00", at(2026, 10, 14, 16, 0)),
+    ];
+
+    for (input, expected) in cases {
+        assert_eq!(utc(input, &london()), expected, "parsing \"{}\"", input);
+    }
+}
+
+#[test]
+fn explicit_timezones() {
+    let cases = [
+        ("15:30 CET", at(2026, 10, 14, 13, 30)),
+        ("3pm PST", at(2026, 10, 14, 22, 0)),
+        ("3pm America/Denver", at(2026, 10, 14, 21, 0)),
+        ("9am new york", at(2026, 10, 14, 13, 0)),
+        ("noon Auckland time", at(2026, 10, 14, 23, 0)),
+        ("5pm Bangalore", at(2026, 10, 14, 11, 30)),
+        ("3pm utc+5", at(2026, 10, 14, 10, 0)),
+        ("3pm UTC", at(2026, 10, 14, 15, 0)),
+    ];
+
+    for (input, expected) in cases {
+        let parsed = parse(input, &london()).unwrap();
+        assert!(parsed.explicit_timezone, "\"{}\" names a timezone", input);
+        assert_eq!(
+            parsed.time.with_timezone(&Utc),
+            expected,
+            "parsing \"{}\"",
+            input
+        );
+    }
+}
+
+#[test]
+fn days() {
+    let cases = [
+        ("tomorrow 3pm", at(2026, 10, 15, 14, 0)),
+        ("3pm tomorrow", at(2026, 10, 15, 14, 0)),
+        ("yesterday at noon", at(2026, 10, 13, 11, 0)),
+        ("today 18:00", at(2026, 10, 14, 17, 0)),
+        ("friday 10am", at(2026, 10, 16, 9, 0)),
+        ("on sat at 10am", at(2026, 10, 17, 9, 0)),
+        ("wednesday 10am", at(2026, 10, 14, 9, 0)),
+        ("nov 3 10am", at(2026, 11, 3, 10, 0)),
+        ("3rd november 10am", at(2026, 11, 3, 10, 0)),
+        ("November 3rd 2027 10am", at(2027, 11, 3, 10, 0)),
+        ("2026-12-25", at(2026, 12, 25, 0, 0)),
+        ("2026-12-25 9:30am", at(2026, 12, 25, 9, 30)),
+        ("25/12 9:30am", at(2026, 12, 25, 9, 30)),
+        ("25/12/2027 9:30am", at(2027, 12, 25, 9, 30)),
+    ];
+
+    for (input, expected) in cases {
+        assert_eq!(utc(input, &london()), expected, "parsing \"{}\"", input);
+    }
+}
+
+#[test]
+fn relative_times() {
+    let cases = [
+        ("in 2h15m", at(2026, 10, 14, 14, 15)),
+        ("in 2h 15m", at(2026, 10, 14, 14, 15)),
+        ("in 2 hours and 15 minutes", at(2026, 10, 14, 14, 15)),
+        ("in 90 mins", at(2026, 10, 14, 13, 30)),
+        ("in an hour", at(2026, 10, 14, 13, 0)),
+        ("in 3 days", at(2026, 10, 17, 12, 0)),
+        ("in 1w", at(2026, 10, 21, 12, 0)),
+        ("now", at(2026, 10, 14, 12, 0)),
+    ];
+
+    for (input, expected) in cases {
+        let parsed = parse(input, &london()).unwrap();
+        assert!(parsed.relative, "\"{}\" is relative", input);
+        assert_eq!(
+            parsed.time.with_timezone(&Utc),
+            expected,
+            "parsing \"{}\"",
+            input
+        );
+    }
+
+    // relative times do not need a timezone
+    let context = ParseContext::new(now(), None);
+    assert_eq!(utc("in 2h", &context), at(2026, 10, 14, 14, 0));
+}
+
+#[test]
+fn iso_8601_timestamps() {
+    let parsed = parse("2026-11-03T09:00+13:00", &london()).unwrap();
+    assert!(parsed.explicit_timezone);
+    assert_eq!(parsed.time.with_timezone(&Utc), at(2026, 11, 2, 20, 0));
+    // shown in the default timezone
+    assert_eq!(parsed.time.timezone(), Tz::Europe__London);
+
+    assert_eq!(utc("2026-11-03T09:00Z", &london()), at(2026, 11, 3, 9, 0));
+    assert_eq!(
+        utc("2026-11-03T09:00:30-0500", &london()),
+        Utc.with_ymd_and_hms(2026, 11, 3, 14, 0, 30).unwrap()
+    );
+
+    // without an offset, the timezone in the expression (or the default) is used
+    assert_eq!(
+        utc("2026-11-03T09:00 Pacific/Auckland", &london()),
+        at(2026, 11, 2, 20, 0)
+    );
+}
+
+#[test]
+fn the_brief() {
+    let parsed = parse("next friday 4:30pm PST", &london()).unwrap();
+    assert_eq!(parsed.time.with_timezone(&Utc), at(2026, 10, 16, 23, 30));
+    assert_eq!(parsed.time.timezone(), Tz::America__Los_Angeles);
+    assert_eq!(
+        parsed.ambiguities,
+        vec![Ambiguity::NextWeekday {
+            chosen: NaiveDate::from_ymd_opt(2026, 10, 16).unwrap(),
+            alternative: NaiveDate::from_ymd_opt(2026, 10, 23).unwrap(),
+        }]
+    );
+
+    let parsed = parse("tomorrow at 9 EST", &london()).unwrap();
+    assert_eq!(parsed.time.with_timezone(&Utc), at(2026, 10, 15, 13, 0));
+    assert_eq!(
+        parsed.ambiguities,
+        vec![Ambiguity::NoMeridiem {
+            chosen: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            alternative: NaiveTime::from_hms_opt(21, 0, 0).unwrap(),
+        }]
+    );
+}
+
+#[test]
+fn prefer_future() {
+    let context = london().prefer_future();
+
+    // 3pm has not happened yet, but 10am has
+    assert_eq!(utc("3pm", &context), at(2026, 10, 14, 14, 0));
+    assert_eq!(utc("10am", &context), at(2026, 10, 15, 9, 0));
+    assert_eq!(utc("10am", &london()), at(2026, 10, 14, 9, 0));
+
+    // it is wednesday, so "wednesday 10am" is next week
+    assert_eq!(utc("wednesday 10am", &context), at(2026, 10, 21, 9, 0));
+    assert_eq!(utc("wednesday 3pm", &context), at(2026, 10, 14, 14, 0));
+
+    // dates without a year move to next year
+    assert_eq!(
+        utc("1 jan 9am", &context),
+        Utc.with_ymd_and_hms(2027, 1, 1, 9, 0, 0).unwrap()
+    );
+    assert_eq!(
+        utc("1 jan 9am", &london()),
+        Utc.with_ymd_and_hms(2026, 1, 1, 9, 0, 0).unwrap()
+    );
+}
+
+#[test]
+fn daylight_savings_overlap() {
+    // the clocks in london go back from 02:00 BST to 01:00 GMT, so 01:30 happens twice
+    let parsed = parse("25 oct 2026 1:30am", &london()).unwrap();
+    assert_eq!(parsed.time.with_timezone(&Utc), at(2026, 10, 25, 0, 30));
+    match parsed.ambiguities.as_slice() {
+        [Ambiguity::DstOverlap { earlier, later }] => {
+            assert_eq!(earlier.with_timezone(&Utc), at(2026, 10, 25, 0, 30));
+            assert_eq!(later.with_timezone(&Utc), at(2026, 10, 25, 1, 30));
+        }
+        other => panic!("expected an overlap, got {:?}", other),
+    }
+
+    // the same in auckland, where the clocks go back from 03:00 NZDT to 02:00 NZST
+    let parsed = parse("5 april 2026 2:30am Pacific/Auckland", &london()).unwrap();
+    assert!(matches!(
+        parsed.ambiguities.as_slice(),
+        [Ambiguity::DstOverlap { .. }]
+    ));
+}
+
+#[test]
+fn daylight_savings_gap() {
+    // the clocks in london go forward from 01:00 GMT to 02:00 BST, so 01:30 does not exist
+    let parsed = parse("29 march 2026 1:30am", &london()).unwrap();
+    assert_eq!(parsed.time.with_timezone(&Utc), at(2026, 3, 29, 1, 30));
+    assert_eq!(parsed.time.format("%H:%M").to_string(), "02:30");
+    match parsed.ambiguities.as_slice() {
+        [Ambiguity::DstGap {
+            requested,
+            shifted_to,
+        }] => {
+            assert_eq!(requested.format("%H:%M").to_string(), "01:30");
+            assert_eq!(shifted_to, &parsed.time);
+        }
+        other => panic!("expected a gap, got {:?}", other),
+    }
+
+    // in auckland the clocks go forward from 02:00 NZST to 03:00 NZDT
+    let parsed = parse("27 sept 2026 2:15am Auckland", &london()).unwrap();
+    assert_eq!(parsed.time.format("%H:%M").to_string(), "03:15");
+}
+
+#[test]
+fn numeric_dates_report_their_order() {
+    let parsed = parse("3/11 9:00am", &london()).unwrap();
+    assert_eq!(parsed.time.with_timezone(&Utc), at(2026, 11, 3, 9, 0));
+    assert_eq!(
+        parsed.ambiguities,
+        vec![Ambiguity::DayMonthOrder {
+            chosen: NaiveDate::from_ymd_opt(2000, 11, 3).unwrap(),
+            alternative: NaiveDate::from_ymd_opt(2000, 3, 11).unwrap(),
+        }]
+    );
+
+    // 25/12 can only be day/month
+    assert!(!parse("25/12 9am", &london()).unwrap().is_ambiguous());
+}
+
+#[test]
+fn numeric_dates_can_be_month_first() {
+    let us = london().month_first(true);
+    let parsed = parse("3/11 9:00am", &us).unwrap();
+    assert_eq!(parsed.time.with_timezone(&Utc), at(2026, 3, 11, 9, 0));
+    assert_eq!(
+        parsed.ambiguities,
+        vec![Ambiguity::MonthDayOrder {
+            chosen: NaiveDate::from_ymd_opt(2000, 3, 11).unwrap(),
+            alternative: NaiveDate::from_ymd_opt(2000, 11, 3).unwrap(),
+        }]
+    );
+
+    assert!(!parse("12/25 9am", &us).unwrap().is_ambiguous());
+    assert!(parse("25/12 9am", &us).is_err());
+}
+
+#[test]
+fn unambiguous_times_report_nothing() {
+    for input in [
+        "3pm",
+        "15:30",
+        "09:00",
+        "noon",
+        "in 2h",
+        "2026-11-03T09:00Z",
+    ] {
+        let parsed = parse(input, &london()).unwrap();
+        assert!(
+            !parsed.is_ambiguous(),
+            "\"{}\" was ambiguous: {:?}",
+            input,
+            parsed.ambiguities
+        );
+    }
+
+    assert!(parse("9:00", &london()).unwrap().is_ambiguous());
+}
+
+#[test]
+fn errors() {
+    let cases = [
+        ("", ParseError::Empty),
+        ("   ", ParseError::Empty),
+        ("banana", ParseError::Unrecognised(String::from("banana"))),
+        (
+            "3pm banana",
+            ParseError::Unrecognised(String::from("banana")),
+        ),
+        ("25:00", ParseError::InvalidTime(String::from("25:00"))),
+        ("13pm", ParseError::InvalidTime(String::from("13pm"))),
+        ("31 feb", ParseError::InvalidDate(String::from("31 feb"))),
+        ("Auckland", ParseError::NoTime),
+    ];
+    for (input, expected) in cases {
+        assert_eq!(
+            parse(input, &london()),
+            Err(expected),
+            "parsing \"{}\"",
+            input
+        );
+    }
+
+    assert_eq!(
+        parse("3pm", &ParseContext::new(now(), None)),
+        Err(ParseError::MissingTimezone)
+    );
+    assert!(matches!(
+        parse("3pm PST CET", &london()),
+        Err(ParseError::Conflicting(_))
+    ));
+    assert!(matches!(
+        parse("in 2h at 3pm", &london()),
+        Err(ParseError::Conflicting(_))
+    ));
+    assert!(matches!(
+        parse("3pm 4pm", &london()),
+        Err(ParseError::Conflicting(_))
+    ));
+}
+
+#[test]
+fn durations_too_large_to_represent() {
+    let cases = [
+        "in 999999999w",
+        "in 999999999 weeks",
+        "in 99999999999999999999 hours",
+        "in 99999999999999999999m",
+        "in 2147483647 weeks and 2147483647 weeks",
+        "in 10000000w 10000000w",
+    ];
+    for input in cases {
+        assert_eq!(
+            parse(input, &london()),
+            Err(ParseError::OutOfRange),
+            "parsing \"{}\"",
+            input
+        );
+    }
+
+    // large durations which still land on a date are fine
+    assert_eq!(
+        utc("in 5200 weeks", &london()),
+        now() + chrono::Duration::weeks(5200)
+    );
+}
+
+#[test]
+fn finding_times_in_messages() {
+    let found = find_all(
+        "let's meet at 3pm, or tomorrow at 9 EST if that's better. I have 12 apples",
+        &london(),
+    );
+    let found: Vec<(&str, DateTime<Utc>)> = found
+        .iter()
+        .map(|f| (f.text.as_str(), f.parsed.time.with_timezone(&Utc)))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            ("3pm", at(2026, 10, 14, 14, 0)),
+            ("tomorrow at 9 EST", at(2026, 10, 15, 13, 0)),
+        ]
+    );
+
+    // numbers which are not times are ignored
+    for text in [
+        "version 1.10 is out",
+        "the score was 3 to 2",
+        "at 9 we leave",
+        "see https://example.com/12:30",
+        "in 2 hours",
+    ] {
+        assert_eq!(find_all(text, &london()), vec![], "searching \"{}\"", text);
+    }
+
+    // without a timezone, only times which name their own timezone are found
+    let context = ParseContext::new(now(), None);
+    let found = find_all("3pm for me, 15:30 CET for you", &context);
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].text, "15:30 CET");
+}
+
+#[test]
+fn expressions_are_found_wherever_they_start() {
+    // only words which could begin an expression are parsed from, which must still include
+    // timezones of several words, weekdays and offsets
+    let found = find_all(
+        "call me, new york 9am works, or friday at noon, or utc+2 18:00 instead",
+        &london(),
+    );
+    let found = found.iter().map(|f| f.text.as_str()).collect::<Vec<_>>();
+    assert_eq!(found, ["new york 9am", "friday at noon", "utc+2 18:00"]);
+}
@@ -0,0 +1,367 @@
+//! Lookup and fuzzy search over the IANA timezone database, with support for common
+//! city, country and abbreviation aliases (e.g. "Bangalore", "Germany" or "PST").
+
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    sync::LazyLock,
+};
+
+use chrono::{DateTime, Offset, TimeZone};
+use chrono_tz::{Tz, TZ_VARIANTS};
+
+/// common names people use for a timezone, which do not appear in the IANA identifier itself
+const ALIASES: &[(&str, &str)] = &[
+    // abbreviations
+    ("UTC", "UTC"),
+    ("GMT", "Europe/London"),
+    ("BST", "Europe/London"),
+    ("WET", "Europe/Lisbon"),
+    ("CET", "Europe/Berlin"),
+    ("CEST", "Europe/Berlin"),
+    ("EET", "Europe/Helsinki"),
+    ("EEST", "Europe/Helsinki"),
+    ("MSK", "Europe/Moscow"),
+    ("IST", "Asia/Kolkata"),
+    ("PKT", "Asia/Karachi"),
+    ("ICT", "Asia/Bangkok"),
+    ("WIB", "Asia/Jakarta"),
+    ("SGT", "Asia/Singapore"),
+    ("HKT", "Asia/Hong_Kong"),
+    ("JST", "Asia/Tokyo"),
+    ("KST", "Asia/Seoul"),
+    ("AWST", "Australia/Perth"),
+    ("ACST", "Australia/Adelaide"),
+    ("AEST", "Australia/Sydney"),
+    ("AEDT", "Australia/Sydney"),
+    ("NZST", "Pacific/Auckland"),
+    ("NZDT", "Pacific/Auckland"),
+    ("HST", "Pacific/Honolulu"),
+    ("AKST", "America/Anchorage"),
+    ("AKDT", "America/Anchorage"),
+    ("PST", "America/Los_Angeles"),
+    ("PDT", "America/Los_Angeles"),
+    ("PT", "America/Los_Angeles"),
+    ("MST", "America/Denver"),
+    ("MDT", "America/Denver"),
+    ("MT", "America/Denver"),
+    ("CST", "America/Chicago"),
+    ("CDT", "America/Chicago"),
+    ("CT", "America/Chicago"),
+    ("EST", "America/New_York"),
+    ("EDT", "America/New_York"),
+    ("ET", "America/New_York"),
+    ("AST", "America/Halifax"),
+    ("BRT", "America/Sao_Paulo"),
+    ("ART", "America/Argentina/Buenos_Aires"),
+    // regions
+    ("Pacific Time", "America/Los_Angeles"),
+    ("Mountain Time", "America/Denver"),
+    ("Central Time", "America/Chicago"),
+    ("Eastern Time", "America/New_York"),
+    // cities which are not the principal city of their IANA zone
+    ("Bangalore", "Asia/Kolkata"),
+    ("Bengaluru", "Asia/Kolkata"),
+    ("Mumbai", "Asia/Kolkata"),
+    ("Delhi", "Asia/Kolkata"),
+    ("New Delhi", "Asia/Kolkata"),
+    ("Chennai", "Asia/Kolkata"),
+    ("Hyderabad", "Asia/Kolkata"),
+    ("Pune", "Asia/Kolkata"),
+    ("San Francisco", "America/Los_Angeles"),
+    ("Seattle", "America/Los_Angeles"),
+    ("Portland", "America/Los_Angeles"),
+    ("San Diego", "America/Los_Angeles"),
+    ("Las Vegas", "America/Los_Angeles"),
+    ("Salt Lake City", "America/Denver"),
+    ("Austin", "America/Chicago"),
+    ("Dallas", "America/Chicago"),
+    ("Houston", "America/Chicago"),
+    ("Minneapolis", "America/Chicago"),
+    ("Boston", "America/New_York"),
+    ("Washington", "America/New_York"),
+    ("Atlanta", "America/New_York"),
+    ("Miami", "America/New_York"),
+    ("Philadelphia", "America/New_York"),
+    ("Montreal", "America/Toronto"),
+    ("Ottawa", "America/Toronto"),
+    ("Calgary", "America/Edmonton"),
+    ("Rio de Janeiro", "America/Sao_Paulo"),
+    ("Frankfurt", "Europe/Berlin"),
+    ("Munich", "Europe/Berlin"),
+    ("Hamburg", "Europe/Berlin"),
+    ("Barcelona", "Europe/Madrid"),
+    ("Milan", "Europe/Rome"),
+    ("Geneva", "Europe/Zurich"),
+    ("Edinburgh", "Europe/London"),
+    ("Manchester", "Europe/London"),
+    ("Kyiv", "Europe/Kiev"),
+    ("St Petersburg", "Europe/Moscow"),
+    ("Tel Aviv", "Asia/Jerusalem"),
+    ("Abu Dhabi", "Asia/Dubai"),
+    ("Beijing", "Asia/Shanghai"),
+    ("Shenzhen", "Asia/Shanghai"),
+    ("Hanoi", "Asia/Ho_Chi_Minh"),
+    ("Saigon", "Asia/Ho_Chi_Minh"),
+    ("Osaka", "Asia/Tokyo"),
+    ("Canberra", "Australia/Sydney"),
+    ("Wellington", "Pacific/Auckland"),
+    ("Christchurch", "Pacific/Auckland"),
+    ("Cape Town", "Africa/Johannesburg"),
+    // countries with a single (or one dominant) timezone
+    ("United Kingdom", "Europe/London"),
+    ("UK", "Europe/London"),
+    ("England", "Europe/London"),
+    ("Scotland", "Europe/London"),
+    ("Ireland", "Europe/Dublin"),
+    ("Portugal", "Europe/Lisbon"),
+    ("Spain", "Europe/Madrid"),
+    ("France", "Europe/Paris"),
+    ("Belgium", "Europe/Brussels"),
+    ("Netherlands", "Europe/Amsterdam"),
+    ("Germany", "Europe/Berlin"),
+    ("Switzerland", "Europe/Zurich"),
+    ("Austria", "Europe/Vienna"),
+    ("Italy", "Europe/Rome"),
+    ("Denmark", "Europe/Copenhagen"),
+    ("Norway", "Europe/Oslo"),
+    ("Sweden", "Europe/Stockholm"),
+    ("Finland", "Europe/Helsinki"),
+    ("Poland", "Europe/Warsaw"),
+    ("Czechia", "Europe/Prague"),
+    ("Greece", "Europe/Athens"),
+    ("Romania", "Europe/Bucharest"),
+    ("Ukraine", "Europe/Kiev"),
+    ("Turkey", "Europe/Istanbul"),
+    ("Israel", "Asia/Jerusalem"),
+    ("Egypt", "Africa/Cairo"),
+    ("South Africa", "Africa/Johannesburg"),
+    ("Nigeria", "Africa/Lagos"),
+    ("Kenya", "Africa/Nairobi"),
+    ("UAE", "Asia/Dubai"),
+    ("United Arab Emirates", "Asia/Dubai"),
+    ("Pakistan", "Asia/Karachi"),
+    ("India", "Asia/Kolkata"),
+    ("Nepal", "Asia/Kathmandu"),
+    ("Bangladesh", "Asia/Dhaka"),
+    ("Thailand", "Asia/Bangkok"),
+    ("Vietnam", "Asia/Ho_Chi_Minh"),
+    ("Philippines", "Asia/Manila"),
+    ("China", "Asia/Shanghai"),
+    ("Taiwan", "Asia/Taipei"),
+    ("South Korea", "Asia/Seoul"),
+    ("Korea", "Asia/Seoul"),
+    ("Japan", "Asia/Tokyo"),
+    ("New Zealand", "Pacific/Auckland"),
+    ("NZ", "Pacific/Auckland"),
+    ("Argentina", "America/Argentina/Buenos_Aires"),
+    ("Colombia", "America/Bogota"),
+    ("Peru", "America/Lima"),
+    ("Chile", "America/Santiago"),
+    ("Hawaii", "Pacific/Honolulu"),
+    ("Alaska", "America/Anchorage"),
+    ("Arizona", "America/Phoenix"),
+];
+
+/// A single result of a timezone search
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimezoneMatch {
+    /// the timezone that was matched
+    pub tz: Tz,
+    /// the alias which matched the query, if the match was not on the IANA name itself
+    pub alias: Option<&'static str>,
+    /// how well the query matched, lower is better
+    score: u32,
+}
+
+impl TimezoneMatch {
+    /// a human readable label for this match, e.g. "Asia/Kolkata (Bangalore)"
+    pub fn label(&self) -> String {
+        match self.alias {
+            Some(alias) => format!("{} ({})", self.tz.name(), alias),
+            None => self.tz.name().to_string(),
+        }
+    }
+}
+
+/// The names a timezone can be resolved from, normalised once so that resolving a name doesn't
+/// normalise every known name again
+struct Lookup {
+    /// the aliases, and the timezones they stand for
+    aliases: HashMap<String, Tz>,
+    /// the IANA names, e.g. "america/new york"
+    names: HashMap<String, Tz>,
+    /// the final segment of each IANA name, e.g. "new york", mapped to the first zone ending in it
+    segments: HashMap<String, Tz>,
+    /// the first word of every alias, name and segment, e.g. "new"
+    first_words: HashSet<String>,
+}
+
+/// built the first time a name is resolved
+static LOOKUP: LazyLock<Lookup> = LazyLock::new(|| {
+    let mut aliases = HashMap::new();
+    for (alias, name) in ALIASES {
+        if let Ok(tz) = Tz::from_str(name) {
+            aliases.entry(normalise(alias)).or_insert(tz);
+        }
+    }
+    let mut names = HashMap::new();
+    let mut segments = HashMap::new();
+    for tz in TZ_VARIANTS.iter() {
+        let name = normalise(tz.name());
+        if let Some(segment) = name.rsplit('/').next() {
+            segments.entry(segment.to_string()).or_insert(*tz);
+        }
+        names.entry(name).or_insert(*tz);
+    }
+    let first_words = aliases
+        .keys()
+        .chain(names.keys())
+        .chain(segments.keys())
+        .filter_map(|name| name.split(' ').next())
+        .map(str::to_string)
+        .collect();
+    Lookup {
+        aliases,
+        names,
+        segments,
+        first_words,
+    }
+});
+
+/// normalise a string for comparison, so that "new york", "New_York" and "new-york" are equal
+fn normalise(input: &str) -> String {
+    input
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '_' | '-' => ' ',
+            c => c.to_ascii_lowercase(),
+        })
+        .collect()
+}
+
+/// score how well `query` matches `candidate`, both of which must already be normalised.
+/// Returns `None` if the candidate does not match at all, lower scores are better matches.
+fn score(query: &str, candidate: &str) -> Option<u32> {
+    if query.is_empty() {
+        return Some(1000);
+    }
+
+    if candidate == query {
+        return Some(0);
+    }
+
+    // matching the final segment of an identifier exactly (e.g. "denver" for "america/denver")
+    let last_segment = candidate.rsplit('/').next().unwrap_or(candidate);
+    if last_segment == query {
+        return Some(1);
+    }
+
+    if candidate.starts_with(query) {
+        return Some(10 + candidate.len().saturating_sub(query.len()) as u32);
+    }
+
+    // the start of any segment or word
+    let word_start = candidate
+        .split(['/', ' '])
+        .any(|word| word.starts_with(query));
+    if word_start {
+        return Some(100 + candidate.len().saturating_sub(query.len()) as u32);
+    }
+
+    if candidate.contains(query) {
+        return Some(200 + candidate.len().saturating_sub(query.len()) as u32);
+    }
+
+    // finally allow a subsequence match, so that "nyork" still finds "new york"
+    let mut candidate_chars = candidate.chars();
+    let is_subsequence = query
+        .chars()
+        .filter(|c| *c != ' ')
+        .all(|q| candidate_chars.any(|c| c == q));
+    if is_subsequence {
+        return Some(500 + candidate.len().saturating_sub(query.len()) as u32);
+    }
+
+    None
+}
+
+/// Resolve a user provided string into a timezone, accepting IANA names in any case, the final
+/// segment of an IANA name ("denver") and any of the known aliases ("Bangalore", "PST").
+/// Aliases take priority over the legacy fixed-offset zones of the same name, so "EST" is
+/// read as US eastern time (including daylight savings) rather than a fixed UTC-5.
+pub fn resolve(input: &str) -> Option<Tz> {
+    let query = normalise(input);
+    if query.is_empty() {
+        return None;
+    }
+
+    if let Some(tz) = LOOKUP.aliases.get(&query) {
+        return Some(*tz);
+    }
+
+    if let Ok(tz) = Tz::from_str(input.trim()) {
+        return Some(tz);
+    }
+
+    LOOKUP
+        .names
+        .get(&query)
+        .or_else(|| LOOKUP.segments.get(&query))
+        .copied()
+}
+
+/// whether a word could be the first word of a timezone, e.g. "new" for "new york"
+pub fn starts_name(word: &str) -> bool {
+    LOOKUP.first_words.contains(&normalise(word))
+}
+
+/// Search all known timezones and aliases for the provided query, returning at most `limit`
+/// matches ordered from best to worst. Each timezone appears at most once.
+pub fn search(query: &str, limit: usize) -> Vec<TimezoneMatch> {
+    let query = normalise(query);
+
+    let by_name = TZ_VARIANTS.iter().filter_map(|tz| {
+        score(&query, &normalise(tz.name())).map(|score| TimezoneMatch {
+            tz: *tz,
+            alias: None,
+            score,
+        })
+    });
+
+    let by_alias = ALIASES.iter().filter_map(|(alias, name)| {
+        let tz = Tz::from_str(name).ok()?;
+        // an alias is very slightly worse than the same quality match against a real name
+        score(&query, &normalise(alias)).map(|score| TimezoneMatch {
+            tz,
+            alias: Some(*alias),
+            score: score + 1,
+        })
+    });
+
+    let mut matches: Vec<TimezoneMatch> = by_name.chain(by_alias).collect();
+    matches.sort_by(|a, b| {
+        a.score
+            .cmp(&b.score)
+            .then_with(|| a.tz.name().cmp(b.tz.name()))
+    });
+
+    let mut seen = HashSet::new();
+    matches.retain(|m| seen.insert(m.tz));
+    matches.truncate(limit);
+    matches
+}
+
+/// format the UTC offset of a point in time, e.g. "UTC+05:30" or "UTC-07:00"
+pub fn format_utc_offset<T: TimeZone>(time: &DateTime<T>) -> String {
+    let seconds = time.offset().fix().local_minus_utc();
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let seconds = seconds.abs();
+    format!(
+        "UTC{}{:02}:{:02}",
+        sign,
+        seconds / 3600,
+        (seconds % 3600) / 60
+    )
+}
@@ -0,0 +1,498 @@
+//! Derive macros for the application commands of the time bot.
+//!
+//! `#[derive(SlashCommand)]` on a struct with named fields registers one command option per
+//! field, and parses those options back out of a `CommandInteraction`, or out of the options of a
+//! subcommand. Each field is configured with an `#[option(...)]` attribute:
+//!
+//! ```ignore
+//! #[derive(SlashCommand)]
+//! pub struct RollCommand<'a> {
+//!     #[option(description = "The dice to roll", autocomplete)]
+//!     dice: &'a str,
+//!     #[option(description = "How many times to roll", min = 1, max = 10)]
+//!     times: Option<i64>,
+//!     #[option(description = "Show every roll", default)]
+//!     verbose: bool,
+//!     #[option(description = "The kind of dice", choice(name = "Six sided", value = 6))]
+//!     sides: Option<i64>,
+//!     #[option(description = "The colour of the dice", choices = Colour::choices())]
+//!     colour: Option<&'a str>,
+//!     #[option(description = "The channel to roll in", channel_types(Text, PublicThread))]
+//!     channel: Option<ChannelId>,
+//!     #[option(description = "A player", repeat(count = 4, description = "Another player"))]
+//!     players: Vec<UserId>,
+//! }
+//! ```
+//!
+//! Fields are required unless they are an `Option`, or marked `default` to fall back to
+//! `Default::default()`. The type of each option comes from the field's `OptionValue` impl.
+//! A `Vec` field is registered `count` times, as "players", "players2", "players3"..., of which
+//! only the first is required and the rest come after every other option, and is read from every
+//! one provided.
+//! `choices` adds the `(name, value)` pairs of any iterator, where `choice` adds a single choice
+//! which is checked when the command is compiled.
+//! Integer bounds which are negative, or integer choices which don't fit in an `i32`, fail to
+//! compile, as serenity only accepts integer bounds as a `u64` and integer choices as an `i32`.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{quote, quote_spanned};
+use syn::{
+    parse_macro_input, parse_quote, spanned::Spanned, Data, DeriveInput, Expr, Field, Fields,
+    GenericArgument, Ident, Lifetime, LifetimeParam, LitInt, LitStr, PathArguments, Type,
+};
+
+/// the settings of a single option, read from the `#[option(...)]` attributes of its field
+#[derive(Default)]
+struct OptionSettings {
+    /// the name of the option, defaults to the name of the field
+    name: Option<LitStr>,
+    /// the description of the option, which discord requires
+    description: Option<LitStr>,
+    /// whether a missing option falls back to `Default::default()`
+    default: bool,
+    /// whether the option offers autocomplete suggestions
+    autocomplete: bool,
+    /// the smallest number accepted
+    min: Option<Expr>,
+    /// the largest number accepted
+    max: Option<Expr>,
+    /// the shortest text accepted
+    min_length: Option<LitInt>,
+    /// the longest text accepted
+    max_length: Option<LitInt>,
+    /// the only values accepted, as pairs of the name shown and the value
+    choices: Vec<(Expr, Expr)>,
+    /// an iterator of further `(name, value)` choices, for choices built when registering
+    choices_from: Option<Expr>,
+    /// the kinds of channel accepted, by their `ChannelType` variant
+    channel_types: Vec<Ident>,
+    /// how many times a `Vec` field's option is registered
+    repeat_count: Option<Expr>,
+    /// the description of every option after the first of a `Vec` field
+    repeat_description: Option<LitStr>,
+}
+
+impl OptionSettings {
+    /// read the settings of a field
+    fn parse(field: &Field) -> syn::Result<Self> {
+        let mut settings = Self::default();
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("option")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("name") {
+                    settings.name = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("description") {
+                    settings.description = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("default") {
+                    settings.default = true;
+                } else if meta.path.is_ident("autocomplete") {
+                    settings.autocomplete = true;
+                } else if meta.path.is_ident("min") {
+                    settings.min = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("max") {
+                    settings.max = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("min_length") {
+                    settings.min_length = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("max_length") {
+                    settings.max_length = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("choices") {
+                    settings.choices_from = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("channel_types") {
+                    meta.parse_nested_meta(|kind| {
+                        settings
+                            .channel_types
+                            .push(kind.path.require_ident()?.clone());
+                        Ok(())
+                    })?;
+                } else if meta.path.is_ident("repeat") {
+                    meta.parse_nested_meta(|repeat| {
+                        if repeat.path.is_ident("count") {
+                            settings.repeat_count = Some(repeat.value()?.parse()?);
+                        } else if repeat.path.is_ident("description") {
+                            settings.repeat_description = Some(repeat.value()?.parse()?);
+                        } else {
+                            return Err(repeat.error("expected `count` or `description`"));
+                        }
+                        Ok(())
+                    })?;
+                    if settings.repeat_count.is_none() {
+                        return Err(meta.error("a repeated option needs a `count`"));
+                    }
+                } else if meta.path.is_ident("choice") {
+                    let mut name: Option<Expr> = None;
+                    let mut value: Option<Expr> = None;
+                    meta.parse_nested_meta(|choice| {
+                        if choice.path.is_ident("name") {
+                            name = Some(choice.value()?.parse()?);
+                        } else if choice.path.is_ident("value") {
+                            value = Some(choice.value()?.parse()?);
+                        } else {
+                            return Err(choice.error("expected `name` or `value`"));
+                        }
+                        Ok(())
+                    })?;
+                    match (name, value) {
+                        (Some(name), Some(value)) => settings.choices.push((name, value)),
+                        _ => return Err(meta.error("a choice needs both a `name` and a `value`")),
+                    }
+                } else {
+                    return Err(meta.error("unknown option setting"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(settings)
+    }
+}
+
+/// the type inside a generic type such as `Option` or `Vec`, if the provided type is one
+fn generic_inner<'a>(ty: &'a Type, generic: &str) -> Option<&'a Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != generic {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+            GenericArgument::Type(inner) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// whether the provided type is `i64`, the type of integer options
+fn is_integer(ty: &Type) -> bool {
+    matches!(ty, Type::Path(path) if path.path.is_ident("i64"))
+}
+
+/// an integer bound, checked to be positive when the command is compiled, as serenity only
+/// accepts integer bounds as a `u64`
+fn integer_bound(bound: &Expr) -> TokenStream2 {
+    quote_spanned! {bound.span()=>
+        {
+            const BOUND: u64 = {
+                let bound: i64 = #bound;
+                assert!(bound >= 0, "integer bounds can't be negative");
+                bound as u64
+            };
+            BOUND
+        }
+    }
+}
+
+/// an integer choice, checked to fit in an i32 when the command is compiled, as serenity only
+/// accepts integer choices as an `i32`
+fn integer_choice(choice: &Expr) -> TokenStream2 {
+    quote_spanned! {choice.span()=>
+        {
+            const CHOICE: i32 = {
+                let choice: i64 = #choice;
+                assert!(choice as i32 as i64 == choice, "integer choices must fit in an i32");
+                choice as i32
+            };
+            CHOICE
+        }
+    }
+}
+
+/// when an option is registered, discord requires every required option to come before the
+/// optional ones, and the repeats of a `Vec` field come last so the other options are seen first
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Registered {
+    /// an option which must be provided
+    Required,
+    /// an option which may be left out
+    Optional,
+    /// the second and later options of a `Vec` field
+    Repeated,
+}
+
+/// a single field of a command, and the option it is read from
+struct CommandOption<'a> {
+    /// the field the option is stored in
+    field: &'a Field,
+    /// the settings from the field's attributes
+    settings: OptionSettings,
+    /// the type of the option's value, without any `Option` or `Vec`
+    value_type: &'a Type,
+    /// whether the option must be provided
+    required: bool,
+    /// whether the field is a `Vec` read from several options
+    repeated: bool,
+}
+
+impl<'a> CommandOption<'a> {
+    /// read an option from its field
+    fn new(field: &'a Field) -> syn::Result<Self> {
+        let settings = OptionSettings::parse(field)?;
+        if settings.description.is_none() {
+            return Err(syn::Error::new_spanned(
+                field,
+                "every option needs a description, e.g. #[option(description = \"...\")]",
+            ));
+        }
+        let optional = generic_inner(&field.ty, "Option");
+        let repeated = generic_inner(&field.ty, "Vec");
+        match (&repeated, &settings.repeat_count) {
+            (Some(_), None) => {
+                return Err(syn::Error::new_spanned(
+                    field,
+                    "a Vec is read from several options, e.g. #[option(repeat(count = 5))]",
+                ))
+            }
+            (None, Some(count)) => {
+                return Err(syn::Error::new_spanned(
+                    count,
+                    "only a Vec can be read from several options",
+                ))
+            }
+            _ => {}
+        }
+        Ok(Self {
+            field,
+            required: optional.is_none() && !settings.default,
+            value_type: optional.or(repeated).unwrap_or(&field.ty),
+            repeated: repeated.is_some(),
+            settings,
+        })
+    }
+
+    /// the name of the option, as seen by discord
+    fn name(&self) -> LitStr {
+        self.settings.name.clone().unwrap_or_else(|| {
+            let ident = self.field.ident.as_ref().expect("fields are named");
+            LitStr::new(&ident.to_string(), ident.span())
+        })
+    }
+
+    /// the expression reading the option from `options`
+    fn parse(&self) -> TokenStream2 {
+        let name = self.name();
+        let ty = &self.value_type;
+        if let Some(count) = self
+            .settings
+            .repeat_count
+            .as_ref()
+            .filter(|_| self.repeated)
+        {
+            let first = match self.required {
+                true => quote! { values.push(options.required::<#ty>(#name)?); },
+                false => quote! { values.extend(options.optional::<#ty>(#name)?); },
+            };
+            quote! {
+                {
+                    let mut values = Vec::new();
+                    #first
+                    for n in 2..=#count {
+                        values.extend(options.optional::<#ty>(&format!("{}{}", #name, n))?);
+                    }
+                    values
+                }
+            }
+        } else if self.required {
+            quote! { options.required::<#ty>(#name)? }
+        } else if self.settings.default {
+            quote! { options.optional::<#ty>(#name)?.unwrap_or_default() }
+        } else {
+            quote! { options.optional::<#ty>(#name)? }
+        }
+    }
+
+    /// the statements adding the option to `options`, each with the order it is registered in,
+    /// as a `Vec` field adds its first option in the usual place and the rest after every other
+    fn register(&self) -> Vec<(Registered, TokenStream2)> {
+        let name = self.name();
+        let description = &self.settings.description;
+        let first = self.build(quote! { #name }, description.as_ref(), self.required);
+        let order = match self.required {
+            true => Registered::Required,
+            false => Registered::Optional,
+        };
+        let mut statements = vec![(order, quote! { options.push(#first); })];
+
+        if let Some(count) = self
+            .settings
+            .repeat_count
+            .as_ref()
+            .filter(|_| self.repeated)
+        {
+            let description = self
+                .settings
+                .repeat_description
+                .as_ref()
+                .or(description.as_ref());
+            let rest = self.build(quote! { format!("{}{}", #name, n) }, description, false);
+            statements.push((
+                Registered::Repeated,
+                quote! { options.extend((2..=#count).map(|n| #rest)); },
+            ));
+        }
+        statements
+    }
+
+    /// the expression building a single option to register with discord
+    fn build(
+        &self,
+        name: TokenStream2,
+        description: Option<&LitStr>,
+        required: bool,
+    ) -> TokenStream2 {
+        let ty = &self.value_type;
+        let options = quote! { crate::discord_bot::commands::options };
+
+        let value = |value: &Expr, integer: fn(&Expr) -> TokenStream2| match is_integer(ty) {
+            true => integer(value),
+            false => quote! { #value },
+        };
+
+        let mut setters = Vec::new();
+        if required {
+            setters.push(quote! { let option = option.required(true); });
+        }
+        if self.settings.autocomplete {
+            setters.push(quote! { let option = option.set_autocomplete(true); });
+        }
+        if let Some(min) = &self.settings.min {
+            let min = value(min, integer_bound);
+            setters
+                .push(quote! { let option = <#ty as #options::OptionBounds>::min(option, #min); });
+        }
+        if let Some(max) = &self.settings.max {
+            let max = value(max, integer_bound);
+            setters
+                .push(quote! { let option = <#ty as #options::OptionBounds>::max(option, #max); });
+        }
+        if let Some(length) = &self.settings.min_length {
+            setters.push(quote! { let option = option.min_length(#length); });
+        }
+        if let Some(length) = &self.settings.max_length {
+            setters.push(quote! { let option = option.max_length(#length); });
+        }
+        for (choice, choice_value) in self.settings.choices.iter() {
+            let value = value(choice_value, integer_choice);
+            setters.push(quote! {
+                let option = <#ty as #options::OptionChoice>::choice(option, #choice, #value);
+            });
+        }
+        if let Some(choices) = &self.settings.choices_from {
+            setters.push(quote! {
+                let option = ::core::iter::IntoIterator::into_iter(#choices)
+                    .fold(option, |option, (name, value)| {
+                        <#ty as #options::OptionChoice>::choice(option, name, value)
+                    });
+            });
+        }
+        if !self.settings.channel_types.is_empty() {
+            let kinds = &self.settings.channel_types;
+            setters.push(quote! {
+                let option = option.channel_types(vec![#(::serenity::all::ChannelType::#kinds),*]);
+            });
+        }
+
+        quote! {
+            {
+                let option = ::serenity::builder::CreateCommandOption::new(
+                    <#ty as #options::OptionValue<'_>>::OPTION_TYPE,
+                    #name,
+                    #description,
+                );
+                #(#setters)*
+                option
+            }
+        }
+    }
+}
+
+/// generate the option registration and parsing for a command, see the crate documentation
+#[proc_macro_derive(SlashCommand, attributes(option))]
+pub fn derive_slash_command(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// expand the derive, failing with an error pointing at the problem
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            Fields::Unit => return Err(syn::Error::new_spanned(
+                &input.ident,
+                "SlashCommand needs named fields, commands without options don't need to derive it",
+            )),
+            Fields::Unnamed(_) => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "SlashCommand needs named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "SlashCommand can only be derived for structs",
+            ))
+        }
+    };
+    let options = fields
+        .iter()
+        .map(CommandOption::new)
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let ident = &input.ident;
+    let (_, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    // the options are parsed for as long as the interaction lives, which is the lifetime of the
+    // command if it borrows from the interaction
+    let mut parse_generics = input.generics.clone();
+    let lifetime = match input.generics.lifetimes().next() {
+        Some(param) => param.lifetime.clone(),
+        None => {
+            let lifetime = Lifetime::new("'interaction", Span::call_site());
+            parse_generics
+                .params
+                .insert(0, LifetimeParam::new(lifetime.clone()).into());
+            lifetime
+        }
+    };
+    let (parse_impl_generics, _, _) = parse_generics.split_for_impl();
+
+    let field_names = options.iter().map(|o| &o.field.ident);
+    let parsers = options.iter().map(CommandOption::parse);
+
+    let mut registered = options
+        .iter()
+        .flat_map(CommandOption::register)
+        .collect::<Vec<_>>();
+    registered.sort_by_key(|(order, _)| *order);
+    let registrations = registered.into_iter().map(|(_, statement)| statement);
+
+    let module = quote! { crate::discord_bot::commands::options };
+    let interaction: Type = parse_quote! { &#lifetime ::serenity::all::CommandInteraction };
+    Ok(quote! {
+        impl #parse_impl_generics #module::SlashCommand<#lifetime> for #ident #ty_generics #where_clause {
+            fn options() -> Vec<::serenity::builder::CreateCommandOption> {
+                let mut options = Vec::new();
+                #(#registrations)*
+                options
+            }
+
+            fn parse(options: #module::Options<#lifetime>) -> Result<Self, String> {
+                Ok(Self {
+                    #(#field_names: #parsers,)*
+                })
+            }
+        }
+
+        impl #parse_impl_generics ::core::convert::TryFrom<#interaction> for #ident #ty_generics #where_clause {
+            type Error = String;
+            fn try_from(interaction: #interaction) -> Result<Self, Self::Error> {
+                <Self as #module::SlashCommand<#lifetime>>::parse(#module::Options::of(interaction))
+            }
+        }
+    })
+}